@@ -0,0 +1,658 @@
+//! Helpers for reading and writing messages via `futures_io::{AsyncRead, AsyncWrite}`.
+//!
+//! This module is available only if the `futures-io` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate bytecodec;
+//! # extern crate futures;
+//! # extern crate httpcodec;
+//! use bytecodec::bytes::BytesEncoder;
+//! use futures::executor::block_on;
+//! use futures::io::Cursor;
+//! use httpcodec::async_io::{AsyncReader, AsyncWriter};
+//! use httpcodec::{BodyEncoder, HttpVersion, NoBodyDecoder, ReasonPhrase, RequestDecoder,
+//!                 Response, ResponseEncoder, StatusCode};
+//!
+//! # fn main() {
+//! let input = Cursor::new(b"GET /foo HTTP/1.1\r\n\r\nGET /bar HTTP/1.1\r\n\r\n".to_vec());
+//! let decoder = RequestDecoder::new(NoBodyDecoder);
+//! let mut reader = AsyncReader::new(input, decoder);
+//!
+//! let request = block_on(reader.read_message()).unwrap().unwrap();
+//! assert_eq!(request.request_target().as_str(), "/foo");
+//! let request = block_on(reader.read_message()).unwrap().unwrap();
+//! assert_eq!(request.request_target().as_str(), "/bar");
+//! assert!(block_on(reader.read_message()).unwrap().is_none());
+//!
+//! let encoder = ResponseEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+//! let mut writer = AsyncWriter::new(Cursor::new(Vec::new()), encoder);
+//! let response = Response::new(
+//!     HttpVersion::V1_1,
+//!     StatusCode::new(200).unwrap(),
+//!     ReasonPhrase::new("OK").unwrap(),
+//!     b"baz",
+//! );
+//! block_on(writer.write_message(response)).unwrap();
+//! assert_eq!(
+//!     writer.writer_ref().get_ref(),
+//!     b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbaz"
+//! );
+//! # }
+//! ```
+use bytecodec::{Eos, Error, ErrorKind, Result};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_timer::Delay;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use message::{MessageDecode, MessageEncode};
+
+const DEFAULT_BUFFER_SIZE: usize = 8192;
+
+/// Reader that decodes messages from an `AsyncRead` stream.
+///
+/// The internal buffer is reused across messages,
+/// so bytes that follow a message (e.g., pipelined requests on a keep-alive connection)
+/// are kept for the next `read_message` call.
+#[derive(Debug)]
+pub struct AsyncReader<R, D> {
+    reader: R,
+    decoder: D,
+    buf: Vec<u8>,
+    head: usize,
+    tail: usize,
+    eos: bool,
+    consumed: u64,
+    timeout: Option<Duration>,
+    max_message_size: Option<u64>,
+}
+impl<R: AsyncRead + Unpin, D: MessageDecode> AsyncReader<R, D> {
+    /// Makes a new `AsyncReader` instance.
+    pub fn new(reader: R, decoder: D) -> Self {
+        Self::with_buffer_size(reader, decoder, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new `AsyncReader` instance that has a read buffer of the given size.
+    pub fn with_buffer_size(reader: R, decoder: D, buffer_size: usize) -> Self {
+        AsyncReader {
+            reader,
+            decoder,
+            buf: vec![0; buffer_size],
+            head: 0,
+            tail: 0,
+            eos: false,
+            consumed: 0,
+            timeout: None,
+            max_message_size: None,
+        }
+    }
+
+    /// Sets the timeout of each `read_message` call.
+    ///
+    /// The default value is `None` (i.e., no timeout).
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the maximum number of bytes that a message can consume.
+    ///
+    /// The default value is `None` (i.e., unlimited).
+    pub fn set_max_message_size(&mut self, size: Option<u64>) {
+        self.max_message_size = size;
+    }
+
+    /// Returns a future that reads a message from the stream.
+    ///
+    /// If the stream reaches EOS at the boundary of messages, the future will result in `Ok(None)`.
+    ///
+    /// The future also results in `Ok(None)` when the decoder stops consuming bytes
+    /// before completing a message, without reading any more bytes from the stream:
+    ///
+    /// - If the decoder is suspended (e.g., `RequestDecoder` waiting for the application
+    ///   to send `100 Continue`), `decoder_ref().is_suspended()` returns `true`.
+    ///   The rest of the message can be read by calling `read_message` again after resuming the decoder.
+    /// - If a protocol switch has taken place, `decoder_ref().switched_protocol()` returns it.
+    ///   The bytes following the switch are available via `buffered_bytes`.
+    ///
+    /// # Errors
+    ///
+    /// If the stream reaches EOS in the middle of a message,
+    /// an `ErrorKind::UnexpectedEos` error will be returned.
+    ///
+    /// If the message exceeds the maximum size,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    ///
+    /// If the timeout expires, an `ErrorKind::Other` error
+    /// caused by an `std::io::ErrorKind::TimedOut` error will be returned.
+    pub fn read_message(&mut self) -> ReadMessage<'_, R, D> {
+        let delay = self.timeout.map(Delay::new);
+        ReadMessage {
+            reader: self,
+            delay,
+        }
+    }
+
+    /// Returns the bytes that have been read from the stream but not decoded yet.
+    pub fn buffered_bytes(&self) -> &[u8] {
+        &self.buf[self.head..self.tail]
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn reader_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn reader_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Returns a reference to the decoder.
+    pub fn decoder_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Takes ownership of the instance, and returns the underlying reader and decoder.
+    pub fn into_inner(self) -> (R, D) {
+        (self.reader, self.decoder)
+    }
+
+    fn poll_read_message(
+        &mut self,
+        cx: &mut Context,
+        delay: &mut Option<Delay>,
+    ) -> Result<Poll<Option<D::Item>>> {
+        loop {
+            if self.decoder.switched_protocol().is_some() {
+                // The rest of the stream is not for the decoder
+                self.consumed = 0;
+                return Ok(Poll::Ready(None));
+            }
+            if self.eos && self.consumed == 0 && self.head == self.tail {
+                // The stream has reached EOS at the boundary of messages
+                return Ok(Poll::Ready(None));
+            }
+
+            let size = track!(self
+                .decoder
+                .decode(&self.buf[self.head..self.tail], Eos::new(self.eos)))?;
+            self.head += size;
+            self.consumed += size as u64;
+            if let Some(max) = self.max_message_size {
+                track_assert!(self.consumed <= max, ErrorKind::InvalidInput; self.consumed, max);
+            }
+            if self.decoder.is_idle() {
+                let item = track!(self.decoder.finish_decoding())?;
+                self.consumed = 0;
+                return Ok(Poll::Ready(Some(item)));
+            }
+            if self.decoder.is_suspended() {
+                return Ok(Poll::Ready(None));
+            }
+            if self.decoder.switched_protocol().is_some() {
+                continue;
+            }
+            track_assert!(!self.eos, ErrorKind::UnexpectedEos; self.consumed);
+
+            if self.head == self.tail {
+                self.head = 0;
+                self.tail = 0;
+            } else if self.tail == self.buf.len() {
+                self.buf.copy_within(self.head..self.tail, 0);
+                self.tail -= self.head;
+                self.head = 0;
+            }
+            track_assert_ne!(
+                self.tail,
+                self.buf.len(),
+                ErrorKind::Other,
+                "The decoder does not consume the buffered bytes"
+            );
+
+            match Pin::new(&mut self.reader).poll_read(cx, &mut self.buf[self.tail..]) {
+                Poll::Ready(Ok(0)) => self.eos = true,
+                Poll::Ready(Ok(n)) => self.tail += n,
+                Poll::Ready(Err(e)) => {
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(track!(Error::from(e)));
+                    }
+                }
+                Poll::Pending => {
+                    track!(poll_timeout(cx, delay))?;
+                    return Ok(Poll::Pending);
+                }
+            }
+        }
+    }
+}
+
+/// Future that reads a message from an `AsyncReader`.
+///
+/// This is created by calling `AsyncReader::read_message` method.
+#[derive(Debug)]
+pub struct ReadMessage<'a, R: 'a, D: 'a> {
+    reader: &'a mut AsyncReader<R, D>,
+    delay: Option<Delay>,
+}
+impl<'a, R: AsyncRead + Unpin, D: MessageDecode> Future for ReadMessage<'a, R, D> {
+    type Output = Result<Option<D::Item>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.reader.poll_read_message(cx, &mut this.delay) {
+            Ok(poll) => poll.map(Ok),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+/// Writer that encodes messages to an `AsyncWrite` stream.
+///
+/// The internal buffer is reused across messages.
+#[derive(Debug)]
+pub struct AsyncWriter<W, E> {
+    writer: W,
+    encoder: E,
+    buf: Vec<u8>,
+    head: usize,
+    tail: usize,
+    timeout: Option<Duration>,
+}
+impl<W: AsyncWrite + Unpin, E: MessageEncode> AsyncWriter<W, E> {
+    /// Makes a new `AsyncWriter` instance.
+    pub fn new(writer: W, encoder: E) -> Self {
+        Self::with_buffer_size(writer, encoder, DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Makes a new `AsyncWriter` instance that has a write buffer of the given size.
+    pub fn with_buffer_size(writer: W, encoder: E, buffer_size: usize) -> Self {
+        AsyncWriter {
+            writer,
+            encoder,
+            buf: vec![0; buffer_size],
+            head: 0,
+            tail: 0,
+            timeout: None,
+        }
+    }
+
+    /// Sets the timeout of each `write_message` and `flush` call.
+    ///
+    /// The default value is `None` (i.e., no timeout).
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns a future that writes the given message to the stream.
+    ///
+    /// If the encoder is suspended in the middle of the message
+    /// (e.g., `RequestEncoder` waiting for a `100 Continue` response),
+    /// the future completes after the bytes encoded so far are flushed.
+    /// In that case, `encoder_ref().is_suspended()` returns `true`, and
+    /// the rest of the message can be written by calling `flush` after resuming the encoder.
+    ///
+    /// If the body is not ready yet (e.g., `StreamingBodyEncoder` waiting for the next chunk),
+    /// the future stays pending until it is.
+    /// Because body encoders cannot notify the readiness, the task is woken up immediately
+    /// (i.e., the encoder is polled repeatedly) in that case.
+    ///
+    /// # Errors
+    ///
+    /// If the timeout expires, an `ErrorKind::Other` error
+    /// caused by an `std::io::ErrorKind::TimedOut` error will be returned.
+    pub fn write_message(&mut self, item: E::Item) -> WriteMessage<'_, W, E> {
+        let error = track!(self.encoder.start_encoding(item)).err();
+        self.flush_with_error(error)
+    }
+
+    /// Returns a future that writes the remaining part of the current message to the stream.
+    pub fn flush(&mut self) -> WriteMessage<'_, W, E> {
+        self.flush_with_error(None)
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn writer_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Returns a reference to the encoder.
+    pub fn encoder_ref(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the encoder.
+    pub fn encoder_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    /// Takes ownership of the instance, and returns the underlying writer and encoder.
+    pub fn into_inner(self) -> (W, E) {
+        (self.writer, self.encoder)
+    }
+
+    fn flush_with_error(&mut self, error: Option<Error>) -> WriteMessage<'_, W, E> {
+        let delay = self.timeout.map(Delay::new);
+        WriteMessage {
+            writer: self,
+            delay,
+            error,
+        }
+    }
+
+    fn poll_write_message(
+        &mut self,
+        cx: &mut Context,
+        delay: &mut Option<Delay>,
+    ) -> Result<Poll<()>> {
+        loop {
+            if self.head == self.tail {
+                self.head = 0;
+                self.tail = track!(self.encoder.encode(&mut self.buf, Eos::new(false)))?;
+                if self.tail == 0 {
+                    if self.encoder.is_idle() || self.encoder.is_suspended() {
+                        break;
+                    }
+
+                    // The body is not ready yet, and the encoder has no way to notify us
+                    if let Poll::Ready(result) = Pin::new(&mut self.writer).poll_flush(cx) {
+                        track!(result.map_err(Error::from))?;
+                    }
+                    track!(poll_timeout(cx, delay))?;
+                    cx.waker().wake_by_ref();
+                    return Ok(Poll::Pending);
+                }
+            }
+
+            match Pin::new(&mut self.writer).poll_write(cx, &self.buf[self.head..self.tail]) {
+                Poll::Ready(Ok(0)) => {
+                    track_panic!(ErrorKind::UnexpectedEos, "The stream has been closed");
+                }
+                Poll::Ready(Ok(n)) => self.head += n,
+                Poll::Ready(Err(e)) => {
+                    if e.kind() != io::ErrorKind::Interrupted {
+                        return Err(track!(Error::from(e)));
+                    }
+                }
+                Poll::Pending => {
+                    track!(poll_timeout(cx, delay))?;
+                    return Ok(Poll::Pending);
+                }
+            }
+        }
+
+        match Pin::new(&mut self.writer).poll_flush(cx) {
+            Poll::Ready(result) => {
+                track!(result.map_err(Error::from))?;
+                Ok(Poll::Ready(()))
+            }
+            Poll::Pending => {
+                track!(poll_timeout(cx, delay))?;
+                Ok(Poll::Pending)
+            }
+        }
+    }
+}
+
+/// Future that writes a message to an `AsyncWriter`.
+///
+/// This is created by calling `AsyncWriter::write_message` or `AsyncWriter::flush` method.
+#[derive(Debug)]
+pub struct WriteMessage<'a, W: 'a, E: 'a> {
+    writer: &'a mut AsyncWriter<W, E>,
+    delay: Option<Delay>,
+    error: Option<Error>,
+}
+impl<'a, W: AsyncWrite + Unpin, E: MessageEncode> Future for WriteMessage<'a, W, E> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        match this.writer.poll_write_message(cx, &mut this.delay) {
+            Ok(poll) => poll.map(Ok),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+fn poll_timeout(cx: &mut Context, delay: &mut Option<Delay>) -> Result<()> {
+    if let Some(ref mut delay) = *delay {
+        if Pin::new(delay).poll(cx).is_ready() {
+            let e = io::Error::new(io::ErrorKind::TimedOut, "Timeout expired");
+            return Err(track!(Error::from(e)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+    use bytecodec::{Encode, ErrorKind};
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+    use futures::task::noop_waker_ref;
+    use futures_io::AsyncRead;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use super::*;
+    use http2::PREFACE;
+    use {
+        BodyDecoder, BodyEncoder, BodyStream, HeaderField, HttpVersion, Method, ProtocolSwitch,
+        Request, RequestDecoder, RequestEncoder, RequestTarget, ResponseDecoder,
+        StreamingBodyEncoder,
+    };
+
+    struct Pending;
+    impl AsyncRead for Pending {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context,
+            _buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Pending
+        }
+    }
+
+    struct Partial(Vec<u8>);
+    impl AsyncRead for Partial {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.0.is_empty() {
+                return Poll::Pending;
+            }
+            let n = ::std::cmp::min(buf.len(), this.0.len());
+            buf[..n].copy_from_slice(&this.0[..n]);
+            this.0.drain(..n);
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    #[test]
+    fn async_reader_works() {
+        let input = Cursor::new(
+            b"GET /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nbarGET /baz HTTP/1.1\r\nContent-Length: 0\r\n\r\nGET"
+                .to_vec(),
+        );
+        let decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut reader = AsyncReader::with_buffer_size(input, decoder, 16);
+
+        let request = track_try_unwrap!(block_on(reader.read_message())).unwrap();
+        assert_eq!(request.request_target().as_str(), "/foo");
+        assert_eq!(request.body(), b"bar");
+
+        let request = track_try_unwrap!(block_on(reader.read_message())).unwrap();
+        assert_eq!(request.request_target().as_str(), "/baz");
+
+        assert_eq!(
+            block_on(reader.read_message()).err().map(|e| *e.kind()),
+            Some(ErrorKind::UnexpectedEos)
+        );
+    }
+
+    #[test]
+    fn async_reader_limits_work() {
+        let input =
+            Cursor::new(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789".to_vec());
+        let decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut reader = AsyncReader::new(input, decoder);
+        reader.set_max_message_size(Some(40));
+        assert_eq!(
+            block_on(reader.read_message()).err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+
+        let decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut reader = AsyncReader::new(Pending, decoder);
+        reader.set_timeout(Some(Duration::from_millis(10)));
+        assert_eq!(
+            block_on(reader.read_message()).err().map(|e| *e.kind()),
+            Some(ErrorKind::Other)
+        );
+    }
+
+    #[test]
+    fn async_reader_suspension_works() {
+        let input = Partial(
+            b"PUT /foo HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n".to_vec(),
+        );
+        let mut decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        decoder.set_expect_continue(true);
+        let mut reader = AsyncReader::new(input, decoder);
+        assert!(track_try_unwrap!(block_on(reader.read_message())).is_none());
+        assert!(reader.decoder_ref().is_suspended());
+
+        reader.decoder_mut().resume();
+        reader.reader_mut().0.extend_from_slice(b"bar");
+        let request = track_try_unwrap!(block_on(reader.read_message())).unwrap();
+        assert_eq!(request.request_target().as_str(), "/foo");
+        assert_eq!(request.body(), b"bar");
+    }
+
+    #[test]
+    fn async_reader_protocol_switch_works() {
+        let input = Cursor::new(
+            b"GET /chat HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n\x81\x05hello"
+                .to_vec(),
+        );
+        let decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut reader = AsyncReader::new(input, decoder);
+        let request = track_try_unwrap!(block_on(reader.read_message())).unwrap();
+        assert_eq!(request.request_target().as_str(), "/chat");
+        assert!(track_try_unwrap!(block_on(reader.read_message())).is_none());
+        assert_eq!(
+            reader.decoder_ref().switched_protocol(),
+            Some(ProtocolSwitch::Upgrade)
+        );
+        assert_eq!(reader.buffered_bytes(), b"\x81\x05hello");
+
+        // HTTP/2 connection preface
+        let mut input = PREFACE.to_vec();
+        input.extend_from_slice(&[0; 9]);
+        let decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut reader = AsyncReader::new(Cursor::new(input), decoder);
+        assert!(track_try_unwrap!(block_on(reader.read_message())).is_none());
+        assert_eq!(
+            reader.decoder_ref().switched_protocol(),
+            Some(ProtocolSwitch::Http2Preface)
+        );
+        assert_eq!(reader.buffered_bytes(), &[0; 9][..]);
+    }
+
+    #[test]
+    fn async_writer_waits_for_body_chunks() {
+        let (sender, stream) = BodyStream::channel();
+        let request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/foo").unwrap(),
+            HttpVersion::V1_1,
+            stream,
+        );
+        let encoder = RequestEncoder::new(StreamingBodyEncoder::new());
+        let mut writer = AsyncWriter::new(Cursor::new(Vec::new()), encoder);
+        {
+            let mut future = writer.write_message(request);
+            let mut cx = Context::from_waker(noop_waker_ref());
+            assert!(Pin::new(&mut future).poll(&mut cx).is_pending());
+
+            track_try_unwrap!(sender.send(b"bar".to_vec()));
+            sender.finish();
+            track_try_unwrap!(block_on(future));
+        }
+        assert_eq!(
+            writer.writer_ref().get_ref(),
+            b"PUT /foo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0003\r\nbar\r\n0\r\n\r\n"
+        );
+        assert!(writer.encoder_ref().is_idle());
+    }
+
+    #[test]
+    fn async_writer_works() {
+        let encoder = RequestEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        let mut writer = AsyncWriter::with_buffer_size(Cursor::new(Vec::new()), encoder, 16);
+        for path in &["/foo", "/bar"] {
+            let request = Request::new(
+                Method::new("PUT").unwrap(),
+                RequestTarget::new(path).unwrap(),
+                HttpVersion::V1_1,
+                b"baz",
+            );
+            track_try_unwrap!(block_on(writer.write_message(request)));
+        }
+        assert_eq!(
+            writer.writer_ref().get_ref(),
+            b"PUT /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nbaz\
+              PUT /bar HTTP/1.1\r\nContent-Length: 3\r\n\r\nbaz"
+        );
+
+        // Writes a request that expects `100 Continue`
+        writer.writer_mut().get_mut().clear();
+        writer.writer_mut().set_position(0);
+        writer.encoder_mut().set_expect_continue(true);
+        let mut request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/qux").unwrap(),
+            HttpVersion::V1_1,
+            b"baz",
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+        track_try_unwrap!(block_on(writer.write_message(request)));
+        assert_eq!(
+            writer.writer_ref().get_ref(),
+            b"PUT /qux HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n"
+        );
+        assert!(writer.encoder_ref().is_suspended());
+        assert!(!writer.encoder_ref().is_idle());
+
+        writer.encoder_mut().resume();
+        track_try_unwrap!(block_on(writer.flush()));
+        assert!(writer.writer_ref().get_ref().ends_with(b"\r\n\r\nbaz"));
+        assert!(writer.encoder_ref().is_idle());
+    }
+}
@@ -0,0 +1,90 @@
+// https://tools.ietf.org/html/rfc4648
+pub const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+pub const URL_SAFE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub fn encode(input: &[u8], alphabet: &[u8; 64], padding: bool) -> String {
+    let mut s = String::with_capacity((input.len() + 2) / 3 * 4);
+    for chunk in input.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (u32::from(b) << (16 - i * 8)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - i * 6)) & 0x3F;
+                s.push(alphabet[index as usize] as char);
+            } else if padding {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+pub fn decode(input: &str, alphabet: &[u8; 64], padding: bool) -> Option<Vec<u8>> {
+    let mut input = input.as_bytes();
+    if padding {
+        if input.len() % 4 != 0 {
+            return None;
+        }
+        let n = input.iter().rev().take_while(|&&b| b == b'=').count();
+        if n > 2 {
+            return None;
+        }
+        input = &input[..input.len() - n];
+    }
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut n = 0;
+        for &b in chunk {
+            let index = alphabet.iter().position(|&c| c == b)? as u32;
+            n = (n << 6) | index;
+        }
+
+        let missing = 4 - chunk.len();
+        n <<= 6 * missing;
+        if n & ((1 << (8 * missing)) - 1) != 0 {
+            return None;
+        }
+        for i in 0..3 - missing {
+            bytes.push((n >> (16 - i * 8)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base64_works() {
+        for input in &[&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+            let encoded = encode(input, STANDARD, true);
+            assert_eq!(
+                decode(&encoded, STANDARD, true).as_ref().map(|x| &x[..]),
+                Some(*input)
+            );
+
+            let encoded = encode(input, URL_SAFE, false);
+            assert_eq!(
+                decode(&encoded, URL_SAFE, false).as_ref().map(|x| &x[..]),
+                Some(*input)
+            );
+        }
+        assert_eq!(encode(b"foobar", STANDARD, true), "Zm9vYmFy");
+        assert_eq!(encode(b"fooba", STANDARD, true), "Zm9vYmE=");
+        assert_eq!(encode(b"fooba", URL_SAFE, false), "Zm9vYmE");
+        assert_eq!(encode(&[0xFB, 0xFF], URL_SAFE, false), "-_8");
+
+        assert_eq!(decode("Zm9vYmE", STANDARD, true), None);
+        assert_eq!(decode("Zm9vYmF=", STANDARD, true), None);
+        assert_eq!(decode("Zm=vYmE=", STANDARD, true), None);
+        assert_eq!(decode("Zm9vYmE=", URL_SAFE, false), None);
+        assert_eq!(decode("Zm9vY", URL_SAFE, false), None);
+    }
+}
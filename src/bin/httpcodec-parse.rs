@@ -0,0 +1,500 @@
+//! A command-line tool that parses, validates and pretty-prints raw HTTP/1.x messages.
+extern crate bytecodec;
+extern crate httpcodec;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate trackable;
+
+use bytecodec::bytes::RemainingBytesDecoder;
+use bytecodec::{Decode, Eos, Error, ErrorKind, Result};
+use httpcodec::{
+    BodyDecoder, BodyFraming, DecodeOptions, Header, ProtocolSwitch, RequestDecoder,
+    ResponseDecoder,
+};
+use serde_json::Value;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "\
+Usage: httpcodec-parse [OPTIONS] [FILE]
+
+Decodes the raw HTTP/1.x messages read from FILE (or the standard input if FILE is
+omitted or `-`), and prints their start-lines, header fields and body framing.
+
+Options:
+    --request                  Decodes the input as requests
+    --response                 Decodes the input as responses
+                               (by default, the kind is guessed for each message)
+    --max-start-line-size N    Sets the maximum size of a start-line
+    --max-header-size N        Sets the maximum size of a header
+    --json                     Prints the result in JSON
+    -h, --help                 Prints this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Request,
+    Response,
+}
+
+#[derive(Debug)]
+struct Args {
+    kind: Option<Kind>,
+    options: DecodeOptions,
+    json: bool,
+    path: Option<String>,
+}
+
+#[derive(Debug)]
+struct Message {
+    kind: &'static str,
+    range: Option<(usize, usize)>,
+    start_line: String,
+    header: Vec<(String, String)>,
+    framing: Option<BodyFraming>,
+    body_size: usize,
+    protocol_switch: Option<ProtocolSwitch>,
+}
+
+#[derive(Debug)]
+struct DecodeError {
+    message: usize,
+    offset: usize,
+    line: usize,
+    column: usize,
+    error: Error,
+}
+
+#[derive(Debug, Default)]
+struct Report {
+    messages: Vec<Message>,
+    error: Option<DecodeError>,
+    unconsumed: usize,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let mut input = Vec::new();
+    let result = match args.path {
+        None => io::stdin().read_to_end(&mut input),
+        Some(ref path) => File::open(path).and_then(|mut f| f.read_to_end(&mut input)),
+    };
+    if let Err(e) = result {
+        eprintln!("error: cannot read the input: {}", e);
+        process::exit(2);
+    }
+
+    let report = inspect(&input, args.kind, &args.options);
+    if args.json {
+        println!("{}", to_json(&report));
+    } else {
+        print_text(&report);
+    }
+    if report.error.is_some() {
+        process::exit(1);
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> std::result::Result<Args, String> {
+    let mut parsed = Args {
+        kind: None,
+        options: DecodeOptions::default(),
+        json: false,
+        path: None,
+    };
+    let mut input_given = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--request" => parsed.kind = Some(Kind::Request),
+            "--response" => parsed.kind = Some(Kind::Response),
+            "--json" => parsed.json = true,
+            "--max-start-line-size" | "--max-header-size" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("{} requires a value", arg))?;
+                let size = value
+                    .parse()
+                    .map_err(|_| format!("invalid size for {}: {:?}", arg, value))?;
+                if arg == "--max-start-line-size" {
+                    parsed.options.max_start_line_size = size;
+                } else {
+                    parsed.options.max_header_size = size;
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ if input_given => return Err(format!("unexpected argument: {}", arg)),
+            _ => {
+                input_given = true;
+                if arg != "-" {
+                    parsed.path = Some(arg);
+                }
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+/// Decodes the messages in `input` one after another.
+///
+/// It stops at the first error, or when a protocol switch is detected.
+fn inspect(input: &[u8], kind: Option<Kind>, options: &DecodeOptions) -> Report {
+    let mut report = Report::default();
+    let mut offset = 0;
+    while offset < input.len() {
+        let rest = &input[offset..];
+        let kind = kind.unwrap_or(if rest.starts_with(b"HTTP/") {
+            Kind::Response
+        } else {
+            Kind::Request
+        });
+        let result = match kind {
+            Kind::Request => decode_request(rest, options, &mut report.messages),
+            Kind::Response => decode_response(rest, options, &mut report.messages),
+        };
+        match result {
+            Err(error) => {
+                let position = offset + locate_error(rest, kind, options);
+                let line_start = input[..position]
+                    .iter()
+                    .rposition(|&b| b == b'\n')
+                    .map_or(0, |i| i + 1);
+                report.error = Some(DecodeError {
+                    message: report.messages.len() + 1,
+                    offset: position,
+                    line: input[..position].iter().filter(|&&b| b == b'\n').count() + 1,
+                    column: position - line_start + 1,
+                    error,
+                });
+                break;
+            }
+            Ok(size) => {
+                let m = report.messages.last_mut().expect("never fails");
+                m.range = Some((offset, offset + size));
+                offset += size;
+                if m.protocol_switch.is_some() {
+                    break;
+                }
+            }
+        }
+    }
+    report.unconsumed = input.len() - offset;
+    report
+}
+
+fn decode_request(
+    input: &[u8],
+    options: &DecodeOptions,
+    messages: &mut Vec<Message>,
+) -> Result<usize> {
+    let mut decoder = RequestDecoder::with_options(
+        BodyDecoder::for_request(RemainingBytesDecoder::new()),
+        options.clone(),
+    );
+    let size = track!(decode_all(&mut decoder, input))?;
+    if decoder.protocol_switch() == Some(ProtocolSwitch::Http2Preface) {
+        messages.push(Message {
+            kind: "preface",
+            range: None,
+            start_line: "PRI * HTTP/2.0".to_owned(),
+            header: Vec::new(),
+            framing: None,
+            body_size: 0,
+            protocol_switch: decoder.protocol_switch(),
+        });
+        return Ok(size);
+    }
+
+    track_assert!(
+        decoder.is_idle(),
+        ErrorKind::UnexpectedEos,
+        "Incomplete request"
+    );
+
+    let framing = decoder.body_decoder_ref().framing();
+    let request = track!(decoder.finish_decoding())?;
+    messages.push(Message {
+        kind: "request",
+        range: None,
+        start_line: format!(
+            "{} {} {}",
+            request.method(),
+            request.request_target(),
+            request.http_version()
+        ),
+        header: fields(&request.header()),
+        framing: Some(framing),
+        body_size: request.body().len(),
+        protocol_switch: decoder.protocol_switch(),
+    });
+    Ok(size)
+}
+
+fn decode_response(
+    input: &[u8],
+    options: &DecodeOptions,
+    messages: &mut Vec<Message>,
+) -> Result<usize> {
+    let mut decoder = ResponseDecoder::with_options(
+        BodyDecoder::new(RemainingBytesDecoder::new()),
+        options.clone(),
+    );
+    decoder.set_keep_interim_responses(true);
+    let size = track!(decode_all(&mut decoder, input))?;
+    track_assert!(
+        decoder.is_idle(),
+        ErrorKind::UnexpectedEos,
+        "Incomplete response"
+    );
+    while let Some(interim) = decoder.pop_interim_response() {
+        messages.push(Message {
+            kind: "interim response",
+            range: None,
+            start_line: format!(
+                "{} {} {}",
+                interim.http_version(),
+                interim.status_code(),
+                interim.reason_phrase()
+            ),
+            header: fields(&interim.header()),
+            framing: None,
+            body_size: 0,
+            protocol_switch: None,
+        });
+    }
+
+    let framing = decoder.body_decoder_ref().framing();
+    let response = track!(decoder.finish_decoding())?;
+    messages.push(Message {
+        kind: "response",
+        range: None,
+        start_line: format!(
+            "{} {} {}",
+            response.http_version(),
+            response.status_code(),
+            response.reason_phrase()
+        ),
+        header: fields(&response.header()),
+        framing: Some(framing),
+        body_size: response.body().len(),
+        protocol_switch: decoder.protocol_switch(),
+    });
+    Ok(size)
+}
+
+fn decode_all<D: Decode>(decoder: &mut D, input: &[u8]) -> Result<usize> {
+    let mut size = track!(decoder.decode(input, Eos::new(false)))?;
+    if !decoder.is_idle() {
+        size += track!(decoder.decode(&input[size..], Eos::new(true)))?;
+    }
+    Ok(size)
+}
+
+/// Returns the position of the byte at which decoding `input` fails.
+///
+/// The input is fed to a new decoder byte by byte to find the position.
+fn locate_error(input: &[u8], kind: Kind, options: &DecodeOptions) -> usize {
+    fn locate<D: Decode>(mut decoder: D, input: &[u8]) -> usize {
+        for i in 0..input.len() {
+            match decoder.decode(&input[i..=i], Eos::new(false)) {
+                Err(_) => return i,
+                Ok(_) if decoder.is_idle() => return i + 1,
+                Ok(_) => {}
+            }
+        }
+        input.len()
+    }
+
+    match kind {
+        Kind::Request => {
+            let body = BodyDecoder::for_request(RemainingBytesDecoder::new());
+            locate(RequestDecoder::with_options(body, options.clone()), input)
+        }
+        Kind::Response => {
+            let body = BodyDecoder::new(RemainingBytesDecoder::new());
+            locate(ResponseDecoder::with_options(body, options.clone()), input)
+        }
+    }
+}
+
+fn fields(header: &Header) -> Vec<(String, String)> {
+    header
+        .fields()
+        .map(|f| (f.name().to_owned(), f.value().to_owned()))
+        .collect()
+}
+
+fn print_text(report: &Report) {
+    for (i, m) in report.messages.iter().enumerate() {
+        match m.range {
+            Some((start, end)) => println!("#{} {} (bytes {}..{})", i + 1, m.kind, start, end),
+            None => println!("#{} {}", i + 1, m.kind),
+        }
+        println!("  {}", m.start_line);
+        for (name, value) in &m.header {
+            println!("  {}: {}", name, value);
+        }
+        match m.framing {
+            Some(BodyFraming::Length(n)) => {
+                println!("  body: {} bytes (content-length: {})", m.body_size, n)
+            }
+            Some(BodyFraming::Chunked) => println!("  body: {} bytes (chunked)", m.body_size),
+            Some(BodyFraming::Close) => println!("  body: {} bytes (until close)", m.body_size),
+            Some(BodyFraming::None) | None => println!("  body: none"),
+        }
+        if let Some(switch) = m.protocol_switch {
+            println!("  protocol switch: {:?}", switch);
+        }
+    }
+    if let Some(ref e) = report.error {
+        println!(
+            "error: message #{} is invalid at byte {} (line {}, column {})",
+            e.message, e.offset, e.line, e.column
+        );
+        println!("{}", e.error);
+    }
+    if report.unconsumed > 0 {
+        println!("{} bytes are left unconsumed", report.unconsumed);
+    }
+}
+
+fn to_json(report: &Report) -> Value {
+    let messages = report
+        .messages
+        .iter()
+        .map(|m| {
+            let framing = match m.framing {
+                Some(BodyFraming::Length(n)) => json!({"type": "length", "length": n}),
+                Some(BodyFraming::Chunked) => json!({"type": "chunked"}),
+                Some(BodyFraming::Close) => json!({"type": "close"}),
+                Some(BodyFraming::None) => json!({"type": "none"}),
+                None => Value::Null,
+            };
+            json!({
+                "kind": m.kind,
+                "range": m.range.map(|(start, end)| json!([start, end])),
+                "start_line": m.start_line,
+                "header": m.header.iter().map(|(n, v)| json!([n, v])).collect::<Vec<_>>(),
+                "framing": framing,
+                "body_size": m.body_size,
+                "protocol_switch": m.protocol_switch.map(|s| format!("{:?}", s)),
+            })
+        })
+        .collect::<Vec<_>>();
+    let error = report.error.as_ref().map(|e| {
+        json!({
+            "message": e.message,
+            "offset": e.offset,
+            "line": e.line,
+            "column": e.column,
+            "kind": format!("{:?}", e.error.kind()),
+            "description": e.error.to_string(),
+        })
+    });
+    json!({
+        "messages": messages,
+        "error": error,
+        "unconsumed": report.unconsumed,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> std::result::Result<Args, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parse_args_works() {
+        let parsed = args(&["--request", "--json", "foo.txt"]).unwrap();
+        assert_eq!(parsed.path.as_deref(), Some("foo.txt"));
+        assert!(parsed.json);
+
+        let parsed = args(&["-"]).unwrap();
+        assert!(parsed.path.is_none());
+
+        assert!(args(&["foo.txt", "bar.txt"]).is_err());
+        assert!(args(&["foo.txt", "-"]).is_err());
+        assert!(args(&["-", "foo.txt"]).is_err());
+        assert!(args(&["-", "-"]).is_err());
+        assert!(args(&["--unknown"]).is_err());
+    }
+
+    #[test]
+    fn inspect_works() {
+        let input = b"GET / HTTP/1.1\r\nHost: foo\r\nContent-Length: 3\r\n\r\nbar\
+                      POST /baz HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nqux\r\n0\r\n\r\n";
+        let report = inspect(input, None, &DecodeOptions::default());
+        assert!(report.error.is_none());
+        assert_eq!(report.messages.len(), 2);
+        assert_eq!(report.messages[0].range, Some((0, 51)));
+        assert_eq!(report.messages[0].framing, Some(BodyFraming::Length(3)));
+        assert_eq!(report.messages[1].start_line, "POST /baz HTTP/1.1");
+        assert_eq!(report.messages[1].framing, Some(BodyFraming::Chunked));
+        assert_eq!(report.messages[1].body_size, 3);
+
+        // Requests without `Content-Length` and `Transfer-Encoding` have no body
+        let input = b"GET /foo HTTP/1.1\r\n\r\nGET /bar HTTP/1.1\r\n\r\n";
+        let report = inspect(input, None, &DecodeOptions::default());
+        assert!(report.error.is_none());
+        assert_eq!(report.messages.len(), 2);
+        assert_eq!(report.messages[0].range, Some((0, 21)));
+        assert_eq!(report.messages[0].framing, Some(BodyFraming::None));
+        assert_eq!(report.messages[1].start_line, "GET /bar HTTP/1.1");
+
+        let input = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\r\nfoo";
+        let report = inspect(input, None, &DecodeOptions::default());
+        assert_eq!(report.messages.len(), 2);
+        assert_eq!(report.messages[0].kind, "interim response");
+        assert_eq!(report.messages[1].framing, Some(BodyFraming::Close));
+        assert_eq!(report.messages[1].body_size, 3);
+
+        let input = b"HTTP/1.1 204 No Content\r\n\r\n";
+        let report = inspect(input, None, &DecodeOptions::default());
+        assert_eq!(report.messages[0].framing, Some(BodyFraming::None));
+        assert_eq!(
+            to_json(&report)["messages"][0]["framing"],
+            json!({"type": "none"})
+        );
+    }
+
+    #[test]
+    fn inspect_reports_error_position() {
+        let input =
+            b"GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\nGET / HTTP/1.1\r\nHo st: foo\r\n\r\n";
+        let report = inspect(input, Some(Kind::Request), &DecodeOptions::default());
+        assert_eq!(report.messages.len(), 1);
+        let e = report.error.unwrap();
+        assert_eq!((e.message, e.offset, e.line, e.column), (2, 55, 5, 3));
+        assert_eq!(*e.error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(report.unconsumed, 30);
+
+        let input = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00";
+        let report = inspect(input, None, &DecodeOptions::default());
+        assert!(report.error.is_none());
+        assert_eq!(report.messages[0].kind, "preface");
+        assert_eq!(report.unconsumed, 2);
+
+        let json = to_json(&inspect(
+            b"GET / HTTP/1.1\r\n",
+            None,
+            &DecodeOptions::default(),
+        ));
+        assert_eq!(json["error"]["kind"], "UnexpectedEos");
+        assert_eq!(json["error"]["offset"], 16);
+    }
+}
@@ -1,11 +1,16 @@
 use bytecodec::combinator::Length;
-use bytecodec::{ByteCount, Decode, DecodeExt, Encode, Eos, ErrorKind, Result, SizedEncode};
+use bytecodec::{ByteCount, Decode, DecodeExt, Encode, Eos, Error, ErrorKind, Result, SizedEncode};
+use std::cmp;
 use std::fmt;
+use std::io::{self, IoSlice, Write};
 use std::mem;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::task::Poll;
+use std::time::Duration;
 use trackable::error::ErrorKindExt;
 
 use chunked_body::{ChunkedBodyDecoder, ChunkedBodyEncoder};
-use {Header, HeaderField, HeaderMut};
+use {EncodeVectored, Header, HeaderField, HeaderMut};
 
 /// `BodyDecode` is used for representing HTTP body decoders.
 pub trait BodyDecode: Decode {
@@ -16,16 +21,37 @@ pub trait BodyDecode: Decode {
     fn initialize(&mut self, header: &Header) -> Result<()> {
         Ok(())
     }
+
+    /// This method is called instead of `initialize` if the message has no body
+    /// regardless of its header (e.g., a `204 No Content` response or a response to a `HEAD` request).
+    ///
+    /// After this call, the decoder is expected to be idle.
+    ///
+    /// The default implementation calls `initialize` with an empty header,
+    /// and then decodes an empty input that reaches EOS.
+    fn skip(&mut self) -> Result<()> {
+        track!(self.initialize(&Header::new(&[], &[])))?;
+        track!(self.decode(&[], Eos::new(true)))?;
+        Ok(())
+    }
 }
 impl<'a, T: ?Sized + BodyDecode> BodyDecode for &'a mut T {
     fn initialize(&mut self, header: &Header) -> Result<()> {
         (**self).initialize(header)
     }
+
+    fn skip(&mut self) -> Result<()> {
+        (**self).skip()
+    }
 }
 impl<T: ?Sized + BodyDecode> BodyDecode for Box<T> {
     fn initialize(&mut self, header: &Header) -> Result<()> {
         (**self).initialize(header)
     }
+
+    fn skip(&mut self) -> Result<()> {
+        (**self).skip()
+    }
 }
 
 /// `BodyEncode` is used for representing HTTP body encoders.
@@ -109,6 +135,16 @@ impl SizedEncode for NoBodyEncoder {
     }
 }
 impl BodyEncode for NoBodyEncoder {}
+impl EncodeVectored for NoBodyEncoder {
+    fn pending_slices<'a>(&'a mut self, _slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        Ok(())
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track_assert_eq!(size, 0, ErrorKind::InvalidInput);
+        Ok(())
+    }
+}
 
 /// A body encoder mainly intended to be used for encoding HEAD responses.
 ///
@@ -170,40 +206,134 @@ impl<E: BodyEncode> BodyEncode for HeadBodyEncoder<E> {
         self.0.update_header(header)
     }
 }
+impl<E: BodyEncode> EncodeVectored for HeadBodyEncoder<E> {
+    fn pending_slices<'a>(&'a mut self, _slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.encode(&mut [], Eos::new(false)))?;
+        Ok(())
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track_assert_eq!(size, 0, ErrorKind::InvalidInput);
+        Ok(())
+    }
+}
+
+/// Framing of a HTTP body (i.e., how the end of the body is determined).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyFraming {
+    /// The body has the length specified by the `Content-Length` header field.
+    Length(u64),
+
+    /// The body is encoded by the chunked transfer coding.
+    Chunked,
+
+    /// The body continues until the end of the stream (i.e., the connection is closed).
+    Close,
+
+    /// The message has no body.
+    ///
+    /// This is the case for a message whose body is skipped regardless of its header
+    /// (e.g., a `204 No Content` response or a response to a `HEAD` request), and
+    /// for a request that has neither `Content-Length` nor `Transfer-Encoding` header field.
+    None,
+}
 
 /// Basic HTTP body decoder.
 ///
 /// It is typically used for making a body decoder from a `Decode` implementor.
 #[derive(Debug, Default)]
-pub struct BodyDecoder<D: Decode>(BodyDecoderInner<D>);
+pub struct BodyDecoder<D: Decode> {
+    inner: BodyDecoderInner<D>,
+    empty_without_length: bool,
+    no_body: bool,
+}
 impl<D: Decode> BodyDecoder<D> {
     /// Makes a new `BodyDecoder` instance.
     pub fn new(inner: D) -> Self {
-        BodyDecoder(BodyDecoderInner::WithoutLength(inner))
+        BodyDecoder {
+            inner: BodyDecoderInner::WithoutLength(inner),
+            empty_without_length: false,
+            no_body: false,
+        }
+    }
+
+    /// Makes a new `BodyDecoder` instance for decoding request bodies.
+    ///
+    /// Unlike `new`, the resulting decoder regards the body of a message that has
+    /// neither `Content-Length` nor `Transfer-Encoding` header field as empty
+    /// instead of reading it until the end of the stream (see [RFC 7230 section 3.3.3]).
+    /// For such a message, `framing` method returns `BodyFraming::None`.
+    ///
+    /// [RFC 7230 section 3.3.3]: https://tools.ietf.org/html/rfc7230#section-3.3.3
+    pub fn for_request(inner: D) -> Self {
+        BodyDecoder {
+            inner: BodyDecoderInner::WithoutLength(inner),
+            empty_without_length: true,
+            no_body: false,
+        }
+    }
+
+    /// Returns a reference to a inner decoder.
+    pub fn inner_ref(&self) -> &D {
+        self.inner.inner_ref()
+    }
+
+    /// Returns a mutable reference to a inner decoder.
+    pub fn inner_mut(&mut self) -> &mut D {
+        self.inner.inner_mut()
+    }
+
+    /// Returns the framing of the body determined from the header of the current message.
+    ///
+    /// Before a header is given, this returns `BodyFraming::Close`.
+    pub fn framing(&self) -> BodyFraming {
+        if self.no_body {
+            return BodyFraming::None;
+        }
+        match self.inner {
+            BodyDecoderInner::Chunked(_) => BodyFraming::Chunked,
+            BodyDecoderInner::WithLength(ref x) => BodyFraming::Length(x.expected_bytes()),
+            BodyDecoderInner::WithoutLength(_) => BodyFraming::Close,
+            BodyDecoderInner::None => unreachable!(),
+        }
     }
 }
 impl<D: Decode> Decode for BodyDecoder<D> {
     type Item = D::Item;
 
     fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
-        self.0.decode(buf, eos)
+        self.inner.decode(buf, eos)
     }
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
-        self.0.finish_decoding()
+        self.inner.finish_decoding()
     }
 
     fn requiring_bytes(&self) -> ByteCount {
-        self.0.requiring_bytes()
+        self.inner.requiring_bytes()
     }
 
     fn is_idle(&self) -> bool {
-        self.0.is_idle()
+        self.inner.is_idle()
     }
 }
 impl<D: Decode> BodyDecode for BodyDecoder<D> {
     fn initialize(&mut self, header: &Header) -> Result<()> {
-        self.0.initialize(header)
+        self.no_body = false;
+        track!(self.inner.initialize(header))?;
+        if self.empty_without_length && self.framing() == BodyFraming::Close {
+            self.inner
+                .update_inner(|inner| BodyDecoderInner::WithLength(inner.length(0)));
+            self.no_body = true;
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self) -> Result<()> {
+        track!(self.initialize(&Header::new(&[], &[])))?;
+        track!(self.decode(&[], Eos::new(true)))?;
+        self.no_body = true;
+        Ok(())
     }
 }
 
@@ -214,18 +344,35 @@ enum BodyDecoderInner<D: Decode> {
     None,
 }
 impl<D: Decode> BodyDecoderInner<D> {
-    fn update_inner<F>(&mut self, f: F) -> Result<()>
+    fn update_inner<F>(&mut self, f: F)
     where
-        F: FnOnce(D) -> Result<Self>,
+        F: FnOnce(D) -> Self,
     {
         let inner = match mem::replace(self, BodyDecoderInner::None) {
             BodyDecoderInner::Chunked(x) => x.into_inner(),
             BodyDecoderInner::WithLength(x) => x.into_inner(),
             BodyDecoderInner::WithoutLength(x) => x,
-            BodyDecoderInner::None => return Ok(()),
+            BodyDecoderInner::None => return,
         };
-        *self = f(inner)?;
-        Ok(())
+        *self = f(inner);
+    }
+
+    fn inner_ref(&self) -> &D {
+        match *self {
+            BodyDecoderInner::Chunked(ref x) => x.inner_ref(),
+            BodyDecoderInner::WithLength(ref x) => x.inner_ref(),
+            BodyDecoderInner::WithoutLength(ref x) => x,
+            BodyDecoderInner::None => unreachable!(),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut D {
+        match *self {
+            BodyDecoderInner::Chunked(ref mut x) => x.inner_mut(),
+            BodyDecoderInner::WithLength(ref mut x) => x.inner_mut(),
+            BodyDecoderInner::WithoutLength(ref mut x) => x,
+            BodyDecoderInner::None => unreachable!(),
+        }
     }
 }
 impl<D: Decode> Decode for BodyDecoderInner<D> {
@@ -274,23 +421,24 @@ impl<D: Decode + Default> Default for BodyDecoderInner<D> {
 }
 impl<D: Decode> BodyDecode for BodyDecoderInner<D> {
     fn initialize(&mut self, header: &Header) -> Result<()> {
-        self.update_inner(|inner| {
-            for field in header.fields() {
-                if field.name().eq_ignore_ascii_case("content-length") {
-                    let size: u64 = track!(
-                        field
-                            .value()
-                            .parse()
-                            .map_err(|e| ErrorKind::InvalidInput.cause(e))
-                    )?;
-                    return Ok(BodyDecoderInner::WithLength(inner.length(size)));
-                } else if field.name().eq_ignore_ascii_case("transfer-encoding") {
-                    track_assert_eq!(field.value(), "chunked", ErrorKind::Other);
-                    return Ok(BodyDecoderInner::Chunked(ChunkedBodyDecoder::new(inner)));
-                }
+        for field in header.fields() {
+            if field.name().eq_ignore_ascii_case("content-length") {
+                let size: u64 = track!(field
+                    .value()
+                    .parse()
+                    .map_err(|e| ErrorKind::InvalidInput.cause(e)))?;
+                self.update_inner(|inner| BodyDecoderInner::WithLength(inner.length(size)));
+                return Ok(());
+            } else if field.name().eq_ignore_ascii_case("transfer-encoding") {
+                track_assert_eq!(field.value(), "chunked", ErrorKind::Other);
+                self.update_inner(|inner| {
+                    BodyDecoderInner::Chunked(ChunkedBodyDecoder::new(inner))
+                });
+                return Ok(());
             }
-            Ok(BodyDecoderInner::WithoutLength(inner))
-        })
+        }
+        self.update_inner(BodyDecoderInner::WithoutLength);
+        Ok(())
     }
 }
 impl<D: Decode> fmt::Debug for BodyDecoderInner<D> {
@@ -304,12 +452,146 @@ impl<D: Decode> fmt::Debug for BodyDecoderInner<D> {
     }
 }
 
+/// A body decoder that passes the body bytes to a sink as soon as they have been de-framed.
+///
+/// It works in the same way regardless of the framing of the body
+/// (i.e., `Content-Length`, chunked transfer coding or the end of the stream),
+/// and the sink receives slices of the input buffer without any intermediate copying.
+///
+/// If the sink returns an `std::io::ErrorKind::WouldBlock` error or accepts only a part of a slice,
+/// the rest of the bytes are left unconsumed and will be passed to the sink in the subsequent decoding.
+///
+/// The decoded item is the number of the bytes in the body.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate bytecodec;
+/// # extern crate httpcodec;
+/// use bytecodec::{Decode, Eos};
+/// use httpcodec::{RequestDecoder, StreamingBodyDecoder};
+///
+/// # fn main() {
+/// let mut decoder = RequestDecoder::new(StreamingBodyDecoder::new(Vec::new()));
+///
+/// let input = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n";
+/// decoder.decode(input, Eos::new(false)).unwrap();
+/// assert_eq!(decoder.body_decoder_ref().sink_ref(), b"foo");
+///
+/// decoder.decode(b"3\r\nbar\r\n0\r\n\r\n", Eos::new(false)).unwrap();
+/// let request = decoder.finish_decoding().unwrap();
+/// assert_eq!(*request.body(), 6);
+/// assert_eq!(decoder.body_decoder_ref().sink_ref(), b"foobar");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct StreamingBodyDecoder<W: Write>(BodyDecoder<SinkDecoder<W>>);
+impl<W: Write> StreamingBodyDecoder<W> {
+    /// Makes a new `StreamingBodyDecoder` instance.
+    pub fn new(sink: W) -> Self {
+        StreamingBodyDecoder(BodyDecoder::new(SinkDecoder {
+            sink,
+            size: 0,
+            eos: false,
+        }))
+    }
+
+    /// Returns a reference to the sink.
+    pub fn sink_ref(&self) -> &W {
+        &self.0.inner_ref().sink
+    }
+
+    /// Returns a mutable reference to the sink.
+    pub fn sink_mut(&mut self) -> &mut W {
+        &mut self.0.inner_mut().sink
+    }
+}
+impl<W: Write> Decode for StreamingBodyDecoder<W> {
+    type Item = u64;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        track!(self.0.decode(buf, eos))
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        track!(self.0.finish_decoding())
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        self.0.requiring_bytes()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.0.is_idle()
+    }
+}
+impl<W: Write> BodyDecode for StreamingBodyDecoder<W> {
+    fn initialize(&mut self, header: &Header) -> Result<()> {
+        track!(self.0.initialize(header))
+    }
+
+    fn skip(&mut self) -> Result<()> {
+        track!(self.0.skip())
+    }
+}
+
+#[derive(Debug)]
+struct SinkDecoder<W> {
+    sink: W,
+    size: u64,
+    eos: bool,
+}
+impl<W: Write> Decode for SinkDecoder<W> {
+    type Item = u64;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        if self.eos {
+            return Ok(0);
+        }
+
+        let mut offset = 0;
+        while offset < buf.len() {
+            match self.sink.write(&buf[offset..]) {
+                Ok(0) => track_panic!(ErrorKind::Other, "The sink has been closed"),
+                Ok(n) => offset += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(track!(Error::from(e))),
+            }
+        }
+        self.size += offset as u64;
+        self.eos = offset == buf.len() && eos.is_reached();
+        Ok(offset)
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        track_assert!(self.eos, ErrorKind::IncompleteDecoding);
+        track!(self.sink.flush().map_err(Error::from))?;
+        self.eos = false;
+        Ok(mem::take(&mut self.size))
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        if self.eos {
+            ByteCount::Finite(0)
+        } else {
+            ByteCount::Infinite
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.eos
+    }
+}
+
 /// Basic HTTP body encoder.
 ///
 /// It is typically used for making a body encoder from a `Encode` implementor.
 ///
 /// If `E::requiring_bytes()` returns `ByteCount::Unknown`,
 /// the chunked body transfer encoding will be used.
+///
+/// Once a body has been encoded completely, the encoder can start encoding the next one.
 #[derive(Debug, Default)]
 pub struct BodyEncoder<E>(BodyEncoderInner<E>);
 impl<E> BodyEncoder<E> {
@@ -352,6 +634,28 @@ impl<E: Encode> BodyEncode for BodyEncoder<E> {
         }
     }
 }
+impl<E: EncodeVectored> EncodeVectored for BodyEncoder<E> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        match self.0 {
+            BodyEncoderInner::NotStarted(_) => Ok(()),
+            BodyEncoderInner::None => track_panic!(ErrorKind::Other),
+            BodyEncoderInner::WithLength(ref mut x) => track!(x.pending_slices(slices)),
+            BodyEncoderInner::Chunked(ref mut x) => track!(x.pending_slices(slices)),
+        }
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        match self.0 {
+            BodyEncoderInner::NotStarted(_) => {
+                track_assert_eq!(size, 0, ErrorKind::InvalidInput);
+                Ok(())
+            }
+            BodyEncoderInner::None => track_panic!(ErrorKind::Other),
+            BodyEncoderInner::WithLength(ref mut x) => track!(x.consume(size)),
+            BodyEncoderInner::Chunked(ref mut x) => track!(x.consume(size)),
+        }
+    }
+}
 
 #[derive(Debug)]
 enum BodyEncoderInner<E> {
@@ -360,6 +664,16 @@ enum BodyEncoderInner<E> {
     Chunked(ChunkedBodyEncoder<E>),
     None,
 }
+impl<E> BodyEncoderInner<E> {
+    fn inner_mut(&mut self) -> Option<&mut E> {
+        match *self {
+            BodyEncoderInner::NotStarted(ref mut x) => Some(x),
+            BodyEncoderInner::WithLength(ref mut x) => Some(x),
+            BodyEncoderInner::Chunked(ref mut x) => Some(x.inner_mut()),
+            BodyEncoderInner::None => None,
+        }
+    }
+}
 impl<E: Encode> Encode for BodyEncoderInner<E> {
     type Item = E::Item;
 
@@ -373,12 +687,15 @@ impl<E: Encode> Encode for BodyEncoderInner<E> {
     }
 
     fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
-        let mut inner =
-            if let BodyEncoderInner::NotStarted(x) = mem::replace(self, BodyEncoderInner::None) {
-                x
-            } else {
+        let mut inner = match mem::replace(self, BodyEncoderInner::None) {
+            BodyEncoderInner::NotStarted(x) => x,
+            BodyEncoderInner::WithLength(x) if x.is_idle() => x,
+            BodyEncoderInner::Chunked(x) if x.is_idle() => x.into_inner(),
+            other => {
+                *self = other;
                 track_panic!(ErrorKind::EncoderFull);
-            };
+            }
+        };
         track!(inner.start_encoding(item))?;
         let this = match inner.requiring_bytes() {
             ByteCount::Infinite => track_panic!(ErrorKind::Other),
@@ -411,3 +728,627 @@ impl<E: Default> Default for BodyEncoderInner<E> {
         BodyEncoderInner::NotStarted(E::default())
     }
 }
+
+/// A body encoder that encodes a `BodyStream` whose chunks are produced over time.
+///
+/// If the total length of the stream has been declared by `BodyStream::with_length`,
+/// the `Content-Length` header will be used. Otherwise the chunked transfer coding will be used.
+///
+/// When no chunk is available at the moment, the encoder produces no bytes without blocking.
+/// Instead of calling `encode` repeatedly in that case,
+/// the caller can block on `wait_ready` until the next chunk is sent via `BodySender`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate bytecodec;
+/// # extern crate httpcodec;
+/// use bytecodec::{Encode, Eos};
+/// use httpcodec::{BodyStream, HttpVersion, ReasonPhrase, Response, ResponseEncoder,
+///                 StatusCode, StreamingBodyEncoder};
+///
+/// # fn main() {
+/// let (sender, stream) = BodyStream::channel();
+/// let response = Response::new(
+///     HttpVersion::V1_1,
+///     StatusCode::new(200).unwrap(),
+///     ReasonPhrase::new("OK").unwrap(),
+///     stream,
+/// );
+/// let mut encoder = ResponseEncoder::new(StreamingBodyEncoder::new());
+/// encoder.start_encoding(response).unwrap();
+///
+/// let mut buf = vec![0; 1024];
+/// let size = encoder.encode(&mut buf, Eos::new(false)).unwrap();
+/// assert_eq!(&buf[..size], b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n");
+///
+/// sender.send(b"foo".to_vec()).unwrap();
+/// let size = encoder.encode(&mut buf, Eos::new(false)).unwrap();
+/// assert_eq!(&buf[..size], b"003\r\nfoo\r\n");
+///
+/// sender.finish();
+/// let size = encoder.encode(&mut buf, Eos::new(false)).unwrap();
+/// assert_eq!(&buf[..size], b"000\r\n\r\n");
+/// assert!(encoder.is_idle());
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct StreamingBodyEncoder(BodyEncoder<BodyStreamEncoder>);
+impl StreamingBodyEncoder {
+    /// Makes a new `StreamingBodyEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blocks the current thread until the encoder can produce bytes or the timeout expires.
+    ///
+    /// It returns `true` if a chunk is available or the stream has ended
+    /// (i.e., the next `encode` call will make progress), and `false` if the timeout has expired.
+    /// If `timeout` is `None`, it waits indefinitely.
+    ///
+    /// When the encoder is used as a part of a message encoder,
+    /// it can be reached via `ResponseEncoder::body_encoder_mut`, for example.
+    ///
+    /// # Errors
+    ///
+    /// If the `BodySender` has been dropped before finishing the stream,
+    /// an `ErrorKind::UnexpectedEos` error will be returned.
+    pub fn wait_ready(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        match (self.0).0.inner_mut() {
+            Some(inner) => track!(inner.wait_ready(timeout)),
+            None => Ok(true),
+        }
+    }
+}
+impl Encode for StreamingBodyEncoder {
+    type Item = BodyStream;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        track!(self.0.encode(buf, eos))
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        track!(self.0.start_encoding(item))
+    }
+
+    fn is_idle(&self) -> bool {
+        self.0.is_idle()
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        self.0.requiring_bytes()
+    }
+}
+impl BodyEncode for StreamingBodyEncoder {
+    fn update_header(&self, header: &mut HeaderMut) -> Result<()> {
+        track!(self.0.update_header(header))
+    }
+}
+impl EncodeVectored for StreamingBodyEncoder {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.0.pending_slices(slices))
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track!(self.0.consume(size))
+    }
+}
+
+/// A sequence of byte chunks that makes up a body.
+///
+/// This is the item of `StreamingBodyEncoder`.
+pub struct BodyStream {
+    source: BodySource,
+    length: Option<u64>,
+    received: Option<Option<Vec<u8>>>,
+}
+impl BodyStream {
+    /// Makes a new `BodyStream` instance and the sender that feeds chunks to it.
+    ///
+    /// The stream ends when `BodySender::finish` is called.
+    /// `StreamingBodyEncoder::wait_ready` can be used to block until the sender sends a chunk.
+    pub fn channel() -> (BodySender, Self) {
+        let (tx, rx) = mpsc::channel();
+        let stream = BodyStream {
+            source: BodySource::Channel(rx),
+            length: None,
+            received: None,
+        };
+        (BodySender(tx), stream)
+    }
+
+    /// Makes a new `BodyStream` instance that consists of the chunks yielded by the given iterator.
+    pub fn from_chunks<I>(chunks: I) -> Self
+    where
+        I: IntoIterator<Item = Vec<u8>>,
+        I::IntoIter: Send + 'static,
+    {
+        BodyStream {
+            source: BodySource::Iter(Box::new(chunks.into_iter())),
+            length: None,
+            received: None,
+        }
+    }
+
+    /// Declares the total length of the stream.
+    ///
+    /// If the actual length of the stream differs from it,
+    /// an `ErrorKind::InvalidInput` error will be returned during encoding.
+    pub fn with_length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Returns the declared total length of the stream.
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    fn poll_chunk(&mut self) -> Result<Poll<Option<Vec<u8>>>> {
+        if let Some(chunk) = self.received.take() {
+            return Ok(Poll::Ready(chunk));
+        }
+        match self.source {
+            BodySource::Channel(ref rx) => match rx.try_recv() {
+                Ok(chunk) => Ok(Poll::Ready(chunk)),
+                Err(TryRecvError::Empty) => Ok(Poll::Pending),
+                Err(TryRecvError::Disconnected) => track_panic!(
+                    ErrorKind::UnexpectedEos,
+                    "The sender has been dropped before finishing the stream"
+                ),
+            },
+            BodySource::Iter(ref mut iter) => Ok(Poll::Ready(iter.next())),
+        }
+    }
+
+    fn wait_chunk(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        if self.received.is_some() {
+            return Ok(true);
+        }
+        if let BodySource::Channel(ref rx) = self.source {
+            let result = match timeout {
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Some(timeout) => rx.recv_timeout(timeout),
+            };
+            match result {
+                Ok(chunk) => self.received = Some(chunk),
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => track_panic!(
+                    ErrorKind::UnexpectedEos,
+                    "The sender has been dropped before finishing the stream"
+                ),
+            }
+        }
+        Ok(true)
+    }
+}
+impl fmt::Debug for BodyStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let source = match self.source {
+            BodySource::Channel(_) => "Channel(_)",
+            BodySource::Iter(_) => "Iter(_)",
+        };
+        f.debug_struct("BodyStream")
+            .field("source", &format_args!("{}", source))
+            .field("length", &self.length)
+            .finish()
+    }
+}
+
+enum BodySource {
+    Channel(Receiver<Option<Vec<u8>>>),
+    Iter(Box<dyn Iterator<Item = Vec<u8>> + Send>),
+}
+
+/// The sending half of a `BodyStream` made by `BodyStream::channel`.
+#[derive(Debug)]
+pub struct BodySender(Sender<Option<Vec<u8>>>);
+impl BodySender {
+    /// Sends a chunk of the body.
+    ///
+    /// # Errors
+    ///
+    /// If the stream has been dropped (e.g., the encoder has been dropped or
+    /// the declared length has been reached), an `ErrorKind::Other` error will be returned.
+    pub fn send(&self, chunk: Vec<u8>) -> Result<()> {
+        track_assert!(
+            self.0.send(Some(chunk)).is_ok(),
+            ErrorKind::Other,
+            "The stream has been dropped"
+        );
+        Ok(())
+    }
+
+    /// Signals the end of the body.
+    pub fn finish(self) {
+        let _ = self.0.send(None);
+    }
+}
+
+#[derive(Debug, Default)]
+struct BodyStreamEncoder {
+    stream: Option<BodyStream>,
+    chunk: Vec<u8>,
+    offset: usize,
+    written: u64,
+}
+impl BodyStreamEncoder {
+    fn wait_ready(&mut self, timeout: Option<Duration>) -> Result<bool> {
+        // The received chunk (or the end of the stream) is handled by the next `encode` call
+        if self.offset < self.chunk.len() {
+            return Ok(true);
+        }
+        match self.stream {
+            Some(ref stream) if stream.length == Some(self.written) => Ok(true),
+            Some(ref mut stream) => track!(stream.wait_chunk(timeout)),
+            None => Ok(true),
+        }
+    }
+
+    fn fill_chunk(&mut self) -> Result<()> {
+        while self.offset == self.chunk.len() {
+            let poll = if let Some(ref mut stream) = self.stream {
+                if stream.length == Some(self.written) {
+                    Poll::Ready(None)
+                } else {
+                    track!(stream.poll_chunk())?
+                }
+            } else {
+                break;
+            };
+            match poll {
+                Poll::Pending => break,
+                Poll::Ready(None) => {
+                    let stream =
+                        track_assert_some!(self.stream.take(), ErrorKind::InconsistentState);
+                    if let Some(length) = stream.length {
+                        track_assert_eq!(
+                            length,
+                            self.written,
+                            ErrorKind::InvalidInput,
+                            "Too short body"
+                        );
+                    }
+                    break;
+                }
+                Poll::Ready(Some(chunk)) => {
+                    let written = self.written + chunk.len() as u64;
+                    if let Some(length) = self.stream.as_ref().and_then(|s| s.length) {
+                        track_assert!(written <= length, ErrorKind::InvalidInput,
+                                      "Too long body"; length, written);
+                    }
+                    self.written = written;
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+impl Encode for BodyStreamEncoder {
+    type Item = BodyStream;
+
+    fn encode(&mut self, buf: &mut [u8], _eos: Eos) -> Result<usize> {
+        let mut size = 0;
+        loop {
+            track!(self.fill_chunk())?;
+            if self.offset == self.chunk.len() || size == buf.len() {
+                break;
+            }
+
+            let n = cmp::min(buf.len() - size, self.chunk.len() - self.offset);
+            buf[size..][..n].copy_from_slice(&self.chunk[self.offset..][..n]);
+            size += n;
+            self.offset += n;
+        }
+        Ok(size)
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        track_assert!(self.is_idle(), ErrorKind::EncoderFull);
+        self.stream = Some(item);
+        self.chunk.clear();
+        self.offset = 0;
+        self.written = 0;
+        Ok(())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.stream.is_none() && self.offset == self.chunk.len()
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        match self.stream.as_ref().and_then(|s| s.length) {
+            Some(length) => {
+                let rest = length.saturating_sub(self.written);
+                ByteCount::Finite(rest + (self.chunk.len() - self.offset) as u64)
+            }
+            None if self.is_idle() => ByteCount::Finite(0),
+            None => ByteCount::Unknown,
+        }
+    }
+}
+impl EncodeVectored for BodyStreamEncoder {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.fill_chunk())?;
+        if self.offset < self.chunk.len() {
+            slices.push(IoSlice::new(&self.chunk[self.offset..]));
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track_assert!(self.offset + size <= self.chunk.len(), ErrorKind::InvalidInput;
+                      size, self.offset, self.chunk.len());
+        self.offset += size;
+        track!(self.fill_chunk())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::io::IoEncodeExt;
+    use bytecodec::{Decode, Encode, Eos, ErrorKind};
+    use std::io::{self, Write};
+
+    use super::*;
+    use {
+        HttpVersion, ReasonPhrase, RequestDecoder, Response, ResponseDecoder, ResponseEncoder,
+        StatusCode,
+    };
+
+    struct LimitedSink {
+        buf: Vec<u8>,
+        room: usize,
+    }
+    impl Write for LimitedSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.room == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let n = ::std::cmp::min(self.room, buf.len());
+            self.buf.extend_from_slice(&buf[..n]);
+            self.room -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn body_decoder_framing_works() {
+        use bytecodec::bytes::RemainingBytesDecoder;
+
+        let mut decoder = RequestDecoder::new(BodyDecoder::new(RemainingBytesDecoder::new()));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::Close);
+
+        let input = b"POST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nfoo";
+        track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::Length(3));
+        track_try_unwrap!(decoder.finish_decoding());
+
+        let input = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::Chunked);
+
+        let mut decoder = ResponseDecoder::new(BodyDecoder::new(RemainingBytesDecoder::new()));
+        let input = b"HTTP/1.1 204 No Content\r\nContent-Length: 3\r\n\r\n";
+        track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::None);
+        track_try_unwrap!(decoder.finish_decoding());
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n";
+        track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::Length(3));
+    }
+
+    #[test]
+    fn request_body_decoder_works() {
+        use bytecodec::bytes::RemainingBytesDecoder;
+
+        let mut decoder =
+            RequestDecoder::new(BodyDecoder::for_request(RemainingBytesDecoder::new()));
+        let input = b"GET / HTTP/1.1\r\n\r\nPOST / HTTP/1.1\r\nContent-Length: 3\r\n\r\nfoo";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, 18);
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::None);
+        let request = track_try_unwrap!(decoder.finish_decoding());
+        assert!(request.body().is_empty());
+
+        track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().framing(), BodyFraming::Length(3));
+        let request = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(request.body(), b"foo");
+    }
+
+    #[test]
+    fn streaming_body_decoder_works() {
+        // Content-Length
+        let mut decoder = RequestDecoder::new(StreamingBodyDecoder::new(Vec::new()));
+        let input = b"POST / HTTP/1.1\r\nContent-Length: 6\r\n\r\nfoo";
+        assert_eq!(
+            track_try_unwrap!(decoder.decode(input, Eos::new(false))),
+            input.len()
+        );
+        assert_eq!(decoder.body_decoder_ref().sink_ref(), b"foo");
+        track_try_unwrap!(decoder.decode(b"bar", Eos::new(false)));
+        let request = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(*request.body(), 6);
+        assert_eq!(decoder.body_decoder_ref().sink_ref(), b"foobar");
+
+        // Close-delimited
+        let mut decoder = ResponseDecoder::new(StreamingBodyDecoder::new(Vec::new()));
+        track_try_unwrap!(decoder.decode(b"HTTP/1.0 200 OK\r\n\r\nfoo", Eos::new(false)));
+        assert_eq!(decoder.body_decoder_ref().sink_ref(), b"foo");
+        decoder.body_decoder_mut().sink_mut().clear();
+        track_try_unwrap!(decoder.decode(b"bar", Eos::new(true)));
+        let response = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(*response.body(), 6);
+        assert_eq!(decoder.body_decoder_ref().sink_ref(), b"bar");
+    }
+
+    #[test]
+    fn streaming_body_decoder_with_would_block_sink_works() {
+        let sink = LimitedSink {
+            buf: Vec::new(),
+            room: 2,
+        };
+        let mut decoder = RequestDecoder::new(StreamingBodyDecoder::new(sink));
+        let input = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n0\r\n\r\n";
+
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(&input[size..], b"o\r\n0\r\n\r\n");
+        assert_eq!(decoder.body_decoder_ref().sink_ref().buf, b"fo");
+
+        decoder.body_decoder_mut().sink_mut().room = 10;
+        track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false)));
+        let request = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(*request.body(), 3);
+        assert_eq!(decoder.body_decoder_ref().sink_ref().buf, b"foo");
+    }
+
+    #[test]
+    fn body_encoder_can_be_reused() {
+        use bytecodec::bytes::BytesEncoder;
+
+        // Content-Length
+        let mut encoder = ResponseEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        let mut buf = Vec::new();
+        for body in &[b"foo", b"bar"] {
+            let response = Response::new(
+                HttpVersion::V1_1,
+                StatusCode::new(200).unwrap(),
+                ReasonPhrase::new("OK").unwrap(),
+                body,
+            );
+            track_try_unwrap!(encoder.start_encoding(response));
+            track_try_unwrap!(encoder.encode_all(&mut buf));
+        }
+        assert_eq!(
+            buf,
+            &b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo\
+               HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar"[..]
+        );
+
+        // Chunked
+        let mut encoder = BodyEncoder::new(BodyStreamEncoder::default());
+        let mut buf = Vec::new();
+        for chunk in &[b"foo", b"bar"] {
+            let stream = BodyStream::from_chunks(vec![chunk.to_vec()]);
+            track_try_unwrap!(encoder.start_encoding(stream));
+            track_try_unwrap!(encoder.encode_all(&mut buf));
+        }
+        assert_eq!(buf, b"003\r\nfoo\r\n0\r\n\r\n003\r\nbar\r\n0\r\n\r\n");
+
+        // A new body cannot be started while the current one is being encoded
+        let stream = BodyStream::from_chunks(vec![b"foo".to_vec()]);
+        track_try_unwrap!(encoder.start_encoding(stream));
+        let stream = BodyStream::from_chunks(vec![b"bar".to_vec()]);
+        assert_eq!(
+            encoder.start_encoding(stream).err().map(|e| *e.kind()),
+            Some(ErrorKind::EncoderFull)
+        );
+    }
+
+    #[test]
+    fn streaming_body_encoder_works() {
+        // Chunked
+        let (sender, stream) = BodyStream::channel();
+        let mut encoder = StreamingBodyEncoder::new();
+        track_try_unwrap!(encoder.start_encoding(stream));
+
+        let mut buf = [0; 32];
+        assert_eq!(
+            track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false))),
+            0
+        );
+        assert!(!encoder.is_idle());
+
+        track_try_unwrap!(sender.send(b"foo".to_vec()));
+        track_try_unwrap!(sender.send(b"bar".to_vec()));
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(&buf[..size], b"06\r\nfoobar\r\n");
+
+        sender.finish();
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(&buf[..size], b"00\r\n\r\n");
+        assert!(encoder.is_idle());
+
+        // Content-Length
+        let response = Response::new(
+            HttpVersion::V1_1,
+            StatusCode::new(200).unwrap(),
+            ReasonPhrase::new("OK").unwrap(),
+            BodyStream::from_chunks(vec![b"foo".to_vec(), b"bar".to_vec()]).with_length(6),
+        );
+        let mut encoder = ResponseEncoder::new(StreamingBodyEncoder::new());
+        track_try_unwrap!(encoder.start_encoding(response));
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert_eq!(buf, b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nfoobar");
+
+        // Length mismatch
+        let mut encoder = StreamingBodyEncoder::new();
+        let stream = BodyStream::from_chunks(vec![b"foo".to_vec()]).with_length(2);
+        track_try_unwrap!(encoder.start_encoding(stream));
+        assert_eq!(
+            encoder
+                .encode(&mut [0; 32], Eos::new(false))
+                .err()
+                .map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+
+        // The rejected chunk is never encoded
+        let mut encoder = BodyStreamEncoder::default();
+        let stream =
+            BodyStream::from_chunks(vec![b"foo".to_vec(), b"barbaz".to_vec()]).with_length(5);
+        track_try_unwrap!(encoder.start_encoding(stream));
+        let mut buf = [0; 32];
+        assert!(encoder.encode(&mut buf, Eos::new(false)).is_err());
+        assert_eq!(encoder.requiring_bytes(), ByteCount::Finite(2));
+        assert!(encoder.encode(&mut buf, Eos::new(false)).is_err());
+    }
+
+    #[test]
+    fn streaming_body_encoder_wait_ready_works() {
+        use std::thread;
+        use std::time::Duration;
+
+        let (sender, stream) = BodyStream::channel();
+        let mut encoder = StreamingBodyEncoder::new();
+        track_try_unwrap!(encoder.start_encoding(stream));
+        assert!(!track_try_unwrap!(
+            encoder.wait_ready(Some(Duration::from_millis(10)))
+        ));
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            track_try_unwrap!(sender.send(b"foo".to_vec()));
+            sender
+        });
+        assert!(track_try_unwrap!(encoder.wait_ready(None)));
+        let mut buf = [0; 32];
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(&buf[..size], b"03\r\nfoo\r\n");
+
+        handle.join().unwrap().finish();
+        assert!(track_try_unwrap!(encoder.wait_ready(None)));
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(&buf[..size], b"00\r\n\r\n");
+        assert!(encoder.is_idle());
+        assert!(track_try_unwrap!(encoder.wait_ready(None)));
+
+        // The sender is dropped without finishing the stream
+        let (sender, stream) = BodyStream::channel();
+        track_try_unwrap!(encoder.start_encoding(stream));
+        drop(sender);
+        assert_eq!(
+            encoder.wait_ready(None).err().map(|e| *e.kind()),
+            Some(ErrorKind::UnexpectedEos)
+        );
+    }
+}
@@ -1,25 +1,38 @@
-use bytecodec::bytes::BytesEncoder;
 use bytecodec::combinator::Slice;
 use bytecodec::{ByteCount, Decode, DecodeExt, Encode, Eos, Error, ErrorKind, Result};
-use std::io::Write;
+use std::io::{IoSlice, Write};
 
 use util::CrlfDecoder;
-use {BodyEncode, HeaderField, HeaderMut};
+use {BodyEncode, EncodeVectored, HeaderField, HeaderMut, VectoredBytesEncoder};
 
 #[derive(Debug, Default)]
 pub struct ChunkedBodyEncoder<E> {
     inner: E,
-    delim: BytesEncoder<[u8; 2]>,
-    last: BytesEncoder<[u8; 7]>,
+    delim: VectoredBytesEncoder<&'static [u8]>,
+    last: VectoredBytesEncoder<&'static [u8]>,
+
+    // Used only for vectored encoding
+    size_line: VectoredBytesEncoder<Vec<u8>>,
+    chunk_remaining: usize,
 }
 impl<E> ChunkedBodyEncoder<E> {
     pub fn new(inner: E) -> Self {
         ChunkedBodyEncoder {
             inner,
-            delim: BytesEncoder::new(),
-            last: BytesEncoder::new(),
+            delim: VectoredBytesEncoder::new(),
+            last: VectoredBytesEncoder::new(),
+            size_line: VectoredBytesEncoder::new(),
+            chunk_remaining: 0,
         }
     }
+
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
 }
 impl<E: Encode> Encode for ChunkedBodyEncoder<E> {
     type Item = E::Item;
@@ -72,9 +85,9 @@ impl<E: Encode> Encode for ChunkedBodyEncoder<E> {
 
         track!(write!(buf, "{:01$x}\r\n", size, offset - 2).map_err(Error::from))?;
         if self.inner.is_idle() && size != 0 {
-            track!(self.last.start_encoding(*b"\r\n0\r\n\r\n"))?;
+            track!(self.last.start_encoding(b"\r\n0\r\n\r\n"))?;
         } else {
-            track!(self.delim.start_encoding(*b"\r\n"))?;
+            track!(self.delim.start_encoding(b"\r\n"))?;
         }
         offset += track!(self.encode(&mut buf[size..], eos))?;
 
@@ -104,6 +117,55 @@ impl<E: Encode> BodyEncode for ChunkedBodyEncoder<E> {
         Ok(())
     }
 }
+impl<E: EncodeVectored> EncodeVectored for ChunkedBodyEncoder<E> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        if self.chunk_remaining == 0 && self.last.is_idle() && !self.inner.is_idle() {
+            // Makes a chunk from all the bytes currently available
+            let size = {
+                let mut data = Vec::new();
+                track!(self.inner.pending_slices(&mut data))?;
+                data.iter().map(|s| s.len()).sum::<usize>()
+            };
+            if size != 0 {
+                let size_line = format!("{:x}\r\n", size).into_bytes();
+                track!(self.size_line.start_encoding(size_line))?;
+                self.chunk_remaining = size;
+            } else if self.inner.is_idle() {
+                track!(self.last.start_encoding(b"0\r\n\r\n"))?;
+            }
+        }
+
+        track!(self.delim.pending_slices(slices))?;
+        track!(self.last.pending_slices(slices))?;
+        track!(self.size_line.pending_slices(slices))?;
+        if self.chunk_remaining != 0 {
+            track!(self.inner.pending_slices(slices))?;
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, mut size: usize) -> Result<()> {
+        size -= self.delim.consume_up_to(size);
+        size -= self.last.consume_up_to(size);
+        size -= self.size_line.consume_up_to(size);
+        if size == 0 {
+            return Ok(());
+        }
+
+        track_assert!(size <= self.chunk_remaining, ErrorKind::InvalidInput;
+                      size, self.chunk_remaining);
+        track!(self.inner.consume(size))?;
+        self.chunk_remaining -= size;
+        if self.chunk_remaining == 0 {
+            if self.inner.is_idle() {
+                track!(self.last.start_encoding(b"\r\n0\r\n\r\n"))?;
+            } else {
+                track!(self.delim.start_encoding(b"\r\n"))?;
+            }
+        }
+        Ok(())
+    }
+}
 
 // FIXME:
 // - Support trailer part
@@ -125,6 +187,14 @@ impl<T: Decode> ChunkedBodyDecoder<T> {
         }
     }
 
+    pub fn inner_ref(&self) -> &T {
+        self.inner.inner_ref()
+    }
+
+    pub fn inner_mut(&mut self) -> &mut T {
+        self.inner.inner_mut()
+    }
+
     pub fn into_inner(self) -> T {
         self.inner.into_inner()
     }
@@ -157,7 +227,12 @@ impl<T: Decode> Decode for ChunkedBodyDecoder<T> {
                 self.crlf = Some(CrlfDecoder::default());
             }
             if !self.inner.is_suspended() {
-                offset += track!(self.inner.decode(&buf[offset..], eos))?;
+                let size = track!(self.inner.decode(&buf[offset..], eos))?;
+                if size == 0 {
+                    // The inner decoder cannot consume more bytes for now
+                    return Ok(offset);
+                }
+                offset += size;
             }
         }
         track_assert!(!eos.is_reached(), ErrorKind::UnexpectedEos);
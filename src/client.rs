@@ -0,0 +1,760 @@
+//! A minimal blocking HTTP/1.1 client.
+//!
+//! `Client` sends requests over an arbitrary `Read + Write` stream (e.g., `TcpStream`)
+//! by using `RequestEncoder`, and decodes the responses by using `ResponseDecoder`.
+//!
+//! # Examples
+//!
+//! The following example uses `recording::ReplayServer`, which requires the `mock` feature.
+//!
+//! ```
+//! # extern crate httpcodec;
+//! # #[cfg(feature = "mock")]
+//! # fn main() {
+//! use httpcodec::client::Client;
+//! use httpcodec::recording::{Exchange, ReplayServer, Replayer};
+//! use httpcodec::Request;
+//! use std::net::TcpStream;
+//!
+//! let replayer = Replayer::new(vec![Exchange {
+//!     request: b"GET /foo HTTP/1.1\r\n\r\n".to_vec(),
+//!     response: b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar".to_vec(),
+//! }])
+//! .unwrap();
+//! let server = ReplayServer::start(replayer).unwrap();
+//!
+//! let stream = TcpStream::connect(server.local_addr()).unwrap();
+//! let mut client = Client::new(stream);
+//! client.set_host(&server.local_addr().to_string());
+//!
+//! let request = Request::builder().request_target("/foo").build(Vec::new()).unwrap();
+//! let response = client.send(request).unwrap();
+//! assert_eq!(response.status_code().as_u16(), 200);
+//! assert_eq!(response.body(), b"bar");
+//! assert!(client.is_reusable());
+//! # }
+//! # #[cfg(not(feature = "mock"))]
+//! # fn main() {}
+//! ```
+use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+use bytecodec::{Decode, Encode, Eos, Error, ErrorKind, Result};
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use util;
+use {
+    BodyDecoder, BodyEncoder, HeaderField, HttpVersion, Method, Request, RequestEncoder,
+    RequestTarget, Response, ResponseDecoder,
+};
+
+type Encoder = RequestEncoder<BodyEncoder<BytesEncoder<Vec<u8>>>>;
+type Decoder = ResponseDecoder<BodyDecoder<RemainingBytesDecoder>>;
+
+/// A function that returns the read timeout of a stream.
+pub type GetReadTimeout<S> = fn(&S) -> io::Result<Option<Duration>>;
+
+/// A function that sets the read timeout of a stream.
+///
+/// If the timeout expires, a read operation on the stream is expected to fail with
+/// an `io::ErrorKind::WouldBlock` or `io::ErrorKind::TimedOut` error.
+pub type SetReadTimeout<S> = fn(&mut S, Option<Duration>) -> io::Result<()>;
+
+/// Blocking HTTP/1.1 client that works on a single connection.
+///
+/// The connection is reused for subsequent requests as long as both peers keep it alive
+/// (see `is_reusable` method).
+///
+/// If a request has the `Expect: 100-continue` field,
+/// the client holds back its body until a `100 Continue` response is received.
+/// If a final response is received instead, the body is not sent and the connection is not reused.
+/// If neither is received within a timeout (see `set_continue_timeout` method),
+/// the body is sent anyway as [RFC 7231 section 5.1.1] suggests,
+/// because the server may not support the expectation.
+///
+/// Waiting for a `100 Continue` response requires a way to set the read timeout of the stream,
+/// which is given by `with_read_timeout` function.
+/// A client made by `new` function does not wait, and sends the body right after the head.
+///
+/// [RFC 7231 section 5.1.1]: https://tools.ietf.org/html/rfc7231#section-5.1.1
+#[derive(Debug)]
+pub struct Client<S> {
+    stream: S,
+    host: Option<String>,
+    max_redirects: usize,
+    continue_timeout: Duration,
+    read_timeout: Option<(GetReadTimeout<S>, SetReadTimeout<S>)>,
+    buf: Vec<u8>,
+    eos: bool,
+    reusable: bool,
+}
+impl<S: Read + Write> Client<S> {
+    /// Makes a new `Client` instance that sends requests over `stream`.
+    ///
+    /// The client made by this function does not wait for `100 Continue` responses.
+    pub fn new(stream: S) -> Self {
+        Client {
+            stream,
+            host: None,
+            max_redirects: 0,
+            continue_timeout: Duration::from_secs(1),
+            read_timeout: None,
+            buf: Vec::new(),
+            eos: false,
+            reusable: true,
+        }
+    }
+
+    /// Makes a new `Client` instance that sends requests over `stream`, and
+    /// uses the given functions for bounding the time to wait for a `100 Continue` response.
+    ///
+    /// The read timeout of `stream` is restored after waiting.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # extern crate httpcodec;
+    /// use httpcodec::client::Client;
+    /// use std::net::TcpStream;
+    ///
+    /// # fn main() {
+    /// let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+    /// let client = Client::with_read_timeout(
+    ///     stream,
+    ///     |s| s.read_timeout(),
+    ///     |s, timeout| s.set_read_timeout(timeout),
+    /// );
+    /// # let _ = client;
+    /// # }
+    /// ```
+    pub fn with_read_timeout(
+        stream: S,
+        get_read_timeout: GetReadTimeout<S>,
+        set_read_timeout: SetReadTimeout<S>,
+    ) -> Self {
+        let mut client = Self::new(stream);
+        client.read_timeout = Some((get_read_timeout, set_read_timeout));
+        client
+    }
+
+    /// Sets the value of the `Host` field added to requests that do not have it.
+    ///
+    /// If this is not set, the `Host` field is derived from the request target
+    /// if it is in the absolute-form (e.g., `http://example.com/foo`).
+    pub fn set_host(&mut self, host: &str) {
+        self.host = Some(host.to_owned());
+    }
+
+    /// Sets the maximum number of redirects followed by a `send` call.
+    ///
+    /// Only redirects to the same origin are followed because the client
+    /// cannot open new connections.
+    /// For `303 See Other` (and `301` and `302` replies to `POST` requests),
+    /// the redirected request is sent as a `GET` request without body.
+    ///
+    /// The default value is `0` (i.e., redirect responses are returned as they are).
+    pub fn set_max_redirects(&mut self, max: usize) {
+        self.max_redirects = max;
+    }
+
+    /// Sets the maximum time to wait for a `100 Continue` response
+    /// before sending the body of a request that has the `Expect: 100-continue` field.
+    ///
+    /// This is used only by the clients made by `with_read_timeout` function.
+    ///
+    /// The default value is one second.
+    pub fn set_continue_timeout(&mut self, timeout: Duration) {
+        self.continue_timeout = timeout;
+    }
+
+    /// Returns `true` if the connection can be used for sending the next request, otherwise `false`.
+    pub fn is_reusable(&self) -> bool {
+        self.reusable
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn stream_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn stream_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Takes ownership of the instance, and returns the underlying stream.
+    pub fn into_stream(self) -> S {
+        self.stream
+    }
+
+    /// Sends the given request, and returns the response to it.
+    ///
+    /// # Errors
+    ///
+    /// If the request has no `Host` field and it cannot be derived (see `set_host` method),
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    ///
+    /// If the connection is no longer reusable, an `ErrorKind::Other` error will be returned.
+    pub fn send<B: AsRef<[u8]>>(&mut self, request: Request<B>) -> Result<Response<Vec<u8>>> {
+        let mut request = request.map_body(|b| b.as_ref().to_vec());
+        track!(self.add_host_field(&mut request))?;
+
+        let mut redirects = 0;
+        loop {
+            let next = if redirects < self.max_redirects {
+                Some(request.clone())
+            } else {
+                None
+            };
+            let response = track!(self.send_once(request))?;
+            let next = match next {
+                Some(ref next) if self.reusable => track!(redirect_request(next, &response))?,
+                _ => None,
+            };
+            match next {
+                None => return Ok(response),
+                Some(next) => {
+                    request = next;
+                    redirects += 1;
+                }
+            }
+        }
+    }
+
+    fn add_host_field(&self, request: &mut Request<Vec<u8>>) -> Result<()> {
+        if request.header().get_field("Host").is_some() {
+            return Ok(());
+        }
+        let host = absolute_form_authority(request.request_target().as_str())
+            .map(|s| s.to_owned())
+            .or_else(|| self.host.clone());
+        match host {
+            Some(host) => {
+                let field = track!(HeaderField::new("Host", &host))?;
+                request.header_mut().add_field(field);
+            }
+            None => {
+                track_assert_eq!(
+                    request.http_version(),
+                    HttpVersion::V1_0,
+                    ErrorKind::InvalidInput,
+                    "No `Host` field"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn send_once(&mut self, request: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        track_assert!(
+            self.reusable,
+            ErrorKind::Other,
+            "The connection is not reusable"
+        );
+        let keep_alive = util::is_keep_alive(request.http_version(), &request.header());
+
+        let mut decoder = Decoder::new(BodyDecoder::new(RemainingBytesDecoder::new()));
+        decoder.set_head_request(request.method().as_str() == "HEAD");
+
+        let mut encoder = Encoder::new(BodyEncoder::new(BytesEncoder::new()));
+        encoder.set_expect_continue(true);
+        track!(encoder.start_encoding(request))?;
+        track!(self.write_request(&mut encoder))?;
+        if encoder.is_suspended() && track!(self.wait_for_continue(&mut decoder))? {
+            encoder.resume();
+            track!(self.write_request(&mut encoder))?;
+        }
+
+        let response = track!(self.read_response(&mut decoder))?;
+        self.reusable = !encoder.is_suspended()
+            && encoder.is_idle()
+            && keep_alive
+            && util::is_keep_alive(response.http_version(), &response.header())
+            && !self.eos;
+        Ok(response)
+    }
+
+    fn write_request(&mut self, encoder: &mut Encoder) -> Result<()> {
+        let mut buf = [0; 4096];
+        loop {
+            let size = track!(encoder.encode(&mut buf, Eos::new(false)))?;
+            if size == 0 {
+                break;
+            }
+            track!(self.stream.write_all(&buf[..size]).map_err(Error::from))?;
+        }
+        track!(self.stream.flush().map_err(Error::from))?;
+        Ok(())
+    }
+
+    fn wait_for_continue(&mut self, decoder: &mut Decoder) -> Result<bool> {
+        let (get_read_timeout, set_read_timeout) = match self.read_timeout {
+            None => return Ok(true),
+            Some(fns) => fns,
+        };
+        let read_timeout = track!(get_read_timeout(&self.stream).map_err(Error::from))?;
+        decoder.set_keep_interim_responses(true);
+        let deadline = Instant::now() + self.continue_timeout;
+        let result = self.wait_for_continue_until(decoder, deadline, set_read_timeout);
+        track!(set_read_timeout(&mut self.stream, read_timeout).map_err(Error::from))?;
+        result
+    }
+
+    fn wait_for_continue_until(
+        &mut self,
+        decoder: &mut Decoder,
+        deadline: Instant,
+        set_read_timeout: SetReadTimeout<S>,
+    ) -> Result<bool> {
+        loop {
+            track!(self.decode_buffered(decoder))?;
+            while let Some(interim) = decoder.pop_interim_response() {
+                if interim.status_code().as_u16() == 100 {
+                    return Ok(true);
+                }
+            }
+            if decoder.is_head_decoded() {
+                return Ok(false);
+            }
+
+            track_assert!(!self.eos, ErrorKind::UnexpectedEos);
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(true);
+            }
+            track!(set_read_timeout(&mut self.stream, Some(deadline - now)).map_err(Error::from))?;
+            match self.read_buf() {
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    return Ok(true);
+                }
+                result => track!(result.map_err(Error::from))?,
+            }
+        }
+    }
+
+    fn read_response(&mut self, decoder: &mut Decoder) -> Result<Response<Vec<u8>>> {
+        loop {
+            track!(self.decode_buffered(decoder))?;
+            if decoder.is_idle() {
+                return track!(decoder.finish_decoding());
+            }
+            track!(self.fill_buf())?;
+        }
+    }
+
+    fn decode_buffered(&mut self, decoder: &mut Decoder) -> Result<()> {
+        let size = track!(decoder.decode(&self.buf, Eos::new(self.eos)))?;
+        self.buf.drain(..size);
+        Ok(())
+    }
+
+    fn fill_buf(&mut self) -> Result<()> {
+        track_assert!(!self.eos, ErrorKind::UnexpectedEos);
+        track!(self.read_buf().map_err(Error::from))
+    }
+
+    fn read_buf(&mut self) -> io::Result<()> {
+        let mut buf = [0; 4096];
+        let size = self.stream.read(&mut buf)?;
+        if size == 0 {
+            self.eos = true;
+        } else {
+            self.buf.extend_from_slice(&buf[..size]);
+        }
+        Ok(())
+    }
+}
+
+fn absolute_form_authority(target: &str) -> Option<&str> {
+    let scheme = "http://";
+    if target.len() < scheme.len() || !target[..scheme.len()].eq_ignore_ascii_case(scheme) {
+        return None;
+    }
+    let rest = &target[scheme.len()..];
+    let end = rest.find(['/', '?']).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+fn redirect_request(
+    request: &Request<Vec<u8>>,
+    response: &Response<Vec<u8>>,
+) -> Result<Option<Request<Vec<u8>>>> {
+    let method = request.method().as_str();
+    let to_get = match response.status_code().as_u16() {
+        301 | 302 => method == "POST",
+        303 => method != "HEAD",
+        307 | 308 => false,
+        _ => return Ok(None),
+    };
+    let response_header = response.header();
+    let location = match response_header.get_field("Location") {
+        None => return Ok(None),
+        Some(location) => location,
+    };
+    let target = if location.starts_with('/') && !location.starts_with("//") {
+        location
+    } else {
+        let request_header = request.header();
+        let host = request_header.get_field("Host").unwrap_or("");
+        match absolute_form_authority(location) {
+            Some(authority) if authority.eq_ignore_ascii_case(host) => {
+                match &location["http://".len() + authority.len()..] {
+                    "" => "/",
+                    path => path,
+                }
+            }
+            _ => return Ok(None),
+        }
+    };
+
+    let (method, body) = if to_get {
+        (track!(Method::new("GET"))?, Vec::new())
+    } else {
+        (request.method(), request.body().clone())
+    };
+    let target = track!(RequestTarget::new(target))?;
+    let mut next = Request::new(method, target, request.http_version(), body);
+    for field in request.header().fields() {
+        let name = field.name();
+        let is_body_field = [
+            "Content-Length",
+            "Content-Type",
+            "Transfer-Encoding",
+            "Expect",
+        ]
+        .iter()
+        .any(|n| name.eq_ignore_ascii_case(n));
+        if !(to_get && is_body_field) {
+            next.header_mut().add_field(field);
+        }
+    }
+    Ok(Some(next))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::io::{self, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+
+    use super::*;
+    use local_server::{LocalServer, Reply};
+    use RequestDecoder;
+
+    fn reply(bytes: &[u8]) -> Reply {
+        Reply {
+            bytes: bytes.to_vec(),
+            close: false,
+        }
+    }
+
+    fn request(method: &str, target: &str, body: &[u8]) -> Request<Vec<u8>> {
+        track_try_unwrap!(Request::builder()
+            .method(method)
+            .request_target(target)
+            .build(body.to_vec()))
+    }
+
+    /// A stream that returns `input` chunk by chunk.
+    ///
+    /// An empty chunk makes a read operation fail as if the read timeout expired
+    /// (if no read timeout is set, it is skipped as if a blocking read waited for the next chunk).
+    struct ScriptedStream {
+        input: VecDeque<Vec<u8>>,
+        output: Vec<u8>,
+        read_timeout: Option<Duration>,
+    }
+    impl ScriptedStream {
+        fn new(input: &[u8]) -> Self {
+            Self::with_chunks(&[input])
+        }
+
+        fn with_chunks(chunks: &[&[u8]]) -> Self {
+            ScriptedStream {
+                input: chunks.iter().map(|c| c.to_vec()).collect(),
+                output: Vec::new(),
+                read_timeout: None,
+            }
+        }
+    }
+    impl Read for ScriptedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut chunk = match self.input.pop_front() {
+                None => return Ok(0),
+                Some(chunk) => chunk,
+            };
+            if chunk.is_empty() {
+                if self.read_timeout.is_some() {
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                return self.read(buf);
+            }
+            let size = chunk.len().min(buf.len());
+            buf[..size].copy_from_slice(&chunk[..size]);
+            if size < chunk.len() {
+                self.input.push_front(chunk.split_off(size));
+            }
+            Ok(size)
+        }
+    }
+    impl Write for ScriptedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn scripted_client(stream: ScriptedStream) -> Client<ScriptedStream> {
+        let mut client = Client::with_read_timeout(
+            stream,
+            |s| Ok(s.read_timeout),
+            |s, timeout| {
+                s.read_timeout = timeout;
+                Ok(())
+            },
+        );
+        client.set_host("example.com");
+        client
+    }
+
+    /// Starts a server that echoes request bodies back but never sends `100 Continue` responses.
+    fn start_echo_server_without_continue() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                thread::spawn(move || {
+                    let mut decoder =
+                        RequestDecoder::new(BodyDecoder::for_request(RemainingBytesDecoder::new()));
+                    let mut pending = Vec::new();
+                    let mut buf = [0; 4096];
+                    loop {
+                        let size = track_try_unwrap!(decoder.decode(&pending, Eos::new(false)));
+                        pending.drain(..size);
+                        if decoder.is_idle() {
+                            let request = track_try_unwrap!(decoder.finish_decoding());
+                            let mut bytes = format!(
+                                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                                request.body().len()
+                            )
+                            .into_bytes();
+                            bytes.extend_from_slice(request.body());
+                            stream.write_all(&bytes).unwrap();
+                            continue;
+                        }
+                        let size = stream.read(&mut buf).unwrap();
+                        if size == 0 {
+                            return;
+                        }
+                        pending.extend_from_slice(&buf[..size]);
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn client_works() {
+        let server = track_try_unwrap!(LocalServer::start(|request| {
+            match request.request_target().as_str() {
+                "/foo" if request.method().as_str() == "HEAD" => {
+                    reply(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n")
+                }
+                "/foo" => reply(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar"),
+                "/empty" => reply(b"HTTP/1.1 204 No Content\r\n\r\n"),
+                "/host" | "http://foo.test/host" => {
+                    let header = request.header();
+                    let host = header.get_field("Host").unwrap_or("");
+                    let mut bytes =
+                        format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", host.len());
+                    bytes.push_str(host);
+                    reply(bytes.as_bytes())
+                }
+                _ => Reply {
+                    bytes: b"HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".to_vec(),
+                    close: true,
+                },
+            }
+        }));
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut client = Client::new(stream);
+        assert!(client.send(request("GET", "/foo", b"")).is_err());
+
+        client.set_host("example.com");
+        let response = track_try_unwrap!(client.send(request("GET", "/foo", b"")));
+        assert_eq!(response.body(), b"bar");
+
+        let response = track_try_unwrap!(client.send(request("HEAD", "/foo", b"")));
+        assert_eq!(response.header().get_field("Content-Length"), Some("3"));
+        assert!(response.body().is_empty());
+
+        let response = track_try_unwrap!(client.send(request("DELETE", "/empty", b"")));
+        assert_eq!(response.status_code().as_u16(), 204);
+
+        let response = track_try_unwrap!(client.send(request("GET", "/host", b"")));
+        assert_eq!(response.body(), b"example.com");
+
+        let response = track_try_unwrap!(client.send(request("GET", "http://foo.test/host", b"")));
+        assert_eq!(response.body(), b"foo.test");
+        assert!(client.is_reusable());
+
+        let response = track_try_unwrap!(client.send(request("GET", "/bar", b"")));
+        assert_eq!(response.status_code().as_u16(), 404);
+        assert!(!client.is_reusable());
+        assert!(client.send(request("GET", "/foo", b"")).is_err());
+    }
+
+    #[test]
+    fn client_redirect_works() {
+        let server = track_try_unwrap!(LocalServer::start(|request| {
+            match request.request_target().as_str() {
+                "/old" => reply(b"HTTP/1.1 302 Found\r\nLocation: http://example.com/new\r\nContent-Length: 0\r\n\r\n"),
+                "/new" => {
+                    let mut bytes = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", request.method().as_str().len() + request.body().len());
+                    bytes.push_str(request.method().as_str());
+                    let mut bytes = bytes.into_bytes();
+                    bytes.extend_from_slice(request.body());
+                    reply(&bytes)
+                }
+                "/temporary" => reply(b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n"),
+                _ => reply(b"HTTP/1.1 301 Moved Permanently\r\nLocation: http://other.test/\r\nContent-Length: 0\r\n\r\n"),
+            }
+        }));
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut client = Client::new(stream);
+        client.set_host("example.com");
+
+        let response = track_try_unwrap!(client.send(request("POST", "/old", b"foo")));
+        assert_eq!(response.status_code().as_u16(), 302);
+
+        client.set_max_redirects(1);
+        let response = track_try_unwrap!(client.send(request("POST", "/old", b"foo")));
+        assert_eq!(response.body(), b"GET");
+
+        let response = track_try_unwrap!(client.send(request("POST", "/temporary", b"foo")));
+        assert_eq!(response.body(), b"POSTfoo");
+
+        let response = track_try_unwrap!(client.send(request("GET", "/other", b"")));
+        assert_eq!(response.status_code().as_u16(), 301);
+    }
+
+    #[test]
+    fn client_expect_continue_works() {
+        let mut request = request("PUT", "/foo", b"bar");
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let stream = ScriptedStream::new(
+            b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n",
+        );
+        let mut client = scripted_client(stream);
+        let response = track_try_unwrap!(client.send(request.clone()));
+        assert_eq!(response.status_code().as_u16(), 200);
+        assert!(client.stream_ref().output.ends_with(b"\r\n\r\nbar"));
+        assert_eq!(client.stream_ref().read_timeout, None);
+        assert!(client.is_reusable());
+
+        let stream =
+            ScriptedStream::new(b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 0\r\n\r\n");
+        let mut client = scripted_client(stream);
+        let response = track_try_unwrap!(client.send(request.clone()));
+        assert_eq!(response.status_code().as_u16(), 417);
+        assert!(client.stream_ref().output.ends_with(b"\r\n\r\n"));
+        assert!(!client.is_reusable());
+
+        // The client stops waiting as soon as the head of a final response is received,
+        // even if its body has not arrived yet.
+        let stream = ScriptedStream::with_chunks(&[
+            b"HTTP/1.1 417 Expectation Failed\r\nContent-Length: 3\r\n\r\n",
+            b"",
+            b"qux",
+        ]);
+        let mut client = scripted_client(stream);
+        client.set_continue_timeout(Duration::from_secs(60));
+        let response = track_try_unwrap!(client.send(request));
+        assert_eq!(response.status_code().as_u16(), 417);
+        assert_eq!(response.body(), b"qux");
+        assert!(client.stream_ref().output.ends_with(b"\r\n\r\n"));
+        assert!(!client.is_reusable());
+    }
+
+    #[test]
+    fn client_expect_continue_with_local_server_works() {
+        let server = track_try_unwrap!(LocalServer::start(|request| {
+            let mut bytes = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                request.body().len()
+            )
+            .into_bytes();
+            bytes.extend_from_slice(request.body());
+            reply(&bytes)
+        }));
+        let mut request = request("PUT", "/foo", b"bar");
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let stream = TcpStream::connect(server.local_addr()).unwrap();
+        let mut client = Client::with_read_timeout(
+            stream,
+            |s| s.read_timeout(),
+            |s, timeout| s.set_read_timeout(timeout),
+        );
+        client.set_host("example.com");
+        client.set_continue_timeout(Duration::from_secs(60));
+
+        // The body is sent as soon as `LocalServer` answers with `100 Continue`.
+        let start = Instant::now();
+        for _ in 0..2 {
+            let response = track_try_unwrap!(client.send(request.clone()));
+            assert_eq!(response.status_code().as_u16(), 200);
+            assert_eq!(response.body(), b"bar");
+            assert!(client.is_reusable());
+        }
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn client_expect_continue_timeout_works() {
+        let addr = start_echo_server_without_continue();
+        let mut request = request("PUT", "/foo", b"bar");
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let read_timeout = Some(Duration::from_secs(5));
+        stream.set_read_timeout(read_timeout).unwrap();
+        let mut client = Client::with_read_timeout(
+            stream,
+            |s| s.read_timeout(),
+            |s, timeout| s.set_read_timeout(timeout),
+        );
+        client.set_host("example.com");
+        client.set_continue_timeout(Duration::from_millis(100));
+        for _ in 0..2 {
+            let response = track_try_unwrap!(client.send(request.clone()));
+            assert_eq!(response.status_code().as_u16(), 200);
+            assert_eq!(response.body(), b"bar");
+            assert!(client.is_reusable());
+        }
+        assert_eq!(client.stream_ref().read_timeout().unwrap(), read_timeout);
+
+        // A client made by `new` sends the body without waiting.
+        let stream = TcpStream::connect(addr).unwrap();
+        let mut client = Client::new(stream);
+        client.set_host("example.com");
+        client.set_continue_timeout(Duration::from_secs(60));
+        let response = track_try_unwrap!(client.send(request));
+        assert_eq!(response.body(), b"bar");
+    }
+}
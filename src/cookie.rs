@@ -0,0 +1,556 @@
+//! Helpers for the `Cookie` and `Set-Cookie` header fields.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate httpcodec;
+//! use httpcodec::cookie::{self, SameSite, SetCookie};
+//! use httpcodec::{Request, Response};
+//!
+//! # fn main() {
+//! // Server side
+//! let mut cookie = SetCookie::new("session", "38afes7a8").unwrap();
+//! cookie.set_path(Some("/")).unwrap();
+//! cookie.set_max_age(Some(3600));
+//! cookie.set_http_only(true);
+//! cookie.set_same_site(Some(SameSite::Lax));
+//!
+//! let mut response = Response::builder().build(()).unwrap();
+//! cookie::add_set_cookie(&mut response.header_mut(), &cookie).unwrap();
+//! assert_eq!(
+//!     response.header().get_field("Set-Cookie"),
+//!     Some("session=38afes7a8; Max-Age=3600; Path=/; HttpOnly; SameSite=Lax")
+//! );
+//!
+//! // Client side
+//! let header = response.header();
+//! let cookies = cookie::set_cookies(&header).collect::<Result<Vec<_>, _>>().unwrap();
+//! assert_eq!(cookies, vec![cookie]);
+//!
+//! let mut request = Request::builder().build(()).unwrap();
+//! cookie::add_cookies(&mut request.header_mut(), vec![("session", "38afes7a8"), ("lang", "en")])
+//!     .unwrap();
+//!
+//! // Server side
+//! let header = request.header();
+//! let pairs = cookie::cookies(&header).collect::<Vec<_>>();
+//! assert_eq!(pairs, vec![("session", "38afes7a8"), ("lang", "en")]);
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! - [RFC 6265] HTTP State Management Mechanism
+//!
+//! [RFC 6265]: https://tools.ietf.org/html/rfc6265
+use bytecodec::{Error, ErrorKind, Result};
+use std::fmt;
+use std::str::{FromStr, Split};
+
+use header::HeaderFields;
+use util;
+use {Header, HeaderField, HeaderMut, HttpDate};
+
+/// Returns an iterator over the cookie pairs in the `Cookie` fields of `header`.
+///
+/// Each item is a `(name, value)` pair of a cookie.
+/// Pairs that do not have `=` or have an empty name are skipped.
+///
+/// See [RFC 6265 section 4.2](https://tools.ietf.org/html/rfc6265#section-4.2).
+pub fn cookies<'a>(header: &'a Header) -> Cookies<'a> {
+    Cookies {
+        fields: header.fields(),
+        pairs: None,
+    }
+}
+
+/// Returns an iterator over the parsed `Set-Cookie` fields of `header`.
+pub fn set_cookies<'a>(header: &'a Header) -> SetCookies<'a> {
+    SetCookies {
+        fields: header.fields(),
+    }
+}
+
+/// Adds a `Cookie` field made from the given `(name, value)` pairs to `header`.
+///
+/// # Errors
+///
+/// If a name is not a "token" or a value is not a "cookie-value" defined in [RFC 6265],
+/// an `ErrorKind::InvalidInput` error will be returned.
+///
+/// [RFC 6265]: https://tools.ietf.org/html/rfc6265#section-4.1.1
+pub fn add_cookies<'a, I>(header: &mut HeaderMut, pairs: I) -> Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut value = String::new();
+    for (name, v) in pairs {
+        track!(check_name(name))?;
+        track!(check_value(v))?;
+        if !value.is_empty() {
+            value.push_str("; ");
+        }
+        value.push_str(name);
+        value.push('=');
+        value.push_str(v);
+    }
+    track_assert!(!value.is_empty(), ErrorKind::InvalidInput, "No cookies");
+    header.add_field(track!(HeaderField::new("Cookie", &value))?);
+    Ok(())
+}
+
+/// Adds a `Set-Cookie` field for `cookie` to `header`.
+///
+/// Each cookie has to be sent in its own `Set-Cookie` field,
+/// so this should be called for every cookie.
+pub fn add_set_cookie(header: &mut HeaderMut, cookie: &SetCookie) -> Result<()> {
+    let value = cookie.to_string();
+    header.add_field(track!(HeaderField::new("Set-Cookie", &value))?);
+    Ok(())
+}
+
+/// An iterator over the cookie pairs in `Cookie` fields.
+///
+/// This is created by calling `cookies` function.
+#[derive(Debug)]
+pub struct Cookies<'a> {
+    fields: HeaderFields<'a>,
+    pairs: Option<Split<'a, char>>,
+}
+impl<'a> Iterator for Cookies<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pair) = self.pairs.as_mut().and_then(|p| p.next()) {
+                let mut kv = pair.splitn(2, '=');
+                let name = kv.next().unwrap_or("").trim();
+                if let Some(value) = kv.next() {
+                    if !name.is_empty() {
+                        return Some((name, value.trim()));
+                    }
+                }
+                continue;
+            }
+
+            let field = self
+                .fields
+                .by_ref()
+                .find(|f| f.name().eq_ignore_ascii_case("Cookie"))?;
+            self.pairs = Some(field.value().split(';'));
+        }
+    }
+}
+
+/// An iterator over the parsed `Set-Cookie` fields.
+///
+/// This is created by calling `set_cookies` function.
+#[derive(Debug)]
+pub struct SetCookies<'a> {
+    fields: HeaderFields<'a>,
+}
+impl<'a> Iterator for SetCookies<'a> {
+    type Item = Result<SetCookie>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.fields
+            .by_ref()
+            .find(|f| f.name().eq_ignore_ascii_case("Set-Cookie"))
+            .map(|f| track!(f.value().parse()))
+    }
+}
+
+/// The value of the `SameSite` attribute.
+///
+/// See [RFC 6265bis](https://datatracker.ietf.org/doc/html/draft-ietf-httpbis-rfc6265bis#section-4.1.2.7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SameSite {
+    /// `SameSite=Strict`
+    Strict,
+
+    /// `SameSite=Lax`
+    Lax,
+
+    /// `SameSite=None`
+    None,
+}
+impl SameSite {
+    /// Returns the string representation of the value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// A cookie set by a `Set-Cookie` field.
+///
+/// The name and the value of the cookie, as well as the values of the attributes,
+/// are validated when they are set, so `to_string` always produces a valid field value.
+///
+/// See [RFC 6265 section 4.1](https://tools.ietf.org/html/rfc6265#section-4.1).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    expires: Option<HttpDate>,
+    max_age: Option<i64>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+impl SetCookie {
+    /// Makes a new `SetCookie` instance that has no attributes.
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a "token" or `value` is not a "cookie-value" defined in [RFC 6265],
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    ///
+    /// [RFC 6265]: https://tools.ietf.org/html/rfc6265#section-4.1.1
+    pub fn new(name: &str, value: &str) -> Result<Self> {
+        track!(check_name(name))?;
+        track!(check_value(value))?;
+        Ok(SetCookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            expires: None,
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        })
+    }
+
+    /// Returns the name of the cookie.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the cookie.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the value of the `Expires` attribute.
+    pub fn expires(&self) -> Option<HttpDate> {
+        self.expires
+    }
+
+    /// Returns the value of the `Max-Age` attribute.
+    pub fn max_age(&self) -> Option<i64> {
+        self.max_age
+    }
+
+    /// Returns the value of the `Domain` attribute.
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Returns the value of the `Path` attribute.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Returns `true` if the cookie has the `Secure` attribute.
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Returns `true` if the cookie has the `HttpOnly` attribute.
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    /// Returns the value of the `SameSite` attribute.
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+
+    /// Sets the value of the `Expires` attribute.
+    pub fn set_expires(&mut self, expires: Option<HttpDate>) {
+        self.expires = expires;
+    }
+
+    /// Sets the value of the `Max-Age` attribute.
+    pub fn set_max_age(&mut self, max_age: Option<i64>) {
+        self.max_age = max_age;
+    }
+
+    /// Sets the value of the `Domain` attribute.
+    ///
+    /// # Errors
+    ///
+    /// If the value contains control characters or `;`,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    pub fn set_domain(&mut self, domain: Option<&str>) -> Result<()> {
+        self.domain = track!(attribute_value(domain))?;
+        Ok(())
+    }
+
+    /// Sets the value of the `Path` attribute.
+    ///
+    /// # Errors
+    ///
+    /// If the value does not start with `/`, or contains control characters or `;`,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    pub fn set_path(&mut self, path: Option<&str>) -> Result<()> {
+        if let Some(path) = path {
+            track_assert!(path.starts_with('/'), ErrorKind::InvalidInput; path);
+        }
+        self.path = track!(attribute_value(path))?;
+        Ok(())
+    }
+
+    /// Sets whether the cookie has the `Secure` attribute.
+    pub fn set_secure(&mut self, secure: bool) {
+        self.secure = secure;
+    }
+
+    /// Sets whether the cookie has the `HttpOnly` attribute.
+    pub fn set_http_only(&mut self, http_only: bool) {
+        self.http_only = http_only;
+    }
+
+    /// Sets the value of the `SameSite` attribute.
+    pub fn set_same_site(&mut self, same_site: Option<SameSite>) {
+        self.same_site = same_site;
+    }
+}
+impl fmt::Display for SetCookie {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(ref expires) = self.expires {
+            write!(f, "; Expires={}", expires)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age)?;
+        }
+        if let Some(ref domain) = self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(ref path) = self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for SetCookie {
+    type Err = Error;
+
+    /// Parses the value of a `Set-Cookie` field.
+    ///
+    /// The name and the value of the cookie are strictly validated,
+    /// while unknown or malformed attributes are ignored as described in
+    /// [RFC 6265 section 5.2](https://tools.ietf.org/html/rfc6265#section-5.2).
+    fn from_str(s: &str) -> Result<Self> {
+        let mut items = s.split(';');
+        let pair = items.next().unwrap_or("");
+        let mut kv = pair.splitn(2, '=');
+        let name = kv.next().unwrap_or("").trim();
+        let value = track_assert_some!(kv.next(), ErrorKind::InvalidInput; pair).trim();
+        let mut cookie = track!(SetCookie::new(name, value))?;
+
+        for item in items {
+            let mut kv = item.splitn(2, '=');
+            let name = kv.next().unwrap_or("").trim();
+            let value = kv.next().map(|v| v.trim()).unwrap_or("");
+            if name.eq_ignore_ascii_case("Expires") {
+                if let Ok(expires) = value.parse() {
+                    cookie.expires = Some(expires);
+                }
+            } else if name.eq_ignore_ascii_case("Max-Age") {
+                let digits = value.strip_prefix('-').unwrap_or(value);
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    if let Ok(max_age) = value.parse() {
+                        cookie.max_age = Some(max_age);
+                    }
+                }
+            } else if name.eq_ignore_ascii_case("Domain") {
+                let domain = value.trim_start_matches('.');
+                if !domain.is_empty() {
+                    cookie.domain = Some(domain.to_owned());
+                }
+            } else if name.eq_ignore_ascii_case("Path") {
+                if value.starts_with('/') {
+                    cookie.path = Some(value.to_owned());
+                }
+            } else if name.eq_ignore_ascii_case("Secure") {
+                cookie.secure = true;
+            } else if name.eq_ignore_ascii_case("HttpOnly") {
+                cookie.http_only = true;
+            } else if name.eq_ignore_ascii_case("SameSite") {
+                cookie.same_site = if value.eq_ignore_ascii_case("Strict") {
+                    Some(SameSite::Strict)
+                } else if value.eq_ignore_ascii_case("Lax") {
+                    Some(SameSite::Lax)
+                } else if value.eq_ignore_ascii_case("None") {
+                    Some(SameSite::None)
+                } else {
+                    cookie.same_site
+                };
+            }
+        }
+        Ok(cookie)
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    track_assert!(!name.is_empty(), ErrorKind::InvalidInput);
+    track_assert!(
+        name.bytes().all(util::is_tchar),
+        ErrorKind::InvalidInput;
+        name
+    );
+    Ok(())
+}
+
+// https://tools.ietf.org/html/rfc6265#section-4.1.1
+fn check_value(value: &str) -> Result<()> {
+    let octets = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+    track_assert!(
+        octets.bytes().all(is_cookie_octet),
+        ErrorKind::InvalidInput;
+        value
+    );
+    Ok(())
+}
+
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+fn attribute_value(value: Option<&str>) -> Result<Option<String>> {
+    if let Some(value) = value {
+        track_assert!(
+            value.bytes().all(|b| (util::is_vchar(b) && b != b';') || b == b' '),
+            ErrorKind::InvalidInput;
+            value
+        );
+        track_assert!(
+            !value.starts_with(' ') && !value.ends_with(' '),
+            ErrorKind::InvalidInput;
+            value
+        );
+    }
+    Ok(value.map(|v| v.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Response;
+
+    #[test]
+    fn cookies_works() {
+        let response = track_try_unwrap!(Response::builder()
+            .header("Cookie", "foo=bar; baz=\"qux\"")
+            .header("Content-Length", "0")
+            .header("cookie", "broken; =empty; a=b=c;")
+            .build(()));
+        let header = response.header();
+        assert_eq!(
+            cookies(&header).collect::<Vec<_>>(),
+            vec![("foo", "bar"), ("baz", "\"qux\""), ("a", "b=c")]
+        );
+    }
+
+    #[test]
+    fn set_cookie_parse_works() {
+        let cookie: SetCookie = track_try_unwrap!(
+            "id=a3fWa; Expires=Wed, 21 Oct 2015 07:28:00 GMT; max-age=-1; Domain=.example.com; \
+             Path=/docs; Secure; httponly; SameSite=strict; Foo=bar"
+                .parse()
+        );
+        assert_eq!(cookie.name(), "id");
+        assert_eq!(cookie.value(), "a3fWa");
+        assert_eq!(
+            cookie.expires().map(|d| d.as_unix_secs()),
+            Some(1_445_412_480)
+        );
+        assert_eq!(cookie.max_age(), Some(-1));
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.path(), Some("/docs"));
+        assert!(cookie.is_secure());
+        assert!(cookie.is_http_only());
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+
+        let cookie: SetCookie = track_try_unwrap!("id=; Max-Age=foo; Path=docs".parse());
+        assert_eq!(cookie.value(), "");
+        assert_eq!(cookie.max_age(), None);
+        assert_eq!(cookie.path(), None);
+
+        let cookie: SetCookie = track_try_unwrap!("id=foo; Expires=tomorrow; Max-Age=+60".parse());
+        assert_eq!(cookie.expires(), None);
+        assert_eq!(cookie.max_age(), None);
+
+        let cookie: SetCookie = track_try_unwrap!("id=foo; Max-Age=60; Max-Age=-".parse());
+        assert_eq!(cookie.max_age(), Some(60));
+
+        assert!("id".parse::<SetCookie>().is_err());
+        assert!("=foo".parse::<SetCookie>().is_err());
+        assert!("id=foo bar".parse::<SetCookie>().is_err());
+    }
+
+    #[test]
+    fn set_cookie_serialization_works() {
+        let mut cookie = track_try_unwrap!(SetCookie::new("id", "a3fWa"));
+        cookie.set_expires(Some(track_try_unwrap!(HttpDate::from_unix_secs(
+            1_445_412_480
+        ))));
+        track_try_unwrap!(cookie.set_domain(Some("example.com")));
+        cookie.set_secure(true);
+        cookie.set_same_site(Some(SameSite::None));
+        assert_eq!(
+            cookie.to_string(),
+            "id=a3fWa; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Domain=example.com; Secure; \
+             SameSite=None"
+        );
+
+        assert!(SetCookie::new("i d", "foo").is_err());
+        assert!(SetCookie::new("id", "foo;bar").is_err());
+        assert!(cookie.set_path(Some("docs")).is_err());
+        assert!(cookie.set_domain(Some("example.com; Secure")).is_err());
+
+        let mut response = track_try_unwrap!(Response::builder().build(()));
+        track_try_unwrap!(add_set_cookie(&mut response.header_mut(), &cookie));
+        let other = track_try_unwrap!(SetCookie::new("lang", "en"));
+        track_try_unwrap!(add_set_cookie(&mut response.header_mut(), &other));
+        assert_eq!(
+            response
+                .header()
+                .fields()
+                .filter(|f| f.name() == "Set-Cookie")
+                .count(),
+            2
+        );
+
+        let header = response.header();
+        let parsed = track_try_unwrap!(set_cookies(&header).collect::<Result<Vec<_>>>());
+        assert_eq!(parsed, vec![cookie, other]);
+    }
+}
@@ -0,0 +1,354 @@
+use bytecodec::{Error, ErrorKind, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const LONG_WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// HTTP-date.
+///
+/// This is used as the value of the `Date`, `Last-Modified`, `If-Modified-Since`,
+/// `Expires` and `Retry-After` header fields.
+///
+/// `HttpDate` can be parsed from any of the three formats defined in [RFC 7231]
+/// (i.e., IMF-fixdate, the obsolete RFC 850 format and ANSI C's asctime() format),
+/// and it is always formatted as IMF-fixdate.
+///
+/// Dates before 1970-01-01 or after 9999-12-31 are not supported.
+/// A two-digit year in the RFC 850 format is interpreted as a year between 1970 and 2069.
+///
+/// [RFC 7231]: https://tools.ietf.org/html/rfc7231#section-7.1.1.1
+///
+/// # Examples
+///
+/// ```
+/// # extern crate httpcodec;
+/// use httpcodec::{HttpDate, Response};
+///
+/// # fn main() {
+/// let response = Response::builder()
+///     .header("Last-Modified", "Sunday, 06-Nov-94 08:49:37 GMT")
+///     .build(())
+///     .unwrap();
+/// let date: HttpDate = response.header().parse_field("Last-Modified").unwrap().unwrap();
+/// assert_eq!(date.as_unix_secs(), 784111777);
+/// assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HttpDate(u64);
+impl HttpDate {
+    /// The largest supported number of seconds since the Unix epoch (i.e., 9999-12-31T23:59:59Z).
+    pub const MAX_UNIX_SECS: u64 = 253_402_300_799;
+
+    /// Makes a new `HttpDate` instance from the number of seconds since the Unix epoch.
+    ///
+    /// # Errors
+    ///
+    /// If `secs` is greater than `HttpDate::MAX_UNIX_SECS`,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    pub fn from_unix_secs(secs: u64) -> Result<Self> {
+        track_assert!(secs <= Self::MAX_UNIX_SECS, ErrorKind::InvalidInput; secs);
+        Ok(HttpDate(secs))
+    }
+
+    /// Makes a new `HttpDate` instance from `time`.
+    ///
+    /// The sub-second part of `time` is truncated.
+    ///
+    /// # Errors
+    ///
+    /// If `time` is out of the supported range, an `ErrorKind::InvalidInput` error will be returned.
+    pub fn from_system_time(time: SystemTime) -> Result<Self> {
+        match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => track!(Self::from_unix_secs(d.as_secs())),
+            Err(_) => track_panic!(ErrorKind::InvalidInput, "Before the Unix epoch"),
+        }
+    }
+
+    /// Returns the number of seconds since the Unix epoch.
+    pub fn as_unix_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// Converts the date to `SystemTime`.
+    pub fn to_system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0)
+    }
+
+    fn from_parts(parts: DateParts) -> Result<Self> {
+        let DateParts {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        } = parts;
+        track_assert!((1970..=9999).contains(&year), ErrorKind::InvalidInput; year);
+        track_assert!(1 <= day && day <= days_in_month(year, month), ErrorKind::InvalidInput; day);
+        track_assert!(hour < 24, ErrorKind::InvalidInput; hour);
+        track_assert!(minute < 60, ErrorKind::InvalidInput; minute);
+        track_assert!(second <= 60, ErrorKind::InvalidInput; second); // leap second
+
+        let days = days_from_civil(year, month, day);
+        let secs = days * 86_400 + hour * 3_600 + minute * 60 + second;
+        Ok(HttpDate(secs.min(Self::MAX_UNIX_SECS)))
+    }
+}
+impl fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let days = self.0 / 86_400;
+        let secs = self.0 % 86_400;
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[((days + 4) % 7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            secs / 3_600,
+            secs % 3_600 / 60,
+            secs % 60
+        )
+    }
+}
+impl FromStr for HttpDate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = parse_imf_fixdate(s)
+            .or_else(|| parse_rfc850_date(s))
+            .or_else(|| parse_asctime_date(s));
+        let parts = track_assert_some!(parts, ErrorKind::InvalidInput; s);
+        track!(Self::from_parts(parts); s)
+    }
+}
+impl From<HttpDate> for SystemTime {
+    fn from(f: HttpDate) -> Self {
+        f.to_system_time()
+    }
+}
+
+#[derive(Debug)]
+struct DateParts {
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+// e.g., "Sun, 06 Nov 1994 08:49:37 GMT"
+fn parse_imf_fixdate(s: &str) -> Option<DateParts> {
+    let mut tokens = s.split(' ');
+    let weekday = tokens.next()?;
+    if !weekday.ends_with(',') || !WEEKDAYS.contains(&&weekday[..weekday.len() - 1]) {
+        return None;
+    }
+    let day = parse_digits(tokens.next()?, 2)?;
+    let month = parse_month(tokens.next()?)?;
+    let year = parse_digits(tokens.next()?, 4)?;
+    let (hour, minute, second) = parse_time(tokens.next()?)?;
+    if tokens.next()? != "GMT" || tokens.next().is_some() {
+        return None;
+    }
+    Some(DateParts {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+// e.g., "Sunday, 06-Nov-94 08:49:37 GMT"
+fn parse_rfc850_date(s: &str) -> Option<DateParts> {
+    let mut tokens = s.split(' ');
+    let weekday = tokens.next()?;
+    if !weekday.ends_with(',') || !LONG_WEEKDAYS.contains(&&weekday[..weekday.len() - 1]) {
+        return None;
+    }
+    let mut date = tokens.next()?.split('-');
+    let day = parse_digits(date.next()?, 2)?;
+    let month = parse_month(date.next()?)?;
+    let year = parse_digits(date.next()?, 2)?;
+    if date.next().is_some() {
+        return None;
+    }
+    let (hour, minute, second) = parse_time(tokens.next()?)?;
+    if tokens.next()? != "GMT" || tokens.next().is_some() {
+        return None;
+    }
+
+    // Two-digit years are mapped onto the fixed window from 1970 to 2069.
+    // This is not the rule of RFC 7231 section 7.1.1.1 (a year that appears to be more than
+    // 50 years in the future means the most recent past year with the same last two digits),
+    // because that rule depends on the current time.
+    let year = if year < 70 { 2000 + year } else { 1900 + year };
+    Some(DateParts {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+// e.g., "Sun Nov  6 08:49:37 1994"
+fn parse_asctime_date(s: &str) -> Option<DateParts> {
+    let mut tokens = s.split(' ');
+    if !WEEKDAYS.contains(&tokens.next()?) {
+        return None;
+    }
+    let month = parse_month(tokens.next()?)?;
+    let day = match tokens.next()? {
+        "" => parse_digits(tokens.next()?, 1)?,
+        day => parse_digits(day, 2)?,
+    };
+    let (hour, minute, second) = parse_time(tokens.next()?)?;
+    let year = parse_digits(tokens.next()?, 4)?;
+    if tokens.next().is_some() {
+        return None;
+    }
+    Some(DateParts {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+fn parse_time(s: &str) -> Option<(u64, u64, u64)> {
+    let mut items = s.split(':');
+    let hour = parse_digits(items.next()?, 2)?;
+    let minute = parse_digits(items.next()?, 2)?;
+    let second = parse_digits(items.next()?, 2)?;
+    if items.next().is_some() {
+        return None;
+    }
+    Some((hour, minute, second))
+}
+
+fn parse_month(s: &str) -> Option<u64> {
+    MONTHS.iter().position(|&m| m == s).map(|i| i as u64 + 1)
+}
+
+fn parse_digits(s: &str, len: usize) -> Option<u64> {
+    if s.len() != len || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn is_leap_year(year: u64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn http_date_works() {
+        let expected = track_try_unwrap!(HttpDate::from_unix_secs(784_111_777));
+        for s in &[
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            let date: HttpDate = track_try_unwrap!(s.parse());
+            assert_eq!(date, expected);
+            assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        }
+
+        let date: HttpDate = track_try_unwrap!("Wed, 21 Oct 2015 07:28:00 GMT".parse());
+        assert_eq!(date.as_unix_secs(), 1_445_412_480);
+
+        let date: HttpDate = track_try_unwrap!("Tue, 29 Feb 2000 23:59:59 GMT".parse());
+        assert_eq!(date.to_string(), "Tue, 29 Feb 2000 23:59:59 GMT");
+
+        let date: HttpDate = track_try_unwrap!("Thursday, 01-Jan-70 00:00:00 GMT".parse());
+        assert_eq!(date.as_unix_secs(), 0);
+        assert_eq!(date.to_system_time(), UNIX_EPOCH);
+
+        let date: HttpDate = track_try_unwrap!("Fri Dec 31 23:59:59 9999".parse());
+        assert_eq!(date.as_unix_secs(), HttpDate::MAX_UNIX_SECS);
+        assert_eq!(date.to_string(), "Fri, 31 Dec 9999 23:59:59 GMT");
+    }
+
+    #[test]
+    fn invalid_http_date_works() {
+        for s in &[
+            "",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 6 Nov 1994 08:49:37 GMT",
+            "Sun,  06 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 08:49:37 GMT ",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Sun, 29 Feb 1900 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1969 08:49:37 GMT",
+            "Sun, 06 nov 1994 08:49:37 GMT",
+            "Sun, 06-Nov-94 08:49:37 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+            "Sun Nov 6 08:49:37 1994",
+            "Sun Nov  6 08:49 1994",
+        ] {
+            assert!(s.parse::<HttpDate>().is_err(), "{:?}", s);
+        }
+        assert!(HttpDate::from_unix_secs(HttpDate::MAX_UNIX_SECS + 1).is_err());
+    }
+}
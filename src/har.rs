@@ -0,0 +1,716 @@
+//! HAR (HTTP Archive) import and export.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate bytecodec;
+//! # extern crate httpcodec;
+//! use bytecodec::EncodeExt;
+//! use bytecodec::bytes::BytesEncoder;
+//! use bytecodec::io::IoEncodeExt;
+//! use httpcodec::har::{Entry, Har, Log, Timings};
+//! use httpcodec::{BodyEncoder, Request, RequestEncoder, Response};
+//!
+//! # fn main() {
+//! let request = Request::builder()
+//!     .request_target("/foo?bar=baz")
+//!     .header("Host", "example.com")
+//!     .build(Vec::new())
+//!     .unwrap();
+//! let response = Response::builder()
+//!     .header("Content-Type", "text/plain")
+//!     .build(b"hello".to_vec())
+//!     .unwrap();
+//!
+//! // Export
+//! let timings = Timings::new(1.0, 20.5, 3.0);
+//! let entry = Entry::new("2018-01-01T00:00:00.000Z", &request, &response, timings).unwrap();
+//! let har = Har { log: Log::new(vec![entry]) };
+//! let json = har.to_json().unwrap();
+//!
+//! // Import
+//! let har = Har::from_json(&json).unwrap();
+//! let request = har.log.entries[0].to_request().unwrap();
+//! assert_eq!(request.request_target().as_str(), "/foo?bar=baz");
+//!
+//! let mut encoder = RequestEncoder::<BodyEncoder<BytesEncoder<_>>>::with_item(request).unwrap();
+//! let mut buf = Vec::new();
+//! encoder.encode_all(&mut buf).unwrap();
+//! assert_eq!(
+//!     buf,
+//!     &b"GET /foo?bar=baz HTTP/1.1\r\nHost: example.com\r\nContent-Length: 0\r\n\r\n"[..]
+//! );
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! - [HAR 1.2 Spec]
+//!
+//! [HAR 1.2 Spec]: http://www.softwareishard.com/blog/har-12-spec/
+use bytecodec::{ErrorKind, Result};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::str;
+use trackable::error::ErrorKindExt;
+
+use base64;
+use {
+    Header, HeaderField, HttpVersion, Method, ReasonPhrase, Request, RequestTarget, Response,
+    StatusCode,
+};
+
+/// The root object of a HAR file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    /// The log.
+    pub log: Log,
+}
+impl Har {
+    /// Parses a HAR file.
+    ///
+    /// # Errors
+    ///
+    /// If `json` is not a valid HAR document, an `ErrorKind::InvalidInput` error will be returned.
+    pub fn from_json(json: &str) -> Result<Self> {
+        track!(serde_json::from_str(json).map_err(|e| ErrorKind::InvalidInput.cause(e).into()))
+    }
+
+    /// Converts to the JSON representation.
+    pub fn to_json(&self) -> Result<String> {
+        track!(serde_json::to_string(self).map_err(|e| ErrorKind::Other.cause(e).into()))
+    }
+}
+
+/// A log of HTTP exchanges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Log {
+    /// The version of the format.
+    pub version: String,
+
+    /// The application that created the log.
+    pub creator: Creator,
+
+    /// The exported HTTP exchanges.
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+impl Log {
+    /// Makes a new version 1.2 `Log` instance created by this crate.
+    pub fn new(entries: Vec<Entry>) -> Self {
+        Log {
+            version: "1.2".to_owned(),
+            creator: Creator {
+                name: env!("CARGO_PKG_NAME").to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+            },
+            entries,
+        }
+    }
+}
+
+/// The application that created a log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Creator {
+    /// The name of the application.
+    pub name: String,
+
+    /// The version of the application.
+    pub version: String,
+}
+
+/// An exported HTTP exchange.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// The date and time (in ISO 8601 format) when the request was started.
+    pub started_date_time: String,
+
+    /// The total elapsed time of the exchange in milliseconds.
+    pub time: f64,
+
+    /// The request.
+    pub request: HarRequest,
+
+    /// The response.
+    pub response: HarResponse,
+
+    /// The cache usage (always empty in the exported entries).
+    #[serde(default)]
+    pub cache: Cache,
+
+    /// The detailed timing information.
+    pub timings: Timings,
+}
+impl Entry {
+    /// Makes a new `Entry` instance from the given request and response.
+    ///
+    /// `started_date_time` should be an ISO 8601 string such as `"2018-01-01T00:00:00.000Z"`.
+    ///
+    /// If the target of `request` is in origin-form, the URL of the entry is
+    /// built from the `Host` header field by assuming the `http` scheme.
+    ///
+    /// # Errors
+    ///
+    /// An `ErrorKind::InvalidInput` error will be returned if the URL of the request cannot be
+    /// determined or the body of the request is not a UTF-8 string
+    /// (HAR cannot hold binary request bodies).
+    pub fn new<T, U>(
+        started_date_time: &str,
+        request: &Request<T>,
+        response: &Response<U>,
+        timings: Timings,
+    ) -> Result<Self>
+    where
+        T: AsRef<[u8]>,
+        U: AsRef<[u8]>,
+    {
+        Ok(Entry {
+            started_date_time: started_date_time.to_owned(),
+            time: timings.total(),
+            request: track!(HarRequest::new(request))?,
+            response: HarResponse::new(response),
+            cache: Cache::default(),
+            timings,
+        })
+    }
+
+    /// Converts the request of the entry to a `Request`.
+    ///
+    /// The request target is made in origin-form, and
+    /// a `Host` header field is added if the request does not have one.
+    ///
+    /// HTTP/2 pseudo header fields (e.g., `:authority`) and the fields that depend on
+    /// the transfer of the original message (i.e., `Content-Length` and `Transfer-Encoding`)
+    /// are removed, so that the request can be fed to `RequestEncoder`.
+    /// Versions other than HTTP/1.0 are regarded as HTTP/1.1.
+    pub fn to_request(&self) -> Result<Request<Vec<u8>>> {
+        let r = &self.request;
+        let method = track!(Method::new(&r.method))?;
+        let (authority, target) = track!(split_url(&r.url))?;
+        let target = track!(RequestTarget::new(&target))?;
+        let body = r
+            .post_data
+            .as_ref()
+            .map_or_else(Vec::new, |d| d.text.clone().into_bytes());
+
+        let mut request = Request::new(method, target, parse_version(&r.http_version), body);
+        if !r
+            .headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case("Host"))
+        {
+            let host = track!(HeaderField::new("Host", authority))?;
+            request.header_mut().add_field(host);
+        }
+        for h in r.headers.iter().filter(|h| is_replayable(&h.name)) {
+            let field = track!(HeaderField::new(&h.name, &h.value))?;
+            request.header_mut().add_field(field);
+        }
+        Ok(request)
+    }
+
+    /// Converts the response of the entry to a `Response`.
+    ///
+    /// As with `to_request`, some header fields are removed.
+    /// `Content-Encoding` is also removed because HAR holds decoded contents.
+    pub fn to_response(&self) -> Result<Response<Vec<u8>>> {
+        let r = &self.response;
+        let status = track!(StatusCode::new(r.status))?;
+        let reason = track!(ReasonPhrase::new(&r.status_text))?;
+        let body = match (r.content.text.as_ref(), r.content.encoding.as_ref()) {
+            (None, _) => Vec::new(),
+            (Some(text), Some(encoding)) if encoding == "base64" => track_assert_some!(
+                base64::decode(text, base64::STANDARD, true),
+                ErrorKind::InvalidInput
+            ),
+            (Some(text), _) => text.clone().into_bytes(),
+        };
+
+        let mut response = Response::new(parse_version(&r.http_version), status, reason, body);
+        for h in r.headers.iter().filter(|h| is_replayable(&h.name)) {
+            if h.name.eq_ignore_ascii_case("Content-Encoding") {
+                continue;
+            }
+            let field = track!(HeaderField::new(&h.name, &h.value))?;
+            response.header_mut().add_field(field);
+        }
+        Ok(response)
+    }
+}
+
+/// A request in a HAR entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    /// The request method.
+    pub method: String,
+
+    /// The absolute URL of the request.
+    pub url: String,
+
+    /// The HTTP version (e.g., `"HTTP/1.1"`).
+    pub http_version: String,
+
+    /// The cookies (always empty in the exported entries).
+    #[serde(default)]
+    pub cookies: Vec<NameValue>,
+
+    /// The header fields.
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+
+    /// The parameters in the query string.
+    #[serde(default)]
+    pub query_string: Vec<NameValue>,
+
+    /// The body of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+
+    /// The size of the request-line and the header in bytes, or `-1` if it is unknown.
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+
+    /// The size of the body in bytes, or `-1` if it is unknown.
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+impl HarRequest {
+    fn new<T: AsRef<[u8]>>(request: &Request<T>) -> Result<Self> {
+        let url = track!(make_url(request))?;
+        let query_string = url
+            .split_once('?')
+            .map_or_else(Vec::new, |(_, q)| parse_query(q));
+        let body = request.body().as_ref();
+        let post_data = if body.is_empty() {
+            None
+        } else {
+            let text = track!(str::from_utf8(body).map_err(|e| ErrorKind::InvalidInput.cause(e)))?;
+            Some(PostData {
+                mime_type: mime_type(&request.header()),
+                text: text.to_owned(),
+            })
+        };
+        let start_line_size = request.method().as_str().len()
+            + 1
+            + request.request_target().as_str().len()
+            + 1
+            + 8
+            + 2;
+        Ok(HarRequest {
+            method: request.method().as_str().to_owned(),
+            url,
+            http_version: request.http_version().as_str().to_owned(),
+            cookies: Vec::new(),
+            headers: name_values(&request.header()),
+            query_string,
+            post_data,
+            headers_size: head_size(start_line_size, &request.header()),
+            body_size: body.len() as i64,
+        })
+    }
+}
+
+/// A response in a HAR entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    /// The status code.
+    pub status: u16,
+
+    /// The reason phrase.
+    pub status_text: String,
+
+    /// The HTTP version (e.g., `"HTTP/1.1"`).
+    pub http_version: String,
+
+    /// The cookies (always empty in the exported entries).
+    #[serde(default)]
+    pub cookies: Vec<NameValue>,
+
+    /// The header fields.
+    #[serde(default)]
+    pub headers: Vec<NameValue>,
+
+    /// The body of the response.
+    pub content: Content,
+
+    /// The value of the `Location` header field.
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+
+    /// The size of the status-line and the header in bytes, or `-1` if it is unknown.
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+
+    /// The size of the body in bytes, or `-1` if it is unknown.
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+impl HarResponse {
+    fn new<T: AsRef<[u8]>>(response: &Response<T>) -> Self {
+        let header = response.header();
+        let body = response.body().as_ref();
+        let (text, encoding) = match str::from_utf8(body) {
+            Ok(text) => (text.to_owned(), None),
+            Err(_) => (
+                base64::encode(body, base64::STANDARD, true),
+                Some("base64".to_owned()),
+            ),
+        };
+        let start_line_size = 8 + 1 + 3 + 1 + response.reason_phrase().as_str().len() + 2;
+        HarResponse {
+            status: response.status_code().as_u16(),
+            status_text: response.reason_phrase().as_str().to_owned(),
+            http_version: response.http_version().as_str().to_owned(),
+            cookies: Vec::new(),
+            headers: name_values(&header),
+            content: Content {
+                size: body.len() as i64,
+                mime_type: mime_type(&header),
+                text: Some(text),
+                encoding,
+            },
+            redirect_url: header.get_field("Location").unwrap_or("").to_owned(),
+            headers_size: head_size(start_line_size, &header),
+            body_size: body.len() as i64,
+        }
+    }
+}
+
+/// A name and value pair (e.g., a header field).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameValue {
+    /// The name.
+    pub name: String,
+
+    /// The value.
+    pub value: String,
+}
+
+/// The body of a request in a HAR entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    /// The MIME type of the body.
+    #[serde(default)]
+    pub mime_type: String,
+
+    /// The body.
+    #[serde(default)]
+    pub text: String,
+}
+
+/// The body of a response in a HAR entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    /// The length of the body in bytes.
+    pub size: i64,
+
+    /// The MIME type of the body.
+    #[serde(default)]
+    pub mime_type: String,
+
+    /// The body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    /// The encoding of `text` (e.g., `"base64"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// The cache usage of a HAR entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cache {}
+
+/// The timing information of a HAR entry.
+///
+/// All times are in milliseconds, and `-1` means that the timing does not apply to the exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    /// Time spent in a queue waiting for a connection.
+    #[serde(default = "not_applicable")]
+    pub blocked: f64,
+
+    /// Time required to resolve a host name.
+    #[serde(default = "not_applicable")]
+    pub dns: f64,
+
+    /// Time required to create a connection (including `ssl`).
+    #[serde(default = "not_applicable")]
+    pub connect: f64,
+
+    /// Time required to send the request.
+    pub send: f64,
+
+    /// Time spent waiting for the first byte of the response.
+    pub wait: f64,
+
+    /// Time required to read the response.
+    pub receive: f64,
+
+    /// Time required for the TLS handshake.
+    #[serde(default = "not_applicable")]
+    pub ssl: f64,
+}
+impl Timings {
+    /// Makes a new `Timings` instance in which only `send`, `wait` and `receive` apply.
+    pub fn new(send: f64, wait: f64, receive: f64) -> Self {
+        Timings {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            send,
+            wait,
+            receive,
+            ssl: -1.0,
+        }
+    }
+
+    /// Returns the total elapsed time.
+    pub fn total(&self) -> f64 {
+        [
+            self.blocked,
+            self.dns,
+            self.connect,
+            self.send,
+            self.wait,
+            self.receive,
+        ]
+        .iter()
+        .filter(|&&t| t >= 0.0)
+        .sum()
+    }
+}
+
+fn not_applicable() -> f64 {
+    -1.0
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+fn make_url<T>(request: &Request<T>) -> Result<String> {
+    let target = request.request_target().as_str();
+    if target.contains("://") {
+        return Ok(target.to_owned());
+    }
+    track_assert!(
+        target.starts_with('/'),
+        ErrorKind::InvalidInput,
+        "Unsupported request target: {:?}",
+        target
+    );
+    let header = request.header();
+    let host = track_assert_some!(
+        header.get_field("Host"),
+        ErrorKind::InvalidInput,
+        "No `Host` header field"
+    );
+    Ok(format!("http://{}{}", host, target))
+}
+
+/// Splits an absolute URL into the authority and the origin-form target.
+fn split_url(url: &str) -> Result<(&str, String)> {
+    let start = track_assert_some!(url.find("://"), ErrorKind::InvalidInput; url) + 3;
+    let rest = &url[start..];
+    let end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    track_assert!(!authority.is_empty(), ErrorKind::InvalidInput; url);
+
+    let path = rest[end..].split('#').next().unwrap_or("");
+    let target = if path.starts_with('/') {
+        path.to_owned()
+    } else {
+        format!("/{}", path)
+    };
+    Ok((authority, target))
+}
+
+fn parse_query(query: &str) -> Vec<NameValue> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let mut kv = p.splitn(2, '=');
+            NameValue {
+                name: kv.next().unwrap_or("").to_owned(),
+                value: kv.next().unwrap_or("").to_owned(),
+            }
+        })
+        .collect()
+}
+
+fn parse_version(version: &str) -> HttpVersion {
+    if version.eq_ignore_ascii_case("HTTP/1.0") {
+        HttpVersion::V1_0
+    } else {
+        HttpVersion::V1_1
+    }
+}
+
+fn is_replayable(name: &str) -> bool {
+    !(name.starts_with(':')
+        || name.eq_ignore_ascii_case("Content-Length")
+        || name.eq_ignore_ascii_case("Transfer-Encoding"))
+}
+
+fn name_values(header: &Header) -> Vec<NameValue> {
+    header
+        .fields()
+        .map(|f| NameValue {
+            name: f.name().to_owned(),
+            value: f.value().to_owned(),
+        })
+        .collect()
+}
+
+fn mime_type(header: &Header) -> String {
+    header.get_field("Content-Type").unwrap_or("").to_owned()
+}
+
+fn head_size(start_line_size: usize, header: &Header) -> i64 {
+    let fields_size: usize = header
+        .fields()
+        .map(|f| f.name().len() + 2 + f.value().len() + 2)
+        .sum();
+    (start_line_size + fields_size + 2) as i64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry() -> Entry {
+        let request = track_try_unwrap!(Request::builder()
+            .method("POST")
+            .request_target("/foo?a=1&b")
+            .header("Host", "example.com:8080")
+            .header("Content-Type", "application/json")
+            .header("Content-Length", "2")
+            .build(b"{}".to_vec()));
+        let response = track_try_unwrap!(Response::builder()
+            .status_code(302)
+            .reason_phrase("Found")
+            .header("Location", "/bar")
+            .build(vec![0xFF, 0x00]));
+        let timings = Timings {
+            dns: 2.0,
+            ..Timings::new(1.0, 10.0, 0.5)
+        };
+        track_try_unwrap!(Entry::new(
+            "2018-01-01T00:00:00.000Z",
+            &request,
+            &response,
+            timings
+        ))
+    }
+
+    #[test]
+    fn export_works() {
+        let entry = entry();
+        assert_eq!(entry.time, 13.5);
+        assert_eq!(entry.request.url, "http://example.com:8080/foo?a=1&b");
+        assert_eq!(
+            entry.request.query_string,
+            vec![
+                NameValue {
+                    name: "a".to_owned(),
+                    value: "1".to_owned()
+                },
+                NameValue {
+                    name: "b".to_owned(),
+                    value: "".to_owned()
+                },
+            ]
+        );
+        assert_eq!(entry.request.headers_size, 103);
+        assert_eq!(entry.request.body_size, 2);
+        assert_eq!(
+            entry.request.post_data,
+            Some(PostData {
+                mime_type: "application/json".to_owned(),
+                text: "{}".to_owned()
+            })
+        );
+        assert_eq!(entry.response.redirect_url, "/bar");
+        assert_eq!(entry.response.content.text, Some("/wA=".to_owned()));
+        assert_eq!(entry.response.content.encoding, Some("base64".to_owned()));
+
+        let har = Har {
+            log: Log::new(vec![entry.clone()]),
+        };
+        let json = track_try_unwrap!(har.to_json());
+        assert!(json.contains(r#""startedDateTime":"2018-01-01T00:00:00.000Z""#));
+        assert!(json.contains(r#""redirectURL":"/bar""#));
+        assert_eq!(track_try_unwrap!(Har::from_json(&json)), har);
+    }
+
+    #[test]
+    fn import_works() {
+        let entry = entry();
+
+        let request = track_try_unwrap!(entry.to_request());
+        assert_eq!(
+            request
+                .map_body(|b| String::from_utf8(b).unwrap())
+                .to_string(),
+            "POST /foo?a=1&b HTTP/1.1\r\n\
+             Host: example.com:8080\r\n\
+             Content-Type: application/json\r\n\
+             \r\n\
+             {}"
+        );
+
+        let response = track_try_unwrap!(entry.to_response());
+        assert_eq!(response.status_code().as_u16(), 302);
+        assert_eq!(response.header().get_field("Location"), Some("/bar"));
+        assert_eq!(response.body(), &[0xFF, 0x00]);
+    }
+
+    #[test]
+    fn import_browser_entry_works() {
+        let json = r#"{"log": {"version": "1.2", "creator": {"name": "WebInspector", "version": "537.36"},
+          "entries": [{
+            "startedDateTime": "2018-01-01T00:00:00.000Z",
+            "time": 12.3,
+            "request": {
+              "method": "GET", "url": "https://example.com/index.html#top", "httpVersion": "http/2.0",
+              "headers": [{"name": ":authority", "value": "example.com"},
+                          {"name": "accept", "value": "text/html"}],
+              "queryString": [], "cookies": [{"name": "id", "value": "1", "httpOnly": true}],
+              "headersSize": -1, "bodySize": 0
+            },
+            "response": {
+              "status": 200, "statusText": "", "httpVersion": "http/2.0",
+              "headers": [{"name": "content-encoding", "value": "gzip"}],
+              "content": {"size": 5, "mimeType": "text/html", "text": "hello"},
+              "redirectURL": "", "headersSize": -1, "bodySize": 25
+            },
+            "cache": {},
+            "timings": {"send": 0.1, "wait": 10.2, "receive": 2.0}
+          }]}}"#;
+        let har = track_try_unwrap!(Har::from_json(json));
+        let entry = &har.log.entries[0];
+        assert_eq!(entry.timings.ssl, -1.0);
+
+        let request = track_try_unwrap!(entry.to_request());
+        assert_eq!(
+            request.map_body(|_| "").to_string(),
+            "GET /index.html HTTP/1.1\r\nHost: example.com\r\naccept: text/html\r\n\r\n"
+        );
+
+        let response = track_try_unwrap!(entry.to_response());
+        assert_eq!(response.header().fields().count(), 0);
+        assert_eq!(response.body(), b"hello");
+
+        assert_eq!(
+            Har::from_json(r#"{"log": {}}"#).err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+}
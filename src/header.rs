@@ -3,6 +3,7 @@ use bytecodec::tuple::TupleDecoder;
 use bytecodec::{ByteCount, Decode, Eos, ErrorKind, Result};
 use std;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::iter::{DoubleEndedIterator, ExactSizeIterator};
 use std::mem;
 use std::ops::Range;
@@ -47,6 +48,16 @@ impl<'a> Header<'a> {
         }
     }
 
+    /// Returns `true` if any field named `name` has `token` in its comma-separated value list.
+    ///
+    /// Both names and tokens are compared case-insensitively.
+    pub(crate) fn has_token(&self, name: &str, token: &str) -> bool {
+        self.fields()
+            .filter(|f| f.name().eq_ignore_ascii_case(name))
+            .flat_map(|f| f.value().split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+
     pub(crate) fn new(buf: &'a [u8], fields: &'a [HeaderFieldPosition]) -> Self {
         Header { buf, fields }
     }
@@ -60,6 +71,30 @@ impl<'a> fmt::Display for Header<'a> {
         Ok(())
     }
 }
+impl<'a> PartialEq for Header<'a> {
+    /// Compares the fields of the headers in order.
+    ///
+    /// Field names are compared case-insensitively, and field values are compared exactly.
+    fn eq(&self, other: &Self) -> bool {
+        self.fields.len() == other.fields.len()
+            && self
+                .fields()
+                .zip(other.fields())
+                .all(|(a, b)| a.name().eq_ignore_ascii_case(b.name()) && a.value() == b.value())
+    }
+}
+impl<'a> Eq for Header<'a> {}
+impl<'a> Hash for Header<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fields.len().hash(state);
+        for field in self.fields() {
+            for b in field.name().bytes() {
+                b.to_ascii_lowercase().hash(state);
+            }
+            field.value().hash(state);
+        }
+    }
+}
 
 /// Mutable HTTP header.
 #[derive(Debug)]
@@ -124,14 +159,24 @@ impl<'n, 'v> HeaderField<'n, 'v> {
     /// `name` must be a "token" defined in [RFC 7230].
     /// Otherwise it will return an `ErrorKind::InvalidInput` error.
     ///
-    /// `value` must be composed of "VCHAR" characters that defined in [RFC 7230].
-    /// If it contains any other characters,
+    /// `value` must be composed of "VCHAR" characters that defined in [RFC 7230]
+    /// and whitespaces (i.e., " " or "\t") between them.
+    /// If it contains any other characters or starts/ends with a whitespace,
     /// an `ErrorKind::InvalidInput` error will be returned.
     ///
     /// [RFC 7230]: https://tools.ietf.org/html/rfc7230
     pub fn new(name: &'n str, value: &'v str) -> Result<Self> {
         track_assert!(name.bytes().all(util::is_tchar), ErrorKind::InvalidInput);
-        track_assert!(value.bytes().all(util::is_vchar), ErrorKind::InvalidInput);
+        track_assert!(
+            value
+                .bytes()
+                .all(|b| util::is_vchar(b) || util::is_whitespace(b)),
+            ErrorKind::InvalidInput
+        );
+        track_assert!(
+            !value.starts_with([' ', '\t']) && !value.ends_with([' ', '\t']),
+            ErrorKind::InvalidInput
+        );
         Ok(HeaderField { name, value })
     }
 
@@ -156,6 +201,66 @@ impl<'n, 'v> fmt::Display for HeaderField<'n, 'v> {
     }
 }
 
+/// An owned variant of `HeaderField`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OwnedHeaderField {
+    name: String,
+    value: String,
+}
+impl OwnedHeaderField {
+    /// Makes a new `OwnedHeaderField` instance.
+    ///
+    /// # Errors
+    ///
+    /// `name` and `value` must satisfy the same conditions as `HeaderField::new`.
+    pub fn new<N, V>(name: N, value: V) -> Result<Self>
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let name = name.into();
+        let value = value.into();
+        track!(HeaderField::new(&name, &value))?;
+        Ok(OwnedHeaderField { name, value })
+    }
+
+    /// Returns the borrowed variant of the header field.
+    pub fn as_field(&self) -> HeaderField<'_, '_> {
+        HeaderField {
+            name: &self.name,
+            value: &self.value,
+        }
+    }
+
+    /// Returns the name of the header field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of the header field.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+impl fmt::Display for OwnedHeaderField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_field().fmt(f)
+    }
+}
+impl<'n, 'v> From<HeaderField<'n, 'v>> for OwnedHeaderField {
+    fn from(f: HeaderField<'n, 'v>) -> Self {
+        OwnedHeaderField {
+            name: f.name.to_owned(),
+            value: f.value.to_owned(),
+        }
+    }
+}
+impl<'a> From<&'a OwnedHeaderField> for HeaderField<'a, 'a> {
+    fn from(f: &'a OwnedHeaderField) -> Self {
+        f.as_field()
+    }
+}
+
 /// An iterator over the fields in a HTTP header.
 ///
 /// This is created by calling `Request::header_fields` or `Response::header_fields`.
@@ -279,7 +384,7 @@ impl Decode for HeaderDecoder {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HeaderFieldPosition {
     pub name: Range<usize>,
     pub value: Range<usize>,
@@ -493,4 +598,30 @@ mod test {
             Some(ErrorKind::InvalidInput)
         );
     }
+
+    #[test]
+    fn header_field_new_works() {
+        assert!(HeaderField::new("Content-Type", "text/html; charset=utf-8").is_ok());
+        assert!(HeaderField::new("Foo", "").is_ok());
+        assert!(HeaderField::new("Foo Bar", "baz").is_err());
+        assert!(HeaderField::new("Foo", " bar").is_err());
+        assert!(HeaderField::new("Foo", "bar\t").is_err());
+        assert!(HeaderField::new("Foo", "bar\r\nbaz").is_err());
+    }
+
+    #[test]
+    fn owned_header_field_works() {
+        let field = track_try_unwrap!(OwnedHeaderField::new("foo", "bar baz"));
+        assert_eq!(
+            field.as_field(),
+            HeaderField::new("foo", "bar baz").unwrap()
+        );
+        assert_eq!(field.to_string(), "foo: bar baz");
+        assert!(OwnedHeaderField::new("foo", " bar").is_err());
+
+        let mut buf = Vec::new();
+        let mut fields = Vec::new();
+        HeaderMut::new(&mut buf, &mut fields).add_field(&field);
+        assert_eq!(buf, b"foo: bar baz\r\n");
+    }
 }
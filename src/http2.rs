@@ -0,0 +1,168 @@
+//! Helpers for starting HTTP/2 from HTTP/1.x connections.
+//!
+//! # References
+//!
+//! - [RFC 7540 section 3] Starting HTTP/2
+//!
+//! [RFC 7540 section 3]: https://tools.ietf.org/html/rfc7540#section-3
+use bytecodec::{ErrorKind, Result};
+
+use base64;
+use {HeaderField, HttpVersion, ReasonPhrase, Request, Response, StatusCode};
+
+/// The HTTP/2 connection preface sent by a client.
+///
+/// `RequestDecoder` reports `ProtocolSwitch::Http2Preface` if it receives this preface.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// A parameter contained in a HTTP/2 SETTINGS frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Setting {
+    /// The identifier of the setting.
+    pub identifier: u16,
+
+    /// The value of the setting.
+    pub value: u32,
+}
+
+/// Returns `true` if the given request is an upgrade request to HTTP/2 over cleartext TCP (h2c),
+/// otherwise `false`.
+///
+/// Such a request has `Upgrade: h2c`, `Connection: Upgrade, HTTP2-Settings` and
+/// exactly one `HTTP2-Settings` header fields.
+pub fn is_upgrade_request<T>(request: &Request<T>) -> bool {
+    let header = request.header();
+    header.has_token("Upgrade", "h2c")
+        && header.has_token("Connection", "upgrade")
+        && header.has_token("Connection", "http2-settings")
+        && header
+            .fields()
+            .filter(|f| f.name().eq_ignore_ascii_case("HTTP2-Settings"))
+            .count()
+            == 1
+}
+
+/// Returns the SETTINGS parameters contained in the given h2c upgrade request.
+///
+/// # Errors
+///
+/// If `is_upgrade_request(request)` returns `false` or
+/// the value of the `HTTP2-Settings` header field is malformed,
+/// an `ErrorKind::InvalidInput` error will be returned.
+pub fn upgrade_settings<T>(request: &Request<T>) -> Result<Vec<Setting>> {
+    track_assert!(is_upgrade_request(request), ErrorKind::InvalidInput);
+    let header = request.header();
+    let value = track_assert_some!(header.get_field("HTTP2-Settings"), ErrorKind::InvalidInput);
+    track!(parse_settings(value))
+}
+
+/// Parses the value of a `HTTP2-Settings` header field.
+///
+/// The value is a SETTINGS frame payload encoded in base64url without padding characters.
+///
+/// # Errors
+///
+/// If the value is malformed, an `ErrorKind::InvalidInput` error will be returned.
+pub fn parse_settings(value: &str) -> Result<Vec<Setting>> {
+    let payload = track_assert_some!(
+        base64::decode(value, base64::URL_SAFE, false),
+        ErrorKind::InvalidInput; value
+    );
+    track_assert_eq!(payload.len() % 6, 0, ErrorKind::InvalidInput; value);
+    let settings = payload
+        .chunks(6)
+        .map(|b| Setting {
+            identifier: u16::from_be_bytes([b[0], b[1]]),
+            value: u32::from_be_bytes([b[2], b[3], b[4], b[5]]),
+        })
+        .collect();
+    Ok(settings)
+}
+
+/// Formats the given settings as the value of a `HTTP2-Settings` header field.
+pub fn format_settings(settings: &[Setting]) -> String {
+    let mut payload = Vec::with_capacity(settings.len() * 6);
+    for s in settings {
+        payload.extend_from_slice(&s.identifier.to_be_bytes());
+        payload.extend_from_slice(&s.value.to_be_bytes());
+    }
+    base64::encode(&payload, base64::URL_SAFE, false)
+}
+
+/// Makes a `101 Switching Protocols` response that accepts a h2c upgrade request.
+pub fn make_upgrade_response() -> Result<Response<()>> {
+    let mut response = Response::new(
+        HttpVersion::V1_1,
+        track!(StatusCode::new(101))?,
+        track!(ReasonPhrase::new("Switching Protocols"))?,
+        (),
+    );
+    response
+        .header_mut()
+        .add_field(track!(HeaderField::new("Connection", "Upgrade"))?)
+        .add_field(track!(HeaderField::new("Upgrade", "h2c"))?);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::ErrorKind;
+
+    use super::*;
+    use {Method, RequestTarget};
+
+    #[test]
+    fn settings_works() {
+        let settings = vec![
+            Setting {
+                identifier: 3,
+                value: 100,
+            },
+            Setting {
+                identifier: 4,
+                value: 0xFFFF,
+            },
+        ];
+        let value = format_settings(&settings);
+        assert_eq!(value, "AAMAAABkAAQAAP__");
+        assert_eq!(track_try_unwrap!(parse_settings(&value)), settings);
+        assert_eq!(track_try_unwrap!(parse_settings("")), vec![]);
+
+        assert_eq!(
+            parse_settings("AAMAAABk=").err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+        assert_eq!(
+            parse_settings("AAMAAA").err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn upgrade_settings_works() {
+        let mut request = Request::new(
+            Method::new("GET").unwrap(),
+            RequestTarget::new("/").unwrap(),
+            HttpVersion::V1_1,
+            (),
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Host", "server.example.com").unwrap())
+            .add_field(HeaderField::new("Connection", "Upgrade,HTTP2-Settings").unwrap())
+            .add_field(HeaderField::new("Upgrade", "h2c").unwrap());
+        assert!(!is_upgrade_request(&request));
+
+        request
+            .header_mut()
+            .add_field(HeaderField::new("HTTP2-Settings", "AAMAAABk").unwrap());
+        assert!(is_upgrade_request(&request));
+        assert_eq!(
+            track_try_unwrap!(upgrade_settings(&request)),
+            vec![Setting {
+                identifier: 3,
+                value: 100
+            }]
+        );
+    }
+}
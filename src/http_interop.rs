@@ -0,0 +1,228 @@
+use bytecodec::{Error, ErrorKind, Result};
+use http;
+use std::convert::TryFrom;
+use std::str;
+use trackable::error::ErrorKindExt;
+
+use {
+    HeaderField, HttpVersion, Method, ReasonPhrase, Request, RequestTarget, Response, StatusCode,
+};
+
+/// An extension of `http::Response` that holds the reason phrase of the response.
+///
+/// It is set when converting a `Response` to a `http::Response`, and
+/// used (if present) when converting in the opposite direction.
+/// Otherwise the canonical reason phrase of the status code is used.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HttpReasonPhrase(String);
+impl HttpReasonPhrase {
+    /// Makes a new `HttpReasonPhrase` instance.
+    pub fn new(phrase: ReasonPhrase) -> Self {
+        HttpReasonPhrase(phrase.as_str().to_owned())
+    }
+
+    /// Returns a reference to the phrase string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<T> TryFrom<http::Request<T>> for Request<T> {
+    type Error = Error;
+
+    fn try_from(request: http::Request<T>) -> Result<Self> {
+        let (parts, body) = request.into_parts();
+        let method = track!(Method::new(parts.method.as_str()))?;
+        let target = parts.uri.to_string();
+        let target = track!(RequestTarget::new(&target))?;
+        let version = track!(from_http_version(parts.version))?;
+
+        let mut request = Request::new(method, target, version, body);
+        track!(copy_headers_from_http(&parts.headers, |f| {
+            request.header_mut().add_field(f);
+        }))?;
+        Ok(request)
+    }
+}
+
+impl<T> TryFrom<Request<T>> for http::Request<T> {
+    type Error = Error;
+
+    fn try_from(request: Request<T>) -> Result<Self> {
+        let mut builder = http::Request::builder()
+            .method(request.method().as_str())
+            .uri(request.request_target().as_str())
+            .version(to_http_version(request.http_version()));
+        for field in request.header().fields() {
+            builder = builder.header(field.name(), field.value());
+        }
+        track!(builder
+            .body(request.into_body())
+            .map_err(into_invalid_input))
+    }
+}
+
+impl<T> TryFrom<http::Response<T>> for Response<T> {
+    type Error = Error;
+
+    fn try_from(response: http::Response<T>) -> Result<Self> {
+        let (parts, body) = response.into_parts();
+        let version = track!(from_http_version(parts.version))?;
+        let status = track!(StatusCode::new(parts.status.as_u16()))?;
+        let reason = parts
+            .extensions
+            .get::<HttpReasonPhrase>()
+            .map(|r| r.as_str())
+            .or_else(|| parts.status.canonical_reason())
+            .unwrap_or("");
+        let reason = track!(ReasonPhrase::new(reason))?;
+
+        let mut response = Response::new(version, status, reason, body);
+        track!(copy_headers_from_http(&parts.headers, |f| {
+            response.header_mut().add_field(f);
+        }))?;
+        Ok(response)
+    }
+}
+
+impl<T> TryFrom<Response<T>> for http::Response<T> {
+    type Error = Error;
+
+    fn try_from(response: Response<T>) -> Result<Self> {
+        let mut builder = http::Response::builder()
+            .status(response.status_code().as_u16())
+            .version(to_http_version(response.http_version()))
+            .extension(HttpReasonPhrase::new(response.reason_phrase()));
+        for field in response.header().fields() {
+            builder = builder.header(field.name(), field.value());
+        }
+        track!(builder
+            .body(response.into_body())
+            .map_err(into_invalid_input))
+    }
+}
+
+fn from_http_version(version: http::Version) -> Result<HttpVersion> {
+    match version {
+        http::Version::HTTP_10 => Ok(HttpVersion::V1_0),
+        http::Version::HTTP_11 => Ok(HttpVersion::V1_1),
+        _ => track_panic!(
+            ErrorKind::InvalidInput,
+            "Unsupported HTTP version: {:?}",
+            version
+        ),
+    }
+}
+
+fn to_http_version(version: HttpVersion) -> http::Version {
+    match version {
+        HttpVersion::V1_0 => http::Version::HTTP_10,
+        HttpVersion::V1_1 => http::Version::HTTP_11,
+    }
+}
+
+fn copy_headers_from_http<F>(headers: &http::HeaderMap, mut f: F) -> Result<()>
+where
+    F: FnMut(HeaderField),
+{
+    for (name, value) in headers {
+        let value = track!(str::from_utf8(value.as_bytes()).map_err(into_invalid_input))?;
+        f(track!(HeaderField::new(name.as_str(), value))?);
+    }
+    Ok(())
+}
+
+fn into_invalid_input<E: std::error::Error + Send + Sync + 'static>(e: E) -> Error {
+    ErrorKind::InvalidInput.cause(e).into()
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::ErrorKind;
+    use http;
+    use std::convert::TryFrom;
+
+    use {
+        HeaderField, HttpVersion, Method, ReasonPhrase, Request, RequestTarget, Response,
+        StatusCode,
+    };
+
+    #[test]
+    fn request_conversion_works() {
+        let mut request = Request::new(
+            Method::new("POST").unwrap(),
+            RequestTarget::new("/foo?bar=baz").unwrap(),
+            HttpVersion::V1_0,
+            "qux",
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Content-Type", "text/plain; charset=utf-8").unwrap())
+            .add_field(HeaderField::new("X-Foo", "1").unwrap())
+            .add_field(HeaderField::new("X-Foo", "2").unwrap());
+
+        let request = track_try_unwrap!(http::Request::try_from(request));
+        assert_eq!(request.method(), http::Method::POST);
+        assert_eq!(request.uri(), "/foo?bar=baz");
+        assert_eq!(request.version(), http::Version::HTTP_10);
+        assert_eq!(
+            request.headers()["content-type"],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(request.headers().get_all("x-foo").iter().count(), 2);
+        assert_eq!(*request.body(), "qux");
+
+        let request = track_try_unwrap!(Request::try_from(request));
+        assert_eq!(request.method().as_str(), "POST");
+        assert_eq!(request.request_target().as_str(), "/foo?bar=baz");
+        assert_eq!(request.http_version(), HttpVersion::V1_0);
+        assert_eq!(
+            request
+                .header()
+                .fields()
+                .map(|f| (f.name().to_owned(), f.value().to_owned()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "content-type".to_owned(),
+                    "text/plain; charset=utf-8".to_owned()
+                ),
+                ("x-foo".to_owned(), "1".to_owned()),
+                ("x-foo".to_owned(), "2".to_owned()),
+            ]
+        );
+        assert_eq!(*request.body(), "qux");
+
+        let request = http::Request::builder()
+            .version(http::Version::HTTP_2)
+            .body(())
+            .unwrap();
+        assert_eq!(
+            Request::try_from(request).err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn response_conversion_works() {
+        let response = Response::new(
+            HttpVersion::V1_1,
+            StatusCode::new(404).unwrap(),
+            ReasonPhrase::new("Nothing Here").unwrap(),
+            (),
+        );
+
+        let response = track_try_unwrap!(http::Response::try_from(response));
+        assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+        assert_eq!(response.version(), http::Version::HTTP_11);
+
+        let response = track_try_unwrap!(Response::try_from(response));
+        assert_eq!(response.status_code().as_u16(), 404);
+        assert_eq!(response.reason_phrase().as_str(), "Nothing Here");
+
+        let response = http::Response::builder().status(201).body(()).unwrap();
+        let response = track_try_unwrap!(Response::try_from(response));
+        assert_eq!(response.status_code().as_u16(), 201);
+        assert_eq!(response.reason_phrase().as_str(), "Created");
+    }
+}
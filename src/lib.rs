@@ -69,30 +69,79 @@
 #![warn(missing_docs)]
 #[macro_use]
 extern crate bytecodec;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
+#[cfg(feature = "futures-io")]
+extern crate futures_timer;
+#[cfg(feature = "http")]
+extern crate http;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(any(feature = "har", all(test, feature = "serde")))]
+extern crate serde_json;
+#[cfg(feature = "tokio-codec")]
+extern crate tokio_util;
 #[macro_use]
 extern crate trackable;
 
+#[cfg(all(test, feature = "futures-io"))]
+extern crate futures;
+
 pub use body::{
-    BodyDecode, BodyDecoder, BodyEncode, BodyEncoder, HeadBodyEncoder, NoBodyDecoder, NoBodyEncoder,
+    BodyDecode, BodyDecoder, BodyEncode, BodyEncoder, BodyFraming, BodySender, BodyStream,
+    HeadBodyEncoder, NoBodyDecoder, NoBodyEncoder, StreamingBodyDecoder, StreamingBodyEncoder,
 };
-pub use header::{Header, HeaderField, HeaderFields, HeaderMut};
-pub use method::Method;
+pub use date::HttpDate;
+pub use header::{Header, HeaderField, HeaderFields, HeaderMut, OwnedHeaderField};
+#[cfg(feature = "http")]
+pub use http_interop::HttpReasonPhrase;
+pub use message::{MessageDecode, MessageEncode};
+pub use method::{Method, OwnedMethod};
 pub use options::DecodeOptions;
-pub use request::{Request, RequestDecoder, RequestEncoder};
-pub use request_target::RequestTarget;
-pub use response::{Response, ResponseDecoder, ResponseEncoder};
-pub use status::{ReasonPhrase, StatusCode};
+pub use request::{Request, RequestBuilder, RequestDecoder, RequestEncoder};
+pub use request_target::{OwnedRequestTarget, RequestTarget};
+pub use response::{Response, ResponseBuilder, ResponseDecoder, ResponseEncoder};
+pub use status::{OwnedReasonPhrase, ReasonPhrase, StatusCode};
+pub use upgrade::ProtocolSwitch;
+pub use vectored::{EncodeVectored, VectoredBytesEncoder};
 pub use version::HttpVersion;
 
+#[cfg(feature = "futures-io")]
+pub mod async_io;
+pub mod client;
+pub mod cookie;
+#[cfg(feature = "har")]
+pub mod har;
+pub mod http2;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod recording;
+pub mod server;
+#[cfg(feature = "tokio-codec")]
+pub mod tokio_codec;
+pub mod websocket;
+
+mod base64;
 mod body;
 mod chunked_body;
+mod date;
 mod header;
+#[cfg(feature = "http")]
+mod http_interop;
+#[cfg(any(test, feature = "mock"))]
+mod local_server;
 mod message;
 mod method;
 mod options;
 mod request;
 mod request_target;
 mod response;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod status;
+mod upgrade;
 mod util;
+mod vectored;
 mod version;
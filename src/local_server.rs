@@ -0,0 +1,115 @@
+use bytecodec::bytes::RemainingBytesDecoder;
+use bytecodec::{Decode, Eos, Error, Result};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use util;
+use {BodyDecoder, Request, RequestDecoder};
+
+const CONTINUE_RESPONSE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+/// The raw response to be written back to a client.
+#[derive(Debug)]
+pub struct Reply {
+    /// The encoded response.
+    pub bytes: Vec<u8>,
+
+    /// If `true`, the connection is closed after the response has been written.
+    pub close: bool,
+}
+
+/// A server that accepts connections on `127.0.0.1` in a background thread.
+///
+/// Each connection is served by its own thread, and
+/// is kept alive unless the client or the reply requests otherwise.
+/// Requests that expect `100-continue` are answered with `100 Continue` before their bodies are read.
+/// The server stops accepting connections when it is dropped.
+#[derive(Debug)]
+pub struct LocalServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+impl LocalServer {
+    pub fn start<F>(respond: F) -> Result<Self>
+    where
+        F: Fn(&Request<Vec<u8>>) -> Reply + Send + Sync + 'static,
+    {
+        let listener = track!(TcpListener::bind("127.0.0.1:0").map_err(Error::from))?;
+        let addr = track!(listener.local_addr().map_err(Error::from))?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let respond = Arc::new(respond);
+
+        let stopped_clone = Arc::clone(&stopped);
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped_clone.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let respond = Arc::clone(&respond);
+                    thread::spawn(move || {
+                        let _ = handle_connection(stream, &*respond);
+                    });
+                }
+            }
+        });
+        Ok(LocalServer {
+            addr,
+            stopped,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+impl Drop for LocalServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        // Wakes up the accepting thread.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle_connection<F>(mut stream: TcpStream, respond: &F) -> Result<()>
+where
+    F: Fn(&Request<Vec<u8>>) -> Reply,
+{
+    let mut decoder = RequestDecoder::new(BodyDecoder::for_request(RemainingBytesDecoder::new()));
+    decoder.set_expect_continue(true);
+    let mut pending = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let size = track!(decoder.decode(&pending, Eos::new(false)))?;
+        pending.drain(..size);
+        if decoder.is_suspended() {
+            track!(stream.write_all(CONTINUE_RESPONSE).map_err(Error::from))?;
+            decoder.resume();
+            continue;
+        }
+        if decoder.is_idle() {
+            let request = track!(decoder.finish_decoding())?;
+            let reply = respond(&request);
+            track!(stream.write_all(&reply.bytes).map_err(Error::from))?;
+            if reply.close || !util::is_keep_alive(request.http_version(), &request.header()) {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let size = track!(stream.read(&mut buf).map_err(Error::from))?;
+        if size == 0 {
+            return Ok(());
+        }
+        pending.extend_from_slice(&buf[..size]);
+    }
+}
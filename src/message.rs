@@ -1,78 +1,414 @@
-use bytecodec::bytes::BytesEncoder;
-use bytecodec::combinator::{MaxBytes, Peekable};
+use bytecodec::combinator::Peekable;
 use bytecodec::{ByteCount, Decode, DecodeExt, Encode, Eos, ErrorKind, Result, SizedEncode};
+#[cfg(feature = "bytes")]
+use bytes::{Buf, Bytes, BytesMut};
+use std::cmp;
+use std::io::IoSlice;
 use std::mem;
+use std::ops::{Deref, Range};
 
 use body::{BodyDecode, BodyEncode};
 use header::{Header, HeaderDecoder, HeaderFieldPosition, HeaderMut};
 use options::DecodeOptions;
+use upgrade::ProtocolSwitch;
+use vectored::{EncodeVectored, VectoredBytesEncoder};
+
+/// `MessageDecode` is used for representing HTTP message decoders.
+///
+/// It reports the states in which a decoder stops consuming bytes
+/// even though no message has been completed.
+pub trait MessageDecode: Decode {
+    /// Returns `true` if the decoder is suspended before decoding the body of the current message.
+    ///
+    /// See `RequestDecoder::set_expect_continue`.
+    fn is_suspended(&self) -> bool;
+
+    /// Returns the protocol switch that prevents the decoder from consuming any more bytes.
+    ///
+    /// This returns `None` while the message that requested the switch is being decoded.
+    /// See `RequestDecoder::protocol_switch` and `ResponseDecoder::protocol_switch`.
+    fn switched_protocol(&self) -> Option<ProtocolSwitch>;
+}
+
+/// `MessageEncode` is used for representing HTTP message encoders.
+///
+/// It reports the state in which an encoder stops producing bytes
+/// even though the current message has not been encoded completely.
+pub trait MessageEncode: Encode {
+    /// Returns `true` if the encoder holds back the body of the current message.
+    ///
+    /// While suspended, the encoder is not idle but produces no more bytes.
+    /// See `RequestEncoder::set_expect_continue`.
+    fn is_suspended(&self) -> bool;
+}
+
+/// Storage of the head part (i.e., the start-line and the header) of a message.
+#[derive(Debug, Clone)]
+pub enum HeadBuf {
+    Owned(Vec<u8>),
+    #[cfg(feature = "bytes")]
+    Shared(Bytes),
+}
+impl HeadBuf {
+    /// Returns a mutable reference to the owned buffer.
+    ///
+    /// A shared buffer is copied into a new owned one.
+    pub fn to_mut(&mut self) -> &mut Vec<u8> {
+        #[cfg(feature = "bytes")]
+        {
+            if let HeadBuf::Shared(ref b) = *self {
+                *self = HeadBuf::Owned(b.to_vec());
+            }
+        }
+        match *self {
+            HeadBuf::Owned(ref mut v) => v,
+            #[cfg(feature = "bytes")]
+            HeadBuf::Shared(_) => unreachable!(),
+        }
+    }
+
+    /// Returns a mutable reference to the owned buffer to which header fields can be appended.
+    ///
+    /// The empty line that terminates a decoded head is removed from the buffer.
+    pub fn appendable_mut(&mut self) -> &mut Vec<u8> {
+        let v = self.to_mut();
+        if v.ends_with(b"\r\n\r\n") {
+            let len = v.len() - 2;
+            v.truncate(len);
+        }
+        v
+    }
+
+    /// Converts into the owned buffer that does not contain the terminating empty line.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.appendable_mut();
+        match self {
+            HeadBuf::Owned(v) => v,
+            #[cfg(feature = "bytes")]
+            HeadBuf::Shared(_) => unreachable!(),
+        }
+    }
+
+    /// Replaces the bytes in `range` with `bytes`.
+    ///
+    /// The positions of `fields` located after `range` are shifted accordingly.
+    pub fn replace(
+        &mut self,
+        range: Range<usize>,
+        bytes: &[u8],
+        fields: &mut [HeaderFieldPosition],
+    ) {
+        let end = range.end;
+        let removed = range.end - range.start;
+        self.to_mut().splice(range, bytes.iter().cloned());
+        for f in fields {
+            for r in &mut [&mut f.name, &mut f.value] {
+                if r.start >= end {
+                    r.start = r.start + bytes.len() - removed;
+                    r.end = r.end + bytes.len() - removed;
+                }
+            }
+        }
+    }
+
+    /// Returns the whole head part including the empty line that terminates it.
+    #[cfg(feature = "bytes")]
+    pub fn to_bytes(&self) -> Bytes {
+        match *self {
+            HeadBuf::Owned(ref v) => {
+                let mut v = v.clone();
+                if !v.ends_with(b"\r\n\r\n") {
+                    v.extend_from_slice(b"\r\n");
+                }
+                Bytes::from(v)
+            }
+            HeadBuf::Shared(ref b) => b.clone(),
+        }
+    }
+
+    /// Returns the value of the first field that has the name `name`.
+    #[cfg(feature = "bytes")]
+    pub fn field_bytes(&self, fields: &[HeaderFieldPosition], name: &str) -> Option<Bytes> {
+        let f = fields
+            .iter()
+            .find(|f| self[f.name.clone()].eq_ignore_ascii_case(name.as_bytes()))?;
+        match *self {
+            HeadBuf::Owned(ref v) => Some(Bytes::copy_from_slice(&v[f.value.clone()])),
+            HeadBuf::Shared(ref b) => Some(b.slice(f.value.clone())),
+        }
+    }
+}
+impl Default for HeadBuf {
+    fn default() -> Self {
+        HeadBuf::Owned(Vec::new())
+    }
+}
+impl Deref for HeadBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            HeadBuf::Owned(ref v) => v,
+            #[cfg(feature = "bytes")]
+            HeadBuf::Shared(ref b) => b,
+        }
+    }
+}
+impl From<Vec<u8>> for HeadBuf {
+    fn from(f: Vec<u8>) -> Self {
+        HeadBuf::Owned(f)
+    }
+}
 
 #[derive(Debug)]
 pub struct Message<S, B> {
-    pub buf: Vec<u8>,
+    pub buf: HeadBuf,
     pub start_line: S,
     pub header: Vec<HeaderFieldPosition>,
     pub body: B,
 }
 
+/// Part of a message head whose size exceeded the limit specified by `DecodeOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadPart {
+    StartLine,
+    Header,
+}
+
 #[derive(Debug)]
 pub struct MessageDecoder<S: Decode, B> {
-    buf: Vec<u8>,
-    start_line: MaxBytes<S>,
-    header: Peekable<MaxBytes<HeaderDecoder>>,
+    buf: HeadBuf,
+    head_size: usize,
+    header_start: usize,
+    retain_head: bool,
+    start_line: S,
+    start_line_item: Option<S::Item>,
+    header: Peekable<HeaderDecoder>,
     body: B,
+    body_initialized: bool,
+    exceeded_limit: Option<HeadPart>,
     options: DecodeOptions,
 }
 impl<S: Decode, B: BodyDecode> MessageDecoder<S, B> {
     pub fn new(start_line: S, body: B, options: DecodeOptions) -> Self {
         MessageDecoder {
-            buf: Vec::new(),
-            start_line: start_line.max_bytes(options.max_start_line_size as u64),
-            header: HeaderDecoder::default()
-                .max_bytes(options.max_header_size as u64)
-                .peekable(),
+            buf: HeadBuf::default(),
+            head_size: 0,
+            header_start: 0,
+            retain_head: false,
+            start_line,
+            start_line_item: None,
+            header: HeaderDecoder::default().peekable(),
             body,
+            body_initialized: false,
+            exceeded_limit: None,
             options,
         }
     }
-}
-impl<S: Decode, B: BodyDecode> Decode for MessageDecoder<S, B> {
-    type Item = Message<S::Item, B::Item>;
 
-    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+    /// Decodes the start-line and the header of a message.
+    ///
+    /// This never consumes the bytes belonging to the body part.
+    ///
+    /// If the start-line or the header exceeds the size limit specified by `DecodeOptions`,
+    /// an `ErrorKind::InvalidInput` error will be returned and
+    /// `exceeded_limit` method reports the offending part.
+    pub fn decode_head(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
         let mut offset = 0;
-        if !self.start_line.is_idle() {
-            offset += track!(self.start_line.decode(buf, eos))?;
+        if self.start_line_item.is_none() {
+            let max = self.options.max_start_line_size;
+            offset += track!(decode_within(
+                &mut self.start_line,
+                buf,
+                eos,
+                max - self.head_size
+            ))?;
             if !self.start_line.is_idle() {
-                self.buf.extend_from_slice(&buf[..offset]);
+                self.consume_head(&buf[..offset]);
+                if self.head_size >= max && offset < buf.len() {
+                    self.exceeded_limit = Some(HeadPart::StartLine);
+                    track_panic!(ErrorKind::InvalidInput, "Too long start-line"; max);
+                }
                 return Ok(offset);
-            } else {
-                self.header
-                    .inner_mut()
-                    .inner_mut()
-                    .set_start_position(self.buf.len() + offset);
             }
+            self.start_line_item = Some(track!(self.start_line.finish_decoding())?);
+            self.header_start = self.head_size + offset;
+            self.header
+                .inner_mut()
+                .set_start_position(self.header_start);
         }
 
         if !self.header.is_idle() {
-            offset += track!(self.header.decode(&buf[offset..], eos))?;
-            self.buf.extend_from_slice(&buf[..offset]);
-            if let Some(header) = self.header.peek() {
-                track!(self.body.initialize(&Header::new(&self.buf, header)))?;
-            } else {
+            let max = self.options.max_header_size;
+            let consumed = self.head_size + offset - self.header_start;
+            offset += track!(decode_within(
+                &mut self.header,
+                &buf[offset..],
+                eos,
+                max - consumed
+            ))?;
+            self.consume_head(&buf[..offset]);
+            if !self.header.is_idle()
+                && self.head_size - self.header_start >= max
+                && offset < buf.len()
+            {
+                self.exceeded_limit = Some(HeadPart::Header);
+                track_panic!(ErrorKind::InvalidInput, "Too large header"; max);
+            }
+        }
+        Ok(offset)
+    }
+
+    /// Returns the part of the head that exceeded its size limit in the last decoding.
+    pub fn exceeded_limit(&self) -> Option<HeadPart> {
+        self.exceeded_limit
+    }
+
+    /// Returns the decoded start-line and header if the head part has been decoded.
+    pub fn head(&self) -> Option<(&S::Item, Header<'_>)> {
+        if !self.is_head_decoded() {
+            return None;
+        }
+        match (self.start_line_item.as_ref(), self.header.peek()) {
+            (Some(start_line), Some(header)) => Some((start_line, Header::new(&self.buf, header))),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of the head part if it has been decoded.
+    pub fn peek_head(&self) -> Option<Message<S::Item, ()>>
+    where
+        S::Item: Clone,
+    {
+        if !self.is_head_decoded() {
+            return None;
+        }
+        match (self.start_line_item.as_ref(), self.header.peek()) {
+            (Some(start_line), Some(header)) => Some(Message {
+                buf: self.buf.clone(),
+                start_line: start_line.clone(),
+                header: header.clone(),
+                body: (),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Finishes decoding the current message without its body.
+    ///
+    /// This is used for messages that never have a body (e.g., interim responses).
+    pub fn finish_head(&mut self) -> Result<Message<S::Item, ()>> {
+        let header = track!(self.header.finish_decoding())?;
+        let start_line =
+            track_assert_some!(self.start_line_item.take(), ErrorKind::IncompleteDecoding);
+        let buf = mem::take(&mut self.buf);
+        self.head_size = 0;
+        Ok(Message {
+            buf,
+            start_line,
+            header,
+            body: (),
+        })
+    }
+
+    /// Treats the current message as one that has no body.
+    ///
+    /// The body decoder is finished by `BodyDecode::skip` without consuming any bytes.
+    pub fn skip_body(&mut self) -> Result<()> {
+        track_assert!(self.is_head_decoded(), ErrorKind::InconsistentState);
+        track!(self.body.skip())?;
+        track_assert!(self.body.is_idle(), ErrorKind::InvalidInput);
+        self.body_initialized = true;
+        Ok(())
+    }
+
+    pub fn buf(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn body_ref(&self) -> &B {
+        &self.body
+    }
+
+    pub fn body_mut(&mut self) -> &mut B {
+        &mut self.body
+    }
+
+    pub fn is_head_decoded(&self) -> bool {
+        self.header.peek().is_some() && self.buf.len() == self.head_size
+    }
+
+    /// Specifies whether the decoder leaves the head bytes in the caller's buffer
+    /// instead of copying them.
+    ///
+    /// If it is `true`, the head part is regarded as decoded only after
+    /// the bytes are handed back via `attach_head`.
+    #[cfg(feature = "bytes")]
+    pub fn set_retain_head(&mut self, retain: bool) {
+        self.retain_head = retain;
+    }
+
+    /// Returns the size of the head part that has been decoded but not attached yet.
+    #[cfg(feature = "bytes")]
+    pub fn unattached_head_size(&self) -> usize {
+        self.head_size - self.buf.len()
+    }
+
+    /// Returns the size of the head part if the decoding has completed and
+    /// the bytes are waiting to be attached.
+    #[cfg(feature = "bytes")]
+    pub fn pending_head_size(&self) -> Option<usize> {
+        if self.header.peek().is_some() && self.buf.len() != self.head_size {
+            Some(self.head_size)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "bytes")]
+    pub fn attach_head(&mut self, head: Bytes) -> Result<()> {
+        track_assert_eq!(head.len(), self.head_size, ErrorKind::InconsistentState);
+        self.buf = HeadBuf::Shared(head);
+        Ok(())
+    }
+
+    fn consume_head(&mut self, bytes: &[u8]) {
+        if !self.retain_head {
+            self.buf.to_mut().extend_from_slice(bytes);
+        }
+        self.head_size += bytes.len();
+    }
+}
+impl<S: Decode, B: BodyDecode> Decode for MessageDecoder<S, B> {
+    type Item = Message<S::Item, B::Item>;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let mut offset = 0;
+        if !self.is_head_decoded() {
+            offset += track!(self.decode_head(buf, eos))?;
+            if !self.is_head_decoded() {
                 return Ok(offset);
             }
         }
 
+        if !self.body_initialized {
+            let header = track_assert_some!(self.header.peek(), ErrorKind::InconsistentState);
+            track!(self.body.initialize(&Header::new(&self.buf, header)))?;
+            self.body_initialized = true;
+        }
+
         bytecodec_try_decode!(self.body, offset, buf, eos);
         Ok(offset)
     }
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
         let body = track!(self.body.finish_decoding())?;
-        let buf = mem::replace(&mut self.buf, Vec::new());
-        let start_line = track!(self.start_line.finish_decoding())?;
+        let buf = mem::take(&mut self.buf);
+        self.head_size = 0;
+        let start_line =
+            track_assert_some!(self.start_line_item.take(), ErrorKind::IncompleteDecoding);
         let header = track!(self.header.finish_decoding())?;
+        self.body_initialized = false;
         Ok(Message {
             buf,
             start_line,
@@ -94,16 +430,41 @@ impl<S: Decode, B: BodyDecode> Decode for MessageDecoder<S, B> {
 
 #[derive(Debug, Default)]
 pub struct MessageEncoder<B> {
-    before_body: BytesEncoder<Vec<u8>>,
+    before_body: VectoredBytesEncoder<Vec<u8>>,
     body: B,
+    suspended: bool,
 }
 impl<B: BodyEncode> MessageEncoder<B> {
     pub fn new(body: B) -> Self {
         MessageEncoder {
-            before_body: BytesEncoder::new(),
+            before_body: VectoredBytesEncoder::new(),
             body,
+            suspended: false,
         }
     }
+
+    /// Holds back the body part until `resume` is called.
+    ///
+    /// The head part is encoded as usual.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    pub fn body_ref(&self) -> &B {
+        &self.body
+    }
+
+    pub fn body_mut(&mut self) -> &mut B {
+        &mut self.body
+    }
 }
 impl<B: BodyEncode> Encode for MessageEncoder<B> {
     type Item = Message<(), B::Item>;
@@ -116,6 +477,9 @@ impl<B: BodyEncode> Encode for MessageEncoder<B> {
                 return Ok(offset);
             }
         }
+        if self.suspended {
+            return Ok(offset);
+        }
         offset += track!(self.body.encode(&mut buf[offset..], eos))?;
         Ok(offset)
     }
@@ -124,16 +488,17 @@ impl<B: BodyEncode> Encode for MessageEncoder<B> {
         track_assert!(self.is_idle(), ErrorKind::EncoderFull);
         track!(self.body.start_encoding(item.body))?;
         {
-            let mut header = HeaderMut::new(&mut item.buf, &mut item.header);
+            let mut header = HeaderMut::new(item.buf.appendable_mut(), &mut item.header);
             track!(self.body.update_header(&mut header))?;
         }
-        item.buf.extend_from_slice(b"\r\n");
-        track!(self.before_body.start_encoding(item.buf))?;
+        let mut buf = item.buf.into_vec();
+        buf.extend_from_slice(b"\r\n");
+        track!(self.before_body.start_encoding(buf))?;
         Ok(())
     }
 
     fn is_idle(&self) -> bool {
-        self.before_body.is_idle() && self.body.is_idle()
+        !self.suspended && self.before_body.is_idle() && self.body.is_idle()
     }
 
     fn requiring_bytes(&self) -> ByteCount {
@@ -147,3 +512,103 @@ impl<B: SizedEncode + BodyEncode> SizedEncode for MessageEncoder<B> {
         self.before_body.exact_requiring_bytes() + self.body.exact_requiring_bytes()
     }
 }
+impl<B: BodyEncode + EncodeVectored> EncodeVectored for MessageEncoder<B> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.before_body.pending_slices(slices))?;
+        if !self.suspended {
+            track!(self.body.pending_slices(slices))?;
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        let head_size = cmp::min(size, self.before_body.remaining_bytes().len());
+        track!(self.before_body.consume(head_size))?;
+        if self.suspended {
+            track_assert_eq!(size, head_size, ErrorKind::InvalidInput);
+            return Ok(());
+        }
+        track!(self.body.consume(size - head_size))
+    }
+}
+
+/// A decoder that consists of a `MessageDecoder` and can decode messages from `BytesMut`.
+#[cfg(feature = "bytes")]
+pub(crate) trait RetainHead: Decode {
+    /// Delegates to `MessageDecoder::set_retain_head`.
+    fn set_retain_head(&mut self, retain: bool);
+
+    /// Returns the number of the bytes consumed but not copied by the decoder.
+    fn retained_size(&self) -> usize;
+
+    /// Delegates to `MessageDecoder::pending_head_size`.
+    fn pending_head_size(&self) -> Option<usize>;
+
+    /// Attaches the bytes of the decoded head part, and handles the head.
+    fn attach_head(&mut self, head: Bytes) -> Result<()>;
+}
+
+/// Decodes bytes in `src` and advances it by the number of the consumed bytes.
+///
+/// The head part of a message is not copied.
+/// It is left in `src` until the decoding of the head completes,
+/// and then split off from `src` and attached to the decoder.
+#[cfg(feature = "bytes")]
+pub(crate) fn decode_bytes<D: RetainHead>(
+    decoder: &mut D,
+    src: &mut BytesMut,
+    eos: Eos,
+) -> Result<()> {
+    decoder.set_retain_head(true);
+    let result = decode_bytes_retaining_head(decoder, src, eos);
+    decoder.set_retain_head(false);
+    result
+}
+
+#[cfg(feature = "bytes")]
+fn decode_bytes_retaining_head<D: RetainHead>(
+    decoder: &mut D,
+    src: &mut BytesMut,
+    eos: Eos,
+) -> Result<()> {
+    loop {
+        let retained = decoder.retained_size();
+        let size = track!(decoder.decode(&src[retained..], eos))?;
+        let consumed = retained + size;
+        if let Some(head_size) = decoder.pending_head_size() {
+            let head = src.split_to(head_size).freeze();
+            src.advance(consumed - head_size);
+            track!(decoder.attach_head(head))?;
+            continue;
+        }
+
+        src.advance(consumed - decoder.retained_size());
+        if size == 0 || decoder.is_idle() {
+            return Ok(());
+        }
+    }
+}
+
+/// Decodes up to `limit` bytes of `buf` by using `decoder`.
+fn decode_within<D: Decode>(decoder: &mut D, buf: &[u8], eos: Eos, limit: usize) -> Result<usize> {
+    if buf.len() <= limit {
+        track!(decoder.decode(buf, eos))
+    } else {
+        track!(decoder.decode(&buf[..limit], Eos::new(false)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn head_buf_to_mut_keeps_terminating_empty_line() {
+        let mut buf = HeadBuf::from(b"GET / HTTP/1.1\r\nHost: a\r\n\r\n".to_vec());
+        assert_eq!(buf.to_mut(), b"GET / HTTP/1.1\r\nHost: a\r\n\r\n");
+
+        assert_eq!(buf.appendable_mut(), b"GET / HTTP/1.1\r\nHost: a\r\n");
+        assert_eq!(buf.appendable_mut(), b"GET / HTTP/1.1\r\nHost: a\r\n");
+        assert_eq!(buf.into_vec(), b"GET / HTTP/1.1\r\nHost: a\r\n");
+    }
+}
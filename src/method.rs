@@ -1,5 +1,6 @@
-use bytecodec::{ByteCount, Decode, Eos, ErrorKind, Result};
+use bytecodec::{ByteCount, Decode, Eos, Error, ErrorKind, Result};
 use std::fmt;
+use std::str::FromStr;
 
 use util;
 
@@ -41,6 +42,64 @@ impl<'a> fmt::Display for Method<'a> {
     }
 }
 
+/// An owned variant of `Method`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OwnedMethod(String);
+impl OwnedMethod {
+    /// Makes a new `OwnedMethod` instance.
+    ///
+    /// # Errors
+    ///
+    /// `method` must satisfy the same conditions as `Method::new`.
+    pub fn new<T: Into<String>>(method: T) -> Result<Self> {
+        let method = method.into();
+        track!(Method::new(&method))?;
+        Ok(OwnedMethod(method))
+    }
+
+    /// Returns the borrowed variant of the method.
+    pub fn as_method(&self) -> Method<'_> {
+        Method(&self.0)
+    }
+
+    /// Returns a reference to the inner string of the method.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Takes ownership of the instance, and returns the inner string.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+impl AsRef<str> for OwnedMethod {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for OwnedMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl FromStr for OwnedMethod {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        track!(Self::new(s))
+    }
+}
+impl<'a> From<Method<'a>> for OwnedMethod {
+    fn from(f: Method<'a>) -> Self {
+        OwnedMethod(f.0.to_owned())
+    }
+}
+impl<'a> From<&'a OwnedMethod> for Method<'a> {
+    fn from(f: &'a OwnedMethod) -> Self {
+        f.as_method()
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct MethodDecoder {
     size: usize,
@@ -106,4 +165,15 @@ mod test {
             Some(ErrorKind::InvalidInput)
         )
     }
+
+    #[test]
+    fn owned_method_works() {
+        let method = track_try_unwrap!(OwnedMethod::new("PATCH"));
+        assert_eq!(method.as_method(), Method("PATCH"));
+        assert_eq!(OwnedMethod::from(Method("GET")).as_str(), "GET");
+        assert_eq!(
+            "GE T".parse::<OwnedMethod>().err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
 }
@@ -0,0 +1,410 @@
+//! An in-process HTTP/1.1 server for tests.
+//!
+//! `MockServer` listens on a local port and answers requests with
+//! canned responses or responses made by handlers.
+//! The received requests are kept so that tests can make assertions on them.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate httpcodec;
+//! use httpcodec::mock::MockServer;
+//! use httpcodec::Response;
+//! use std::io::{Read, Write};
+//! use std::net::TcpStream;
+//!
+//! # fn main() {
+//! let server = MockServer::builder()
+//!     .respond("GET", "/foo", Response::builder().build(b"bar".to_vec()).unwrap())
+//!     .start()
+//!     .unwrap();
+//!
+//! let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+//! stream
+//!     .write_all(b"GET /foo?baz HTTP/1.1\r\nConnection: close\r\n\r\n")
+//!     .unwrap();
+//! let mut buf = Vec::new();
+//! stream.read_to_end(&mut buf).unwrap();
+//! assert_eq!(buf, b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar");
+//!
+//! let requests = server.received_requests();
+//! assert_eq!(requests.len(), 1);
+//! assert_eq!(requests[0].request_target().as_str(), "/foo?baz");
+//! # }
+//! ```
+use bytecodec::bytes::BytesEncoder;
+use bytecodec::io::IoEncodeExt;
+use bytecodec::{Encode, Result};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use chunked_body::ChunkedBodyEncoder;
+use local_server::{LocalServer, Reply};
+use {BodyEncode, BodyEncoder, HeadBodyEncoder, NoBodyEncoder, Request, Response, ResponseEncoder};
+
+type Handler = Box<dyn Fn(&Request<Vec<u8>>) -> Response<Vec<u8>> + Send + Sync>;
+
+/// Builder for `MockServer`.
+///
+/// Requests are matched against the routes in the order they were added,
+/// and the first matching route makes the response.
+/// If no route matches, the fallback handler is used (by default, it returns `404 Not Found`).
+///
+/// If the `Content-Length` field is contained in a response, it is replaced
+/// by the one computed from the body.
+/// If a response has the `Transfer-Encoding: chunked` field,
+/// its body is sent by using the chunked transfer coding.
+/// A `204 No Content` or `304 Not Modified` response is sent without
+/// the `Content-Length` field and the body.
+pub struct MockServerBuilder {
+    routes: Vec<Route>,
+    fallback: Handler,
+}
+impl MockServerBuilder {
+    /// Makes a new `MockServerBuilder` instance.
+    pub fn new() -> Self {
+        MockServerBuilder {
+            routes: Vec::new(),
+            fallback: Box::new(|_| not_found()),
+        }
+    }
+
+    /// Adds a route that answers requests matching `method` and `path` with `response`.
+    ///
+    /// Routes for `GET` also answer `HEAD` requests (the body is not sent in that case).
+    ///
+    /// If `path` contains a query string, it is compared with the whole request target,
+    /// otherwise only with the path component of the request target.
+    pub fn respond(self, method: &str, path: &str, response: Response<Vec<u8>>) -> Self {
+        self.handle(method, path, move |_| response.clone())
+    }
+
+    /// Adds a route that answers requests matching `method` and `path` by using `handler`.
+    ///
+    /// `path` is compared in the same way as `respond` method.
+    pub fn handle<F>(mut self, method: &str, path: &str, handler: F) -> Self
+    where
+        F: Fn(&Request<Vec<u8>>) -> Response<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method: method.to_owned(),
+            path: path.to_owned(),
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Sets the handler for requests that match no routes.
+    pub fn fallback<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Request<Vec<u8>>) -> Response<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.fallback = Box::new(handler);
+        self
+    }
+
+    /// Starts a server on `127.0.0.1` with an ephemeral port.
+    pub fn start(self) -> Result<MockServer> {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+        let server = track!(LocalServer::start(move |request| {
+            received
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(request.clone());
+            let response = self.handle_request(request);
+            let close = response.header().has_token("Connection", "close");
+            match encode_response(request, response) {
+                Ok(bytes) => Reply { bytes, close },
+                Err(_) => Reply {
+                    bytes: Vec::new(),
+                    close: true,
+                },
+            }
+        }))?;
+        Ok(MockServer { server, requests })
+    }
+
+    fn handle_request(&self, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+        for route in &self.routes {
+            if route.matches(request) {
+                return (route.handler)(request);
+            }
+        }
+        (self.fallback)(request)
+    }
+}
+impl Default for MockServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl fmt::Debug for MockServerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockServerBuilder")
+            .field("routes", &self.routes)
+            .finish()
+    }
+}
+
+struct Route {
+    method: String,
+    path: String,
+    handler: Handler,
+}
+impl Route {
+    fn matches<T>(&self, request: &Request<T>) -> bool {
+        let method = request.method().as_str();
+        if method != self.method && !(method == "HEAD" && self.method == "GET") {
+            return false;
+        }
+        let target = request.request_target().as_str();
+        if self.path.contains('?') {
+            target == self.path
+        } else {
+            target.split('?').next() == Some(&self.path[..])
+        }
+    }
+}
+impl fmt::Debug for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.path)
+    }
+}
+
+/// HTTP/1.1 server running in background threads for testing HTTP clients.
+///
+/// The server stops accepting connections when it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    server: LocalServer,
+    requests: Arc<Mutex<Vec<Request<Vec<u8>>>>>,
+}
+impl MockServer {
+    /// Makes a new `MockServerBuilder` instance.
+    pub fn builder() -> MockServerBuilder {
+        MockServerBuilder::new()
+    }
+
+    /// Returns the address that the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+
+    /// Returns the URL of the given request target on the server (e.g., `http://127.0.0.1:3000/foo`).
+    pub fn url(&self, target: &str) -> String {
+        format!("http://{}{}", self.local_addr(), target)
+    }
+
+    /// Returns the requests that the server has received so far, in the order of arrival.
+    pub fn received_requests(&self) -> Vec<Request<Vec<u8>>> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+fn not_found() -> Response<Vec<u8>> {
+    track_try_unwrap!(Response::builder()
+        .status_code(404)
+        .reason_phrase("Not Found")
+        .build(b"No matching route.\n".to_vec()))
+}
+
+fn encode_response(request: &Request<Vec<u8>>, response: Response<Vec<u8>>) -> Result<Vec<u8>> {
+    let chunked = response.header().has_token("Transfer-Encoding", "chunked");
+    let (head, body) = response.take_body();
+    let mut response = Response::new(
+        head.http_version(),
+        head.status_code(),
+        head.reason_phrase(),
+        body,
+    );
+    for field in head.header().fields() {
+        if !field.name().eq_ignore_ascii_case("Content-Length")
+            && !field.name().eq_ignore_ascii_case("Transfer-Encoding")
+        {
+            response.header_mut().add_field(field);
+        }
+    }
+
+    let mut buf = Vec::new();
+    let status = response.status_code().as_u16();
+    if status == 204 || status == 304 {
+        let (head, _) = response.take_body();
+        track!(encode(NoBodyEncoder, head, &mut buf))?;
+        return Ok(buf);
+    }
+
+    let is_head = request.method().as_str() == "HEAD";
+    match (is_head, chunked) {
+        (false, false) => track!(encode(
+            BodyEncoder::new(BytesEncoder::new()),
+            response,
+            &mut buf
+        ))?,
+        (false, true) => track!(encode(
+            ChunkedBodyEncoder::new(BytesEncoder::new()),
+            response,
+            &mut buf
+        ))?,
+        (true, false) => track!(encode(
+            HeadBodyEncoder::new(BodyEncoder::new(BytesEncoder::new())),
+            response,
+            &mut buf
+        ))?,
+        (true, true) => track!(encode(
+            HeadBodyEncoder::new(ChunkedBodyEncoder::new(BytesEncoder::new())),
+            response,
+            &mut buf
+        ))?,
+    }
+    Ok(buf)
+}
+
+fn encode<E: BodyEncode>(
+    body_encoder: E,
+    response: Response<E::Item>,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let mut encoder = ResponseEncoder::new(body_encoder);
+    track!(encoder.start_encoding(response))?;
+    track!(encoder.encode_all(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::bytes::RemainingBytesDecoder;
+    use bytecodec::{Decode, Eos};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    use super::*;
+    use {BodyDecoder, ResponseDecoder};
+
+    fn send(server: &MockServer, requests: &[u8]) -> Vec<u8> {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(requests).unwrap();
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+        buf
+    }
+
+    fn exchange(server: &MockServer, requests: &[u8]) -> Vec<Response<Vec<u8>>> {
+        let buf = send(server, requests);
+
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut offset = 0;
+        let mut responses = Vec::new();
+        while offset < buf.len() {
+            offset += track_try_unwrap!(decoder.decode(&buf[offset..], Eos::new(false)));
+            responses.push(track_try_unwrap!(decoder.finish_decoding()));
+        }
+        responses
+    }
+
+    #[test]
+    fn mock_server_works() {
+        let server = track_try_unwrap!(MockServer::builder()
+            .respond(
+                "GET",
+                "/foo",
+                track_try_unwrap!(Response::builder()
+                    .header("Content-Length", "100")
+                    .build(b"foo".to_vec()))
+            )
+            .handle("POST", "/echo", |request| {
+                track_try_unwrap!(Response::builder()
+                    .header("Transfer-Encoding", "chunked")
+                    .build(request.body().clone()))
+            })
+            .start());
+
+        let responses = exchange(
+            &server,
+            b"GET /foo?a=b HTTP/1.1\r\n\r\n\
+              POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nbar\r\n0\r\n\r\n\
+              DELETE /foo HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].body(), b"foo");
+        assert_eq!(responses[0].header().get_field("Content-Length"), Some("3"));
+        assert_eq!(responses[1].body(), b"bar");
+        assert_eq!(
+            responses[1].header().get_field("Transfer-Encoding"),
+            Some("chunked")
+        );
+        assert_eq!(responses[2].status_code().as_u16(), 404);
+
+        let response = send(&server, b"HEAD /foo HTTP/1.1\r\nConnection: close\r\n\r\n");
+        assert_eq!(response, b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n");
+
+        let requests = server.received_requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].request_target().as_str(), "/foo?a=b");
+        assert_eq!(requests[1].body(), b"bar");
+        assert_eq!(requests[3].method().as_str(), "HEAD");
+    }
+
+    #[test]
+    fn mock_server_sends_no_body_for_204_and_304() {
+        let server = track_try_unwrap!(MockServer::builder()
+            .respond(
+                "DELETE",
+                "/foo",
+                track_try_unwrap!(Response::builder()
+                    .status_code(204)
+                    .reason_phrase("No Content")
+                    .build(b"ignored".to_vec()))
+            )
+            .respond(
+                "GET",
+                "/foo",
+                track_try_unwrap!(Response::builder()
+                    .status_code(304)
+                    .reason_phrase("Not Modified")
+                    .header("ETag", "\"bar\"")
+                    .build(Vec::new()))
+            )
+            .start());
+
+        let response = send(
+            &server,
+            b"DELETE /foo HTTP/1.1\r\n\r\nGET /foo HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert_eq!(
+            response,
+            &b"HTTP/1.1 204 No Content\r\n\r\n\
+               HTTP/1.1 304 Not Modified\r\nETag: \"bar\"\r\n\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn mock_server_fallback_works() {
+        let server = track_try_unwrap!(MockServer::builder()
+            .respond(
+                "GET",
+                "/foo?a=b",
+                track_try_unwrap!(Response::builder().build(Vec::new()))
+            )
+            .start());
+        let responses = exchange(&server, b"GET /foo HTTP/1.1\r\nConnection: close\r\n\r\n");
+        assert_eq!(responses[0].status_code().as_u16(), 404);
+
+        let server = track_try_unwrap!(MockServer::builder()
+            .fallback(|_| track_try_unwrap!(Response::builder()
+                .status_code(503)
+                .header("Connection", "close")
+                .build(Vec::new())))
+            .start());
+        let responses = exchange(&server, b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].status_code().as_u16(), 503);
+        assert_eq!(
+            server.url("/bar"),
+            format!("http://{}/bar", server.local_addr())
+        );
+    }
+}
@@ -0,0 +1,553 @@
+//! Recording and replaying of HTTP/1.x traffic.
+//!
+//! `RecordingDecoder` and `RecordingEncoder` capture the raw bytes of the messages
+//! flowing through the decoders and encoders they wrap,
+//! and `Recorder` writes the captured request/response pairs to a recording file.
+//!
+//! A recording can be read back by `read_recording` function, and
+//! `ReplayServer` answers requests that match the recorded ones on a local port,
+//! so that HTTP clients can be tested without accessing the actual upstream servers.
+//! `ReplayServer` is available only if the `mock` feature is enabled.
+//!
+//! # Format
+//!
+//! A recording consists of the line `httpcodec-recording 1` followed by exchanges.
+//! Each exchange is a raw request and a raw response,
+//! and each of them is prefixed by a line holding a direction marker
+//! (`>` for requests and `<` for responses) and its length in bytes:
+//!
+//! ```text
+//! httpcodec-recording 1
+//! > {request length}
+//! {raw request}
+//! < {response length}
+//! {raw response}
+//! ...
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate bytecodec;
+//! # extern crate httpcodec;
+//! use bytecodec::bytes::RemainingBytesDecoder;
+//! use bytecodec::io::IoEncodeExt;
+//! use bytecodec::{DecodeExt, Encode};
+//! use httpcodec::recording::{self, Recorder, RecordingDecoder, RecordingEncoder};
+//! use httpcodec::{BodyDecoder, BodyEncoder, RequestDecoder, Response, ResponseEncoder};
+//! use bytecodec::bytes::BytesEncoder;
+//!
+//! # fn main() {
+//! let mut decoder = RecordingDecoder::new(RequestDecoder::new(BodyDecoder::for_request(
+//!     RemainingBytesDecoder::new(),
+//! )));
+//! let mut encoder = RecordingEncoder::new(ResponseEncoder::new(BodyEncoder::new(
+//!     BytesEncoder::new(),
+//! )));
+//! let mut recorder = Recorder::new(Vec::new()).unwrap();
+//!
+//! let request = decoder
+//!     .decode_from_bytes(b"GET /foo HTTP/1.1\r\nHost: example.com\r\n\r\n")
+//!     .unwrap();
+//! assert_eq!(request.request_target().as_str(), "/foo");
+//!
+//! let response = Response::builder().build(b"bar".to_vec()).unwrap();
+//! encoder.start_encoding(response).unwrap();
+//! encoder.encode_all(Vec::new()).unwrap();
+//!
+//! recorder
+//!     .record(decoder.recorded_bytes(), encoder.recorded_bytes())
+//!     .unwrap();
+//!
+//! let exchanges = recording::read_recording(&recorder.into_inner()[..]).unwrap();
+//! assert_eq!(exchanges.len(), 1);
+//! assert_eq!(
+//!     exchanges[0].request,
+//!     b"GET /foo HTTP/1.1\r\nHost: example.com\r\n\r\n"
+//! );
+//! assert_eq!(
+//!     exchanges[0].response,
+//!     b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar"
+//! );
+//! # }
+//! ```
+use bytecodec::bytes::RemainingBytesDecoder;
+use bytecodec::{ByteCount, Decode, DecodeExt, Encode, Eos, Error, ErrorKind, Result, SizedEncode};
+use std::io::{Read, Write};
+use std::mem;
+#[cfg(feature = "mock")]
+use std::net::SocketAddr;
+#[cfg(feature = "mock")]
+use std::sync::Mutex;
+use trackable::error::ErrorKindExt;
+
+#[cfg(feature = "mock")]
+use local_server::{LocalServer, Reply};
+use {BodyDecoder, Request, RequestDecoder};
+
+const MAGIC_LINE: &[u8] = b"httpcodec-recording 1\n";
+
+#[cfg(feature = "mock")]
+const NOT_FOUND_RESPONSE: &[u8] =
+    b"HTTP/1.1 404 Not Found\r\nContent-Length: 22\r\n\r\nNo recorded response.\n";
+
+/// A decoder that captures the raw bytes of the items decoded by the inner decoder.
+#[derive(Debug, Default)]
+pub struct RecordingDecoder<D> {
+    inner: D,
+    buf: Vec<u8>,
+    recorded: Vec<u8>,
+}
+impl<D: Decode> RecordingDecoder<D> {
+    /// Makes a new `RecordingDecoder` instance.
+    pub fn new(inner: D) -> Self {
+        RecordingDecoder {
+            inner,
+            buf: Vec::new(),
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Returns the raw bytes of the last decoded item.
+    pub fn recorded_bytes(&self) -> &[u8] {
+        &self.recorded
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn inner_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Takes ownership of the instance, and returns the inner decoder.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+impl<D: Decode> Decode for RecordingDecoder<D> {
+    type Item = D::Item;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let size = track!(self.inner.decode(buf, eos))?;
+        self.buf.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        let item = track!(self.inner.finish_decoding())?;
+        self.recorded = mem::take(&mut self.buf);
+        Ok(item)
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        self.inner.requiring_bytes()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.inner.is_idle()
+    }
+}
+
+/// An encoder that captures the raw bytes of the items encoded by the inner encoder.
+#[derive(Debug, Default)]
+pub struct RecordingEncoder<E> {
+    inner: E,
+    recorded: Vec<u8>,
+}
+impl<E: Encode> RecordingEncoder<E> {
+    /// Makes a new `RecordingEncoder` instance.
+    pub fn new(inner: E) -> Self {
+        RecordingEncoder {
+            inner,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// Returns the raw bytes that have been encoded since the last item was started.
+    ///
+    /// Once the encoder becomes idle, this holds the whole bytes of the item.
+    pub fn recorded_bytes(&self) -> &[u8] {
+        &self.recorded
+    }
+
+    /// Returns a reference to the inner encoder.
+    pub fn inner_ref(&self) -> &E {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner encoder.
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.inner
+    }
+
+    /// Takes ownership of the instance, and returns the inner encoder.
+    pub fn into_inner(self) -> E {
+        self.inner
+    }
+}
+impl<E: Encode> Encode for RecordingEncoder<E> {
+    type Item = E::Item;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        let size = track!(self.inner.encode(buf, eos))?;
+        self.recorded.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        track!(self.inner.start_encoding(item))?;
+        self.recorded.clear();
+        Ok(())
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        self.inner.requiring_bytes()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.inner.is_idle()
+    }
+}
+impl<E: SizedEncode> SizedEncode for RecordingEncoder<E> {
+    fn exact_requiring_bytes(&self) -> u64 {
+        self.inner.exact_requiring_bytes()
+    }
+}
+
+/// A pair of a raw request and the raw response to it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exchange {
+    /// The raw bytes of the request.
+    pub request: Vec<u8>,
+
+    /// The raw bytes of the response.
+    pub response: Vec<u8>,
+}
+
+/// Writer of recordings.
+#[derive(Debug)]
+pub struct Recorder<W> {
+    writer: W,
+}
+impl<W: Write> Recorder<W> {
+    /// Makes a new `Recorder` instance.
+    ///
+    /// The first line of a recording is written to `writer` immediately.
+    pub fn new(mut writer: W) -> Result<Self> {
+        track!(writer.write_all(MAGIC_LINE).map_err(Error::from))?;
+        Ok(Recorder { writer })
+    }
+
+    /// Appends an exchange of the given raw request and response to the recording.
+    pub fn record(&mut self, request: &[u8], response: &[u8]) -> Result<()> {
+        track!(self.write_entry(b'>', request))?;
+        track!(self.write_entry(b'<', response))?;
+        track!(self.writer.flush().map_err(Error::from))?;
+        Ok(())
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn writer_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Takes ownership of the instance, and returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_entry(&mut self, marker: u8, bytes: &[u8]) -> Result<()> {
+        let line = format!("{} {}\n", marker as char, bytes.len());
+        track!(self.writer.write_all(line.as_bytes()).map_err(Error::from))?;
+        track!(self.writer.write_all(bytes).map_err(Error::from))?;
+        track!(self.writer.write_all(b"\n").map_err(Error::from))?;
+        Ok(())
+    }
+}
+
+/// Reads the exchanges contained in the given recording.
+///
+/// # Errors
+///
+/// If the recording is malformed, an `ErrorKind::InvalidInput` error will be returned.
+pub fn read_recording<R: Read>(mut reader: R) -> Result<Vec<Exchange>> {
+    let mut buf = Vec::new();
+    track!(reader.read_to_end(&mut buf).map_err(Error::from))?;
+    track_assert!(buf.starts_with(MAGIC_LINE), ErrorKind::InvalidInput);
+
+    let mut rest = &buf[MAGIC_LINE.len()..];
+    let mut exchanges = Vec::new();
+    while !rest.is_empty() {
+        let request = track!(read_entry(&mut rest, b'>'))?;
+        let response = track!(read_entry(&mut rest, b'<'))?;
+        exchanges.push(Exchange { request, response });
+    }
+    Ok(exchanges)
+}
+
+fn read_entry(rest: &mut &[u8], marker: u8) -> Result<Vec<u8>> {
+    let line_end = track_assert_some!(
+        rest.iter().position(|&b| b == b'\n'),
+        ErrorKind::InvalidInput
+    );
+    let line = track!(
+        ::std::str::from_utf8(&rest[..line_end]).map_err(|e| ErrorKind::InvalidInput.cause(e))
+    )?;
+    track_assert!(line.as_bytes().first() == Some(&marker), ErrorKind::InvalidInput; line);
+    track_assert!(line.as_bytes().get(1) == Some(&b' '), ErrorKind::InvalidInput; line);
+    let size: usize = track!(line[2..]
+        .parse()
+        .map_err(|e| ErrorKind::InvalidInput.cause(e)))?;
+
+    let bytes = &rest[line_end + 1..];
+    track_assert!(bytes.len() > size, ErrorKind::InvalidInput; line);
+    track_assert_eq!(bytes[size], b'\n', ErrorKind::InvalidInput; line);
+    let entry = bytes[..size].to_vec();
+    *rest = &bytes[size + 1..];
+    Ok(entry)
+}
+
+/// A set of recorded responses to be replayed.
+///
+/// A request is matched against the recorded requests by its method, request target and body.
+/// If several recorded requests match, their responses are replayed in the order they were recorded,
+/// and the last one is repeated once all of them have been replayed.
+#[derive(Debug)]
+pub struct Replayer {
+    entries: Vec<ReplayEntry>,
+}
+impl Replayer {
+    /// Makes a new `Replayer` instance that replays the given exchanges.
+    ///
+    /// # Errors
+    ///
+    /// If any of the recorded requests cannot be decoded,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    pub fn new(exchanges: Vec<Exchange>) -> Result<Self> {
+        let mut entries = Vec::with_capacity(exchanges.len());
+        for exchange in exchanges {
+            let mut decoder =
+                RequestDecoder::new(BodyDecoder::for_request(RemainingBytesDecoder::new()));
+            let request = track!(decoder
+                .decode_from_bytes(&exchange.request)
+                .map_err(|e| ErrorKind::InvalidInput.takes_over(e)))?;
+            entries.push(ReplayEntry {
+                method: request.method().as_str().to_owned(),
+                request_target: request.request_target().as_str().to_owned(),
+                body: request.into_body(),
+                response: exchange.response,
+                replayed: false,
+            });
+        }
+        Ok(Replayer { entries })
+    }
+
+    /// Makes a new `Replayer` instance from the given recording.
+    pub fn from_recording<R: Read>(reader: R) -> Result<Self> {
+        let exchanges = track!(read_recording(reader))?;
+        track!(Self::new(exchanges))
+    }
+
+    /// Returns the raw response recorded for the given request.
+    ///
+    /// If there is no matching request, this returns `None`.
+    pub fn respond<T: AsRef<[u8]>>(&mut self, request: &Request<T>) -> Option<&[u8]> {
+        let mut matched = None;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if entry.matches(request) {
+                matched = Some(i);
+                if !entry.replayed {
+                    break;
+                }
+            }
+        }
+        let entry = &mut self.entries[matched?];
+        entry.replayed = true;
+        Some(&entry.response)
+    }
+}
+
+#[derive(Debug)]
+struct ReplayEntry {
+    method: String,
+    request_target: String,
+    body: Vec<u8>,
+    response: Vec<u8>,
+    replayed: bool,
+}
+impl ReplayEntry {
+    fn matches<T: AsRef<[u8]>>(&self, request: &Request<T>) -> bool {
+        request.method().as_str() == self.method
+            && request.request_target().as_str() == self.request_target
+            && request.body().as_ref() == &self.body[..]
+    }
+}
+
+/// A server that replays recorded responses on a local port.
+///
+/// The server accepts connections on `127.0.0.1` in a background thread,
+/// and answers each request with the response that `Replayer::respond` returns.
+/// Requests that have no recorded response are answered with `404 Not Found`.
+///
+/// Connections are kept alive unless the client requests otherwise.
+/// The server stops accepting connections when it is dropped.
+///
+/// This is available only if the `mock` feature is enabled.
+#[cfg(feature = "mock")]
+#[derive(Debug)]
+pub struct ReplayServer {
+    server: LocalServer,
+}
+#[cfg(feature = "mock")]
+impl ReplayServer {
+    /// Starts a new server that replays the responses held by `replayer`.
+    pub fn start(replayer: Replayer) -> Result<Self> {
+        let replayer = Mutex::new(replayer);
+        let server = track!(LocalServer::start(move |request| {
+            let mut replayer = replayer.lock().unwrap_or_else(|e| e.into_inner());
+            let bytes = replayer
+                .respond(request)
+                .unwrap_or(NOT_FOUND_RESPONSE)
+                .to_vec();
+            Reply {
+                bytes,
+                close: false,
+            }
+        }))?;
+        Ok(ReplayServer { server })
+    }
+
+    /// Returns the address that the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::bytes::RemainingBytesDecoder;
+    use bytecodec::DecodeExt;
+    #[cfg(feature = "mock")]
+    use std::io::{Read, Write};
+    #[cfg(feature = "mock")]
+    use std::net::TcpStream;
+
+    use super::*;
+    #[cfg(feature = "mock")]
+    use ResponseDecoder;
+    use {BodyDecoder, RequestDecoder};
+
+    fn exchange(request: &[u8], response: &[u8]) -> Exchange {
+        Exchange {
+            request: request.to_vec(),
+            response: response.to_vec(),
+        }
+    }
+
+    fn request(bytes: &[u8]) -> Request<Vec<u8>> {
+        let mut decoder =
+            RequestDecoder::new(BodyDecoder::for_request(RemainingBytesDecoder::new()));
+        track_try_unwrap!(decoder.decode_from_bytes(bytes))
+    }
+
+    #[test]
+    fn recording_works() {
+        let mut recorder = track_try_unwrap!(Recorder::new(Vec::new()));
+        track_try_unwrap!(recorder.record(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.0 200 OK\r\n\r\n\n"));
+        track_try_unwrap!(recorder.record(b"foo", b""));
+
+        let recording = recorder.into_inner();
+        assert_eq!(
+            recording,
+            &b"httpcodec-recording 1\n> 18\nGET / HTTP/1.1\r\n\r\n\n< 20\nHTTP/1.0 200 OK\r\n\r\n\n\n> 3\nfoo\n< 0\n\n"[..]
+        );
+
+        let exchanges = track_try_unwrap!(read_recording(&recording[..]));
+        assert_eq!(
+            exchanges,
+            vec![
+                exchange(b"GET / HTTP/1.1\r\n\r\n", b"HTTP/1.0 200 OK\r\n\r\n\n"),
+                exchange(b"foo", b""),
+            ]
+        );
+
+        assert!(read_recording(&recording[1..]).is_err());
+        assert!(read_recording(&recording[..recording.len() - 1]).is_err());
+        assert!(read_recording(&b"httpcodec-recording 1\n< 0\n\n"[..]).is_err());
+    }
+
+    #[test]
+    fn replayer_works() {
+        let mut replayer = track_try_unwrap!(Replayer::new(vec![
+            exchange(b"GET /foo HTTP/1.1\r\n\r\n", b"first"),
+            exchange(
+                b"POST /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nbar",
+                b"post"
+            ),
+            exchange(b"GET /foo HTTP/1.1\r\nX-Foo: ignored\r\n\r\n", b"second"),
+        ]));
+
+        let get = request(b"GET /foo HTTP/1.1\r\nHost: example.com\r\n\r\n");
+        assert_eq!(replayer.respond(&get), Some(&b"first"[..]));
+        assert_eq!(replayer.respond(&get), Some(&b"second"[..]));
+        assert_eq!(replayer.respond(&get), Some(&b"second"[..]));
+
+        let post = request(
+            b"POST /foo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nbar\r\n0\r\n\r\n",
+        );
+        assert_eq!(replayer.respond(&post), Some(&b"post"[..]));
+
+        let post = request(b"POST /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nbaz");
+        assert_eq!(replayer.respond(&post), None);
+        assert_eq!(
+            replayer.respond(&request(b"GET /bar HTTP/1.1\r\n\r\n")),
+            None
+        );
+
+        assert!(Replayer::new(vec![exchange(b"GET / HTTP/1.1\r\n", b"")]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn replay_server_works() {
+        let replayer = track_try_unwrap!(Replayer::new(vec![
+            exchange(
+                b"GET /foo HTTP/1.1\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar",
+            ),
+            exchange(
+                b"PUT /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nqux",
+                b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n",
+            ),
+        ]));
+        let server = track_try_unwrap!(ReplayServer::start(replayer));
+
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream
+            .write_all(b"GET /foo HTTP/1.1\r\nHost: localhost\r\n\r\nPUT /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nqux")
+            .unwrap();
+        stream
+            .write_all(b"GET /bar HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).unwrap();
+
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut offset = 0;
+        let mut responses = Vec::new();
+        while offset < buf.len() {
+            offset += track_try_unwrap!(decoder.decode(&buf[offset..], Eos::new(false)));
+            responses.push(track_try_unwrap!(decoder.finish_decoding()));
+        }
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].status_code().as_u16(), 200);
+        assert_eq!(responses[0].body(), b"bar");
+        assert_eq!(responses[1].status_code().as_u16(), 201);
+        assert_eq!(responses[2].status_code().as_u16(), 404);
+    }
+}
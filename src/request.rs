@@ -1,28 +1,51 @@
 use bytecodec::tuple::TupleDecoder;
-use bytecodec::{ByteCount, Decode, Encode, Eos, Result, SizedEncode};
+use bytecodec::{ByteCount, Decode, Encode, Eos, Error, ErrorKind, Result, SizedEncode};
+#[cfg(feature = "bytes")]
+use bytes::{Bytes, BytesMut};
+use std::cmp;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::IoSlice;
 use std::str;
 
 use body::{BodyDecode, BodyEncode};
-use header::{Header, HeaderFieldPosition, HeaderMut};
-use message::{Message, MessageDecoder, MessageEncoder};
+use header::{Header, HeaderField, HeaderFieldPosition, HeaderMut};
+use http2::PREFACE;
+#[cfg(feature = "bytes")]
+use message::{self, RetainHead};
+use message::{
+    HeadBuf, HeadPart, Message, MessageDecode, MessageDecoder, MessageEncode, MessageEncoder,
+};
 use method::{Method, MethodDecoder};
 use options::DecodeOptions;
 use request_target::{RequestTarget, RequestTargetDecoder};
+use upgrade::ProtocolSwitch;
 use util::CrlfDecoder;
+use vectored::EncodeVectored;
 use version::{HttpVersion, HttpVersionDecoder};
 
 /// HTTP request message.
-#[derive(Debug)]
+///
+/// Two messages are equal if they have the same start line, header fields and body.
+/// Header field names are compared case-insensitively.
+#[derive(Debug, Clone)]
 pub struct Request<T> {
-    buf: Vec<u8>,
+    buf: HeadBuf,
     request_line: RequestLine,
     header: Vec<HeaderFieldPosition>,
     body: T,
 }
 impl<T> Request<T> {
     /// Makes a new `Request` instance with the given request-line components and body.
-    pub fn new(method: Method, target: RequestTarget, version: HttpVersion, body: T) -> Self {
+    ///
+    /// `method` and `target` can also be given as `&OwnedMethod` and `&OwnedRequestTarget`.
+    pub fn new<'a, M, R>(method: M, target: R, version: HttpVersion, body: T) -> Self
+    where
+        M: Into<Method<'a>>,
+        R: Into<RequestTarget<'a>>,
+    {
+        let method = method.into();
+        let target = target.into();
         let mut buf = Vec::new();
         buf.extend_from_slice(method.as_str().as_bytes());
         buf.push(b' ');
@@ -38,7 +61,7 @@ impl<T> Request<T> {
         };
 
         Request {
-            buf,
+            buf: HeadBuf::from(buf),
             request_line,
             header: Vec::new(),
             body,
@@ -66,6 +89,34 @@ impl<T> Request<T> {
         self.request_line.http_version
     }
 
+    /// Sets the method of the request.
+    pub fn set_method<'a, M: Into<Method<'a>>>(&mut self, method: M) {
+        let method = method.into();
+        let range = 0..self.request_line.method_size;
+        self.buf
+            .replace(range, method.as_str().as_bytes(), &mut self.header);
+        self.request_line.method_size = method.as_str().len();
+    }
+
+    /// Sets the target of the request.
+    pub fn set_request_target<'a, R: Into<RequestTarget<'a>>>(&mut self, target: R) {
+        let target = target.into();
+        let start = self.request_line.method_size + 1;
+        let range = start..start + self.request_line.request_target_size;
+        self.buf
+            .replace(range, target.as_str().as_bytes(), &mut self.header);
+        self.request_line.request_target_size = target.as_str().len();
+    }
+
+    /// Sets the HTTP version of the request.
+    pub fn set_http_version(&mut self, version: HttpVersion) {
+        let start = self.request_line.method_size + 1 + self.request_line.request_target_size + 1;
+        let range = start..start + 8;
+        self.buf
+            .replace(range, version.as_str().as_bytes(), &mut self.header);
+        self.request_line.http_version = version;
+    }
+
     /// Returns the header of the request.
     pub fn header(&self) -> Header {
         Header::new(&self.buf, &self.header)
@@ -73,7 +124,25 @@ impl<T> Request<T> {
 
     /// Returns the mutable header of the request.
     pub fn header_mut(&mut self) -> HeaderMut {
-        HeaderMut::new(&mut self.buf, &mut self.header)
+        HeaderMut::new(self.buf.appendable_mut(), &mut self.header)
+    }
+
+    /// Returns the head part (i.e., the request-line and the header) of the request as `Bytes`.
+    ///
+    /// If the request has been decoded by `RequestDecoder::decode_bytes`,
+    /// this returns the received bytes verbatim without copying them.
+    #[cfg(feature = "bytes")]
+    pub fn head_bytes(&self) -> Bytes {
+        self.buf.to_bytes()
+    }
+
+    /// Returns the value of the first field that has the name `name` in the header as `Bytes`.
+    ///
+    /// If the request has been decoded by `RequestDecoder::decode_bytes`,
+    /// the returned value shares the storage of the request.
+    #[cfg(feature = "bytes")]
+    pub fn header_field_bytes(&self, name: &str) -> Option<Bytes> {
+        self.buf.field_bytes(&self.header, name)
     }
 
     /// Returns a reference to the body of the request.
@@ -116,6 +185,31 @@ impl<T> Request<T> {
         }
     }
 }
+impl Request<()> {
+    /// Returns a builder for making a request.
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::new()
+    }
+}
+impl<T: PartialEq> PartialEq for Request<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.method() == other.method()
+            && self.request_target() == other.request_target()
+            && self.http_version() == other.http_version()
+            && self.header() == other.header()
+            && self.body == other.body
+    }
+}
+impl<T: Eq> Eq for Request<T> {}
+impl<T: Hash> Hash for Request<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.method().hash(state);
+        self.request_target().hash(state);
+        self.http_version().hash(state);
+        self.header().hash(state);
+        self.body.hash(state);
+    }
+}
 impl<T: fmt::Display> fmt::Display for Request<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
@@ -131,9 +225,127 @@ impl<T: fmt::Display> fmt::Display for Request<T> {
     }
 }
 
+/// A builder of `Request`.
+///
+/// Each component is validated when it is given, and
+/// the first validation error (if any) is reported by `build` method.
+///
+/// The default request-line is `GET / HTTP/1.1`.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate httpcodec;
+/// use httpcodec::Request;
+///
+/// # fn main() {
+/// let request = Request::builder()
+///     .method("POST")
+///     .request_target("/foo")
+///     .header("Content-Type", "text/plain")
+///     .build("bar")
+///     .unwrap();
+/// assert_eq!(
+///     request.to_string(),
+///     "POST /foo HTTP/1.1\r\nContent-Type: text/plain\r\n\r\nbar"
+/// );
+///
+/// let result = Request::builder().header("Bad Name", "foo").build(());
+/// assert!(result.is_err());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RequestBuilder {
+    method: String,
+    target: String,
+    version: HttpVersion,
+    fields: Vec<(String, String)>,
+    error: Option<Error>,
+}
+impl RequestBuilder {
+    /// Makes a new `RequestBuilder` instance.
+    pub fn new() -> Self {
+        RequestBuilder {
+            method: "GET".to_owned(),
+            target: "/".to_owned(),
+            version: HttpVersion::V1_1,
+            fields: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Sets the method of the request.
+    pub fn method(mut self, method: &str) -> Self {
+        let result = track!(Method::new(method), "method={:?}", method).map(|_| ());
+        self.check(result);
+        self.method = method.to_owned();
+        self
+    }
+
+    /// Sets the target of the request.
+    pub fn request_target(mut self, target: &str) -> Self {
+        let result = track!(RequestTarget::new(target), "request_target={:?}", target);
+        self.check(result.map(|_| ()));
+        self.target = target.to_owned();
+        self
+    }
+
+    /// Sets the HTTP version of the request.
+    pub fn http_version(mut self, version: HttpVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Adds a header field to the request.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let result = track!(HeaderField::new(name, value), "header={:?}", name).map(|_| ());
+        self.check(result);
+        self.fields.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Builds a request that has the given body.
+    ///
+    /// # Errors
+    ///
+    /// If any of the given components is invalid,
+    /// the error of the first one will be returned.
+    pub fn build<T>(self, body: T) -> Result<Request<T>> {
+        if let Some(e) = self.error {
+            return Err(track!(e));
+        }
+        let method = track!(Method::new(&self.method))?;
+        let target = track!(RequestTarget::new(&self.target))?;
+        let mut request = Request::new(method, target, self.version, body);
+        for (name, value) in &self.fields {
+            request
+                .header_mut()
+                .add_field(track!(HeaderField::new(name, value))?);
+        }
+        Ok(request)
+    }
+
+    fn check(&mut self, result: Result<()>) {
+        if let (None, Err(e)) = (&self.error, result) {
+            self.error = Some(e);
+        }
+    }
+}
+impl Default for RequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// HTTP request decoder.
 #[derive(Debug)]
-pub struct RequestDecoder<D>(MessageDecoder<RequestLineDecoder, D>);
+pub struct RequestDecoder<D> {
+    inner: MessageDecoder<RequestLineDecoder, D>,
+    expect_continue: bool,
+    suspended: bool,
+    protocol_switch: Option<ProtocolSwitch>,
+    preface_matched: Option<usize>,
+}
 impl<D: BodyDecode> RequestDecoder<D> {
     /// Make a new `RequestDecoder` instance.
     pub fn new(body_decoder: D) -> Self {
@@ -143,18 +355,189 @@ impl<D: BodyDecode> RequestDecoder<D> {
     /// Make a new `RequestDecoder` instance with the given options.
     pub fn with_options(body_decoder: D, options: DecodeOptions) -> Self {
         let inner = MessageDecoder::new(RequestLineDecoder::default(), body_decoder, options);
-        RequestDecoder(inner)
+        RequestDecoder {
+            inner,
+            expect_continue: false,
+            suspended: false,
+            protocol_switch: None,
+            preface_matched: Some(0),
+        }
+    }
+
+    /// Enables or disables the handling of `Expect: 100-continue` headers.
+    ///
+    /// If it is enabled, the decoder suspends just after decoding the header of
+    /// an HTTP/1.1 request that has the `Expect: 100-continue` field
+    /// (the expectation is ignored for HTTP/1.0 requests as RFC 7231 requires).
+    /// The application can examine the head part by calling `peek_head` method, and then
+    /// call `resume` to decode the body (e.g., after sending a `100 Continue` response) or
+    /// `reject_body` to refuse it (e.g., after sending a `417 Expectation Failed` response).
+    ///
+    /// The default value is `false`.
+    pub fn set_expect_continue(&mut self, enabled: bool) {
+        self.expect_continue = enabled;
+    }
+
+    /// Returns `true` if the decoder is suspended before decoding a request body, otherwise `false`.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Resumes the decoding of the body of the current request.
+    pub fn resume(&mut self) {
+        self.suspended = false;
+    }
+
+    /// Refuses the body of the current (suspended) request.
+    ///
+    /// The request is completed as if it had no body, so the decoder becomes idle
+    /// without consuming any more bytes and `finish_decoding` returns the request
+    /// with an empty body.
+    ///
+    /// The connection can be used for subsequent requests only if the client has
+    /// not sent the body (i.e., it has waited for the final response).
+    /// Because this cannot be known in general, a server usually closes the connection
+    /// after rejecting a body unless the client is known to honor the expectation.
+    ///
+    /// # Errors
+    ///
+    /// If the decoder is not suspended, `ErrorKind::InconsistentState` will be returned.
+    pub fn reject_body(&mut self) -> Result<()> {
+        track_assert!(self.suspended, ErrorKind::InconsistentState);
+        track!(self.inner.skip_body())?;
+        self.suspended = false;
+        Ok(())
+    }
+
+    /// Returns the protocol switch requested by the last decoded request.
+    ///
+    /// A `CONNECT` request results in `ProtocolSwitch::Tunnel`, and a request that has
+    /// the `Upgrade` header field (and the "upgrade" option in the `Connection` field)
+    /// results in `ProtocolSwitch::Upgrade`.
+    ///
+    /// In addition, if the HTTP/2 connection preface is received instead of a request,
+    /// `ProtocolSwitch::Http2Preface` is reported.
+    ///
+    /// Once a switch is detected, the decoder consumes no more bytes after the request.
+    /// If the server accepts the switch, the bytes following the request
+    /// (they remain in the caller's buffer) should be handed to the next protocol layer.
+    /// Otherwise, `clear_protocol_switch` can be used to continue decoding HTTP requests.
+    pub fn protocol_switch(&self) -> Option<ProtocolSwitch> {
+        self.protocol_switch
+    }
+
+    /// Clears the detected protocol switch, and resumes decoding subsequent requests.
+    pub fn clear_protocol_switch(&mut self) {
+        self.protocol_switch = None;
+    }
+
+    /// Returns a copy of the head part of the request being decoded.
+    ///
+    /// If the header of the request has not been decoded yet, this will return `None`.
+    pub fn peek_head(&self) -> Option<Request<()>> {
+        self.inner.peek_head().map(|m| Request {
+            buf: m.buf,
+            request_line: m.start_line,
+            header: m.header,
+            body: (),
+        })
+    }
+
+    /// Returns a reference to the body decoder.
+    pub fn body_decoder_ref(&self) -> &D {
+        self.inner.body_ref()
+    }
+
+    /// Returns a mutable reference to the body decoder.
+    pub fn body_decoder_mut(&mut self) -> &mut D {
+        self.inner.body_mut()
+    }
+
+    /// Returns the part of the head that exceeded the size limit given by `DecodeOptions`.
+    pub(crate) fn exceeded_limit(&self) -> Option<HeadPart> {
+        self.inner.exceeded_limit()
+    }
+
+    /// Decodes bytes in `src` without copying the head part of the request.
+    ///
+    /// The consumed bytes are removed from `src`.
+    /// The head part of a decoded request shares the storage of `src`
+    /// (see `Request::head_bytes` and `Request::header_field_bytes`).
+    ///
+    /// Note that this and `decode` method should not be used together for decoding a request.
+    #[cfg(feature = "bytes")]
+    pub fn decode_bytes(&mut self, src: &mut BytesMut, eos: Eos) -> Result<()> {
+        track!(message::decode_bytes(self, src, eos))
+    }
+
+    fn handle_head(&mut self) -> Result<()> {
+        let mut skip_body = false;
+        if let Some((request_line, header)) = self.inner.head() {
+            if &self.inner.buf()[..request_line.method_size] == b"CONNECT" {
+                self.protocol_switch = Some(ProtocolSwitch::Tunnel);
+                skip_body = true;
+            } else if header.get_field("Upgrade").is_some()
+                && header.has_token("Connection", "upgrade")
+            {
+                self.protocol_switch = Some(ProtocolSwitch::Upgrade);
+                skip_body = header.get_field("Content-Length").is_none()
+                    && header.get_field("Transfer-Encoding").is_none();
+            }
+            // A request without body does not wait for `100 Continue`
+            self.suspended = self.expect_continue
+                && !skip_body
+                && request_line.http_version == HttpVersion::V1_1
+                && expects_continue(&header);
+        }
+        if skip_body {
+            track!(self.inner.skip_body())?;
+        }
+        Ok(())
     }
 }
 impl<D: BodyDecode> Decode for RequestDecoder<D> {
     type Item = Request<D::Item>;
 
     fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
-        track!(self.0.decode(buf, eos))
+        if self.protocol_switch.is_some() && !self.inner.is_head_decoded() {
+            return Ok(0);
+        }
+
+        let mut offset = 0;
+        if let Some(matched) = self.preface_matched {
+            let n = cmp::min(buf.len(), PREFACE.len() - matched);
+            if buf[..n] == PREFACE[matched..][..n] && !eos.is_reached() {
+                if matched + n == PREFACE.len() {
+                    self.protocol_switch = Some(ProtocolSwitch::Http2Preface);
+                    self.preface_matched = None;
+                } else {
+                    self.preface_matched = Some(matched + n);
+                }
+                return Ok(n);
+            }
+
+            // Not a preface: feeds the bytes held so far to the inner decoder
+            self.preface_matched = None;
+            let size = track!(self.inner.decode_head(&PREFACE[..matched], Eos::new(false)))?;
+            track_assert_eq!(size, matched, ErrorKind::InconsistentState);
+        }
+        if !self.inner.is_head_decoded() {
+            offset += track!(self.inner.decode_head(buf, eos))?;
+            if self.inner.is_head_decoded() {
+                track!(self.handle_head())?;
+            }
+        }
+        if self.suspended {
+            return Ok(offset);
+        }
+        offset += track!(self.inner.decode(&buf[offset..], eos))?;
+        Ok(offset)
     }
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
-        let m = track!(self.0.finish_decoding())?;
+        track_assert!(!self.suspended, ErrorKind::IncompleteDecoding);
+        let m = track!(self.inner.finish_decoding())?;
+        self.preface_matched = Some(0);
         Ok(Request {
             buf: m.buf,
             request_line: m.start_line,
@@ -164,11 +547,47 @@ impl<D: BodyDecode> Decode for RequestDecoder<D> {
     }
 
     fn requiring_bytes(&self) -> ByteCount {
-        self.0.requiring_bytes()
+        if self.suspended {
+            ByteCount::Unknown
+        } else {
+            self.inner.requiring_bytes()
+        }
     }
 
     fn is_idle(&self) -> bool {
-        self.0.is_idle()
+        !self.suspended && self.inner.is_idle()
+    }
+}
+impl<D: BodyDecode> MessageDecode for RequestDecoder<D> {
+    fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    fn switched_protocol(&self) -> Option<ProtocolSwitch> {
+        if self.inner.is_head_decoded() {
+            None
+        } else {
+            self.protocol_switch
+        }
+    }
+}
+#[cfg(feature = "bytes")]
+impl<D: BodyDecode> RetainHead for RequestDecoder<D> {
+    fn set_retain_head(&mut self, retain: bool) {
+        self.inner.set_retain_head(retain);
+    }
+
+    fn retained_size(&self) -> usize {
+        self.preface_matched.unwrap_or(0) + self.inner.unattached_head_size()
+    }
+
+    fn pending_head_size(&self) -> Option<usize> {
+        self.inner.pending_head_size()
+    }
+
+    fn attach_head(&mut self, head: Bytes) -> Result<()> {
+        track!(self.inner.attach_head(head))?;
+        track!(self.handle_head())
     }
 }
 impl<D: Default + BodyDecode> Default for RequestDecoder<D> {
@@ -177,7 +596,7 @@ impl<D: Default + BodyDecode> Default for RequestDecoder<D> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct RequestLine {
     method_size: usize,
     request_target_size: usize,
@@ -220,42 +639,102 @@ impl Decode for RequestLineDecoder {
 
 /// HTTP request encoder.
 #[derive(Debug, Default)]
-pub struct RequestEncoder<E>(MessageEncoder<E>);
+pub struct RequestEncoder<E> {
+    inner: MessageEncoder<E>,
+    expect_continue: bool,
+}
 impl<E: BodyEncode> RequestEncoder<E> {
     /// Makes a new `RequestEncoder` instance.
     pub fn new(body_encoder: E) -> Self {
-        RequestEncoder(MessageEncoder::new(body_encoder))
+        RequestEncoder {
+            inner: MessageEncoder::new(body_encoder),
+            expect_continue: false,
+        }
+    }
+
+    /// Enables or disables the handling of `Expect: 100-continue` headers.
+    ///
+    /// If it is enabled, the encoder holds back the body of a request that has
+    /// the `Expect: 100-continue` field until `resume` is called
+    /// (HTTP/1.0 requests are never held back, because servers do not send
+    /// `100 Continue` responses to them).
+    /// The application is expected to call `resume` after receiving a `100 Continue` response.
+    ///
+    /// While the body is held back, the encoder produces no bytes after the head part
+    /// but is not idle, so no new request can be started until the body is resumed and encoded.
+    /// `is_suspended` tells this state apart from the completion of the request.
+    ///
+    /// The default value is `false`.
+    pub fn set_expect_continue(&mut self, enabled: bool) {
+        self.expect_continue = enabled;
+    }
+
+    /// Returns `true` if the encoder is holding back a request body, otherwise `false`.
+    pub fn is_suspended(&self) -> bool {
+        self.inner.is_suspended()
+    }
+
+    /// Resumes the encoding of the body of the current request.
+    pub fn resume(&mut self) {
+        self.inner.resume();
     }
 }
 impl<E: BodyEncode> Encode for RequestEncoder<E> {
     type Item = Request<E::Item>;
 
     fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
-        track!(self.0.encode(buf, eos))
+        track!(self.inner.encode(buf, eos))
     }
 
     fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        let expect_continue = self.expect_continue
+            && item.http_version() == HttpVersion::V1_1
+            && expects_continue(&item.header());
         let item = Message {
             buf: item.buf,
             start_line: (),
             header: item.header,
             body: item.body,
         };
-        track!(self.0.start_encoding(item))
+        track!(self.inner.start_encoding(item))?;
+        if expect_continue {
+            self.inner.suspend();
+        }
+        Ok(())
     }
 
     fn is_idle(&self) -> bool {
-        self.0.is_idle()
+        self.inner.is_idle()
     }
 
     fn requiring_bytes(&self) -> ByteCount {
-        self.0.requiring_bytes()
+        self.inner.requiring_bytes()
+    }
+}
+impl<E: BodyEncode> MessageEncode for RequestEncoder<E> {
+    fn is_suspended(&self) -> bool {
+        self.inner.is_suspended()
     }
 }
 impl<E: SizedEncode + BodyEncode> SizedEncode for RequestEncoder<E> {
     fn exact_requiring_bytes(&self) -> u64 {
-        self.0.exact_requiring_bytes()
+        self.inner.exact_requiring_bytes()
+    }
+}
+impl<E: BodyEncode + EncodeVectored> EncodeVectored for RequestEncoder<E> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.inner.pending_slices(slices))
     }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track!(self.inner.consume(size))
+    }
+}
+
+fn expects_continue(header: &Header) -> bool {
+    header.fields().any(|f| {
+        f.name().eq_ignore_ascii_case("expect") && f.value().eq_ignore_ascii_case("100-continue")
+    })
 }
 
 #[cfg(test)]
@@ -266,7 +745,44 @@ mod test {
     use std::str;
 
     use super::*;
-    use {BodyDecoder, BodyEncoder, HttpVersion, Method, RequestTarget};
+    use {
+        BodyDecoder, BodyEncoder, HeaderField, HttpVersion, Method, OwnedHeaderField, OwnedMethod,
+        OwnedRequestTarget, ProtocolSwitch, RequestTarget,
+    };
+
+    #[test]
+    fn request_decoder_limits_work() {
+        let options = DecodeOptions {
+            max_start_line_size: 20,
+            max_header_size: 20,
+        };
+        let input = b"GET / HTTP/1.1\r\nHost: a\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut decoder = RequestDecoder::with_options(
+            BodyDecoder::for_request(RemainingBytesDecoder::new()),
+            options.clone(),
+        );
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, input.len() / 2);
+        track_try_unwrap!(decoder.finish_decoding());
+        track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false)));
+        track_try_unwrap!(decoder.finish_decoding());
+
+        let mut decoder = RequestDecoder::with_options(
+            BodyDecoder::for_request(RemainingBytesDecoder::new()),
+            options.clone(),
+        );
+        track_try_unwrap!(decoder.decode(b"GET /0123456789", Eos::new(false)));
+        assert!(decoder.decode(b"0123456789 HTTP/1.1\r\n", Eos::new(false)).is_err());
+        assert_eq!(decoder.exceeded_limit(), Some(HeadPart::StartLine));
+
+        let mut decoder = RequestDecoder::with_options(
+            BodyDecoder::for_request(RemainingBytesDecoder::new()),
+            options,
+        );
+        let input = b"GET / HTTP/1.1\r\nX-Foo: 0123456789abcdef\r\n\r\n";
+        assert!(decoder.decode(input, Eos::new(false)).is_err());
+        assert_eq!(decoder.exceeded_limit(), Some(HeadPart::Header));
+    }
 
     #[test]
     fn request_encoder_works() {
@@ -310,4 +826,410 @@ mod test {
         );
         assert_eq!(item.body(), "barbaz");
     }
+
+    #[test]
+    fn request_decoder_suspends_on_expect_continue() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        decoder.set_expect_continue(true);
+
+        let input = b"PUT /foo HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 6\r\n\r\nbarbaz";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, input.len() - 6);
+        assert!(decoder.is_suspended());
+        assert!(!decoder.is_idle());
+
+        let head = decoder.peek_head().unwrap();
+        assert_eq!(head.method().as_str(), "PUT");
+        assert_eq!(head.header().get_field("Content-Length"), Some("6"));
+
+        decoder.resume();
+        track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false)));
+        assert!(decoder.is_idle());
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.body(), "barbaz");
+    }
+
+    #[test]
+    fn request_decoder_ignores_expect_continue_of_http10() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        decoder.set_expect_continue(true);
+
+        let input = b"PUT /foo HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 6\r\n\r\nbarbaz";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, input.len());
+        assert!(!decoder.is_suspended());
+        assert!(decoder.is_idle());
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.body(), "barbaz");
+    }
+
+    #[test]
+    fn request_decoder_ignores_expect_continue_of_connect() {
+        let mut decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        decoder.set_expect_continue(true);
+
+        let head = "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\
+                    Expect: 100-continue\r\n\r\n";
+        let size = track_try_unwrap!(decoder.decode(head.as_bytes(), Eos::new(false)));
+        assert_eq!(size, head.len());
+        assert!(!decoder.is_suspended());
+        assert!(decoder.is_idle());
+        track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(decoder.protocol_switch(), Some(ProtocolSwitch::Tunnel));
+    }
+
+    #[test]
+    fn request_decoder_rejects_body_on_expect_continue() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        decoder.set_expect_continue(true);
+        assert!(decoder.reject_body().is_err());
+
+        let input = b"PUT /foo HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 6\r\n\r\n\
+                      GET /bar HTTP/1.1\r\n\r\n";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert!(decoder.is_suspended());
+
+        track_try_unwrap!(decoder.reject_body());
+        assert!(!decoder.is_suspended());
+        assert!(decoder.is_idle());
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.request_target().as_str(), "/foo");
+        assert_eq!(item.body(), "");
+
+        let item = track_try_unwrap!(decoder.decode_exact(&input[size..]));
+        assert_eq!(item.method().as_str(), "GET");
+        assert_eq!(item.request_target().as_str(), "/bar");
+    }
+
+    #[test]
+    fn request_encoder_holds_back_body_on_expect_continue() {
+        let mut request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/foo").unwrap(),
+            HttpVersion::V1_1,
+            b"barbaz",
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let mut encoder = RequestEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        encoder.set_expect_continue(true);
+        track_try_unwrap!(encoder.start_encoding(request));
+
+        let mut buf = [0; 1024];
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(
+            str::from_utf8(&buf[..size]).ok(),
+            Some("PUT /foo HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 6\r\n\r\n")
+        );
+        assert!(encoder.is_suspended());
+        assert!(!encoder.is_idle());
+        assert_eq!(encoder.requiring_bytes(), ByteCount::Finite(6));
+        assert_eq!(
+            track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false))),
+            0
+        );
+
+        encoder.resume();
+        let size = track_try_unwrap!(encoder.encode(&mut buf, Eos::new(false)));
+        assert_eq!(&buf[..size], b"barbaz");
+        assert!(encoder.is_idle());
+    }
+
+    #[test]
+    fn request_encoder_ignores_expect_continue_of_http10() {
+        let mut request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/foo").unwrap(),
+            HttpVersion::V1_0,
+            b"barbaz",
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let mut encoder = RequestEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        encoder.set_expect_continue(true);
+        track_try_unwrap!(encoder.start_encoding(request));
+
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert!(buf.ends_with(b"\r\n\r\nbarbaz"));
+        assert!(!encoder.is_suspended());
+        assert!(encoder.is_idle());
+    }
+
+    #[test]
+    fn request_encoder_encode_all_with_expect_continue_works() {
+        let mut request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/foo").unwrap(),
+            HttpVersion::V1_1,
+            b"barbaz",
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+
+        let mut encoder = RequestEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        encoder.set_expect_continue(true);
+        track_try_unwrap!(encoder.start_encoding(request));
+
+        // `encode_all` fails instead of returning while the body is held back
+        let mut buf = Vec::new();
+        assert!(encoder.encode_all(&mut buf).is_err());
+        assert!(buf.ends_with(b"\r\n\r\n"));
+        assert!(encoder.is_suspended());
+        assert!(!encoder.is_idle());
+
+        // The body must be sent before starting the next request
+        let next = Request::new(
+            Method::new("GET").unwrap(),
+            RequestTarget::new("/bar").unwrap(),
+            HttpVersion::V1_1,
+            b"qux123",
+        );
+        assert!(encoder.start_encoding(next).is_err());
+
+        encoder.resume();
+        assert_eq!(encoder.requiring_bytes(), ByteCount::Finite(6));
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert!(buf.ends_with(b"\r\n\r\nbarbaz"));
+    }
+
+    #[test]
+    fn request_decoder_detects_protocol_switch() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+
+        let head = "GET /chat HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\r\n";
+        let mut input = head.as_bytes().to_vec();
+        input.extend_from_slice(b"\x81\x05hello");
+        let size = track_try_unwrap!(decoder.decode(&input, Eos::new(false)));
+        assert_eq!(size, head.len());
+        assert!(decoder.is_idle());
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.body(), "");
+        assert_eq!(decoder.protocol_switch(), Some(ProtocolSwitch::Upgrade));
+        assert_eq!(
+            track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false))),
+            0
+        );
+
+        let mut decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let head = "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n";
+        let mut input = head.as_bytes().to_vec();
+        input.extend_from_slice(b"\x16\x03\x01");
+        let size = track_try_unwrap!(decoder.decode(&input, Eos::new(false)));
+        assert_eq!(size, head.len());
+        track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(decoder.protocol_switch(), Some(ProtocolSwitch::Tunnel));
+
+        decoder.clear_protocol_switch();
+        let item = track_try_unwrap!(
+            decoder.decode_exact(b"GET /foo HTTP/1.1\r\nContent-Length: 0\r\n\r\n".as_ref())
+        );
+        assert_eq!(item.request_target().as_str(), "/foo");
+        assert_eq!(decoder.protocol_switch(), None);
+    }
+
+    #[test]
+    fn request_decoder_detects_http2_preface() {
+        let mut decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let mut input = PREFACE.to_vec();
+        input.extend_from_slice(b"\x00\x00\x00\x04\x00\x00\x00\x00\x00");
+
+        let size = track_try_unwrap!(decoder.decode(&input[..10], Eos::new(false)));
+        assert_eq!(size, 10);
+        assert_eq!(decoder.protocol_switch(), None);
+
+        let size = track_try_unwrap!(decoder.decode(&input[10..], Eos::new(false)));
+        assert_eq!(size, PREFACE.len() - 10);
+        assert_eq!(
+            decoder.protocol_switch(),
+            Some(ProtocolSwitch::Http2Preface)
+        );
+        assert!(!decoder.is_idle());
+        assert_eq!(
+            track_try_unwrap!(decoder.decode(&input[PREFACE.len()..], Eos::new(false))),
+            0
+        );
+
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let item = track_try_unwrap!(decoder
+            .decode_exact(b"PRIVATE /foo HTTP/1.1\r\nContent-Length: 6\r\n\r\nbarbaz".as_ref()));
+        assert_eq!(item.method().as_str(), "PRIVATE");
+        assert_eq!(item.body(), "barbaz");
+        assert_eq!(decoder.protocol_switch(), None);
+
+        let mut decoder = RequestDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        assert_eq!(
+            decoder
+                .decode_exact(b"PRI * HTTP/2.0\r\n\r\nXX\r\n\r\n".as_ref())
+                .err()
+                .map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn request_decoder_decode_bytes_works() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let head = "POST /foo HTTP/1.1\r\nContent-Length: 6\r\nX-Foo: bar\r\n\r\n";
+
+        let mut src = BytesMut::from(&head.as_bytes()[..10]);
+        track_try_unwrap!(decoder.decode_bytes(&mut src, Eos::new(false)));
+        assert_eq!(src.len(), 10);
+
+        src.extend_from_slice(&head.as_bytes()[10..]);
+        src.extend_from_slice(b"barbazGET");
+        let storage = src.as_ptr();
+        track_try_unwrap!(decoder.decode_bytes(&mut src, Eos::new(false)));
+        assert!(decoder.is_idle());
+        assert_eq!(&src[..], b"GET");
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.method().as_str(), "POST");
+        assert_eq!(item.body(), "barbaz");
+        assert_eq!(item.head_bytes(), head.as_bytes());
+        assert_eq!(item.head_bytes().as_ptr(), storage);
+        assert_eq!(
+            item.header_field_bytes("x-foo"),
+            Some(Bytes::from_static(b"bar"))
+        );
+        assert_eq!(item.header_field_bytes("x-bar"), None);
+    }
+
+    #[test]
+    fn request_builder_works() {
+        let request = track_try_unwrap!(Request::builder()
+            .method("PUT")
+            .request_target("/foo")
+            .http_version(HttpVersion::V1_0)
+            .header("Content-Length", "3")
+            .header("X-Foo", "bar")
+            .build("baz"));
+        assert_eq!(
+            request.to_string(),
+            "PUT /foo HTTP/1.0\r\nContent-Length: 3\r\nX-Foo: bar\r\n\r\nbaz"
+        );
+
+        let request = track_try_unwrap!(Request::builder().build(()));
+        assert_eq!(request.method().as_str(), "GET");
+        assert_eq!(request.request_target().as_str(), "/");
+        assert_eq!(request.http_version(), HttpVersion::V1_1);
+
+        let e = Request::builder()
+            .header("X-Foo", "ok")
+            .header("X Bar", "ng")
+            .method("G E T")
+            .build(())
+            .err()
+            .unwrap();
+        assert_eq!(*e.kind(), ErrorKind::InvalidInput);
+        assert!(e.to_string().contains("header=\"X Bar\""));
+        assert!(!e.to_string().contains("method="));
+    }
+
+    #[test]
+    fn request_with_owned_components_works() {
+        let method = track_try_unwrap!(OwnedMethod::new("POST"));
+        let target = track_try_unwrap!(OwnedRequestTarget::new("/foo"));
+        let field = track_try_unwrap!(OwnedHeaderField::new("X-Foo", "bar"));
+        let request = ::std::thread::spawn(move || {
+            let mut request = Request::new(&method, &target, HttpVersion::V1_1, "");
+            request.header_mut().add_field(&field);
+            request.to_string()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(request, "POST /foo HTTP/1.1\r\nX-Foo: bar\r\n\r\n");
+    }
+
+    #[test]
+    fn request_equality_works() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let decoded = track_try_unwrap!(decoder.decode_exact(
+            b"PUT /foo HTTP/1.1\r\ncontent-length: 3\r\nX-Foo: bar\r\n\r\nbaz".as_ref()
+        ));
+        let built = track_try_unwrap!(Request::builder()
+            .method("PUT")
+            .request_target("/foo")
+            .header("Content-Length", "3")
+            .header("x-foo", "bar")
+            .build("baz".to_owned()));
+        assert_eq!(decoded, built);
+        assert_eq!(decoded.clone(), decoded);
+        assert_eq!(hash(&decoded), hash(&built));
+
+        let other = track_try_unwrap!(Request::builder()
+            .method("PUT")
+            .request_target("/foo")
+            .header("Content-Length", "3")
+            .header("x-foo", "BAR")
+            .build("baz".to_owned()));
+        assert_ne!(decoded, other);
+
+        let mut reordered = track_try_unwrap!(Request::builder()
+            .method("PUT")
+            .request_target("/foo")
+            .build("baz".to_owned()));
+        reordered
+            .header_mut()
+            .add_field(HeaderField::new("X-Foo", "bar").unwrap())
+            .add_field(HeaderField::new("Content-Length", "3").unwrap());
+        assert_ne!(decoded, reordered);
+    }
+
+    #[test]
+    fn request_start_line_setters_work() {
+        let mut decoder =
+            RequestDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let mut item = track_try_unwrap!(
+            decoder.decode_exact(b"GET /foo HTTP/1.0\r\nHost: example.com\r\n\r\n".as_ref())
+        );
+
+        item.set_method(Method::new("DELETE").unwrap());
+        item.set_request_target(&OwnedRequestTarget::new("/foo/bar?baz").unwrap());
+        item.set_http_version(HttpVersion::V1_1);
+        item.header_mut()
+            .add_field(HeaderField::new("X-Foo", "qux").unwrap());
+        assert_eq!(item.method().as_str(), "DELETE");
+        assert_eq!(item.request_target().as_str(), "/foo/bar?baz");
+        assert_eq!(item.http_version(), HttpVersion::V1_1);
+        assert_eq!(item.header().get_field("Host"), Some("example.com"));
+        assert_eq!(
+            item.to_string(),
+            "DELETE /foo/bar?baz HTTP/1.1\r\nHost: example.com\r\nX-Foo: qux\r\n\r\n"
+        );
+
+        item.set_request_target(RequestTarget::new("*").unwrap());
+        assert_eq!(item.header().get_field("Host"), Some("example.com"));
+        let mut buf = Vec::new();
+        track_try_unwrap!(RequestEncoder::<BodyEncoder<BytesEncoder<_>>>::with_item(
+            item.map_body(String::into_bytes)
+        )
+        .and_then(|mut e| e.encode_all(&mut buf)));
+        assert_eq!(
+            buf,
+            &b"DELETE * HTTP/1.1\r\nHost: example.com\r\nX-Foo: qux\r\nContent-Length: 0\r\n\r\n"[..]
+        );
+    }
+
+    fn hash<T: Hash>(x: &T) -> u64 {
+        let mut hasher = ::std::collections::hash_map::DefaultHasher::new();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
 }
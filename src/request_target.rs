@@ -1,5 +1,6 @@
-use bytecodec::{ByteCount, Decode, Eos, ErrorKind, Result};
+use bytecodec::{ByteCount, Decode, Eos, Error, ErrorKind, Result};
 use std::fmt;
+use std::str::FromStr;
 
 use util;
 
@@ -42,6 +43,64 @@ impl<'a> fmt::Display for RequestTarget<'a> {
     }
 }
 
+/// An owned variant of `RequestTarget`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OwnedRequestTarget(String);
+impl OwnedRequestTarget {
+    /// Makes a new `OwnedRequestTarget` instance.
+    ///
+    /// # Errors
+    ///
+    /// `target` must satisfy the same conditions as `RequestTarget::new`.
+    pub fn new<T: Into<String>>(target: T) -> Result<Self> {
+        let target = target.into();
+        track!(RequestTarget::new(&target))?;
+        Ok(OwnedRequestTarget(target))
+    }
+
+    /// Returns the borrowed variant of the target.
+    pub fn as_request_target(&self) -> RequestTarget<'_> {
+        RequestTarget(&self.0)
+    }
+
+    /// Returns a reference to the inner string of the target.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Takes ownership of the instance, and returns the inner string.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+impl AsRef<str> for OwnedRequestTarget {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for OwnedRequestTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl FromStr for OwnedRequestTarget {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        track!(Self::new(s))
+    }
+}
+impl<'a> From<RequestTarget<'a>> for OwnedRequestTarget {
+    fn from(f: RequestTarget<'a>) -> Self {
+        OwnedRequestTarget(f.0.to_owned())
+    }
+}
+impl<'a> From<&'a OwnedRequestTarget> for RequestTarget<'a> {
+    fn from(f: &'a OwnedRequestTarget) -> Self {
+        f.as_request_target()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct RequestTargetDecoder {
     size: usize,
@@ -1,28 +1,47 @@
 use bytecodec::tuple::TupleDecoder;
-use bytecodec::{ByteCount, Decode, Encode, Eos, Result, SizedEncode};
+use bytecodec::{ByteCount, Decode, Encode, Eos, Error, Result, SizedEncode};
+#[cfg(feature = "bytes")]
+use bytes::{Bytes, BytesMut};
+use std::collections::VecDeque;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::IoSlice;
 use std::str;
+use std::time::SystemTime;
 
 use header::HeaderFieldPosition;
-use message::{Message, MessageDecoder, MessageEncoder};
+#[cfg(feature = "bytes")]
+use message::{self, RetainHead};
+use message::{HeadBuf, Message, MessageDecode, MessageDecoder, MessageEncode, MessageEncoder};
 use status::{ReasonPhraseDecoder, StatusCodeDecoder};
+use upgrade::ProtocolSwitch;
 use util::SpaceDecoder;
 use version::HttpVersionDecoder;
 use {
-    BodyDecode, BodyEncode, DecodeOptions, Header, HeaderMut, HttpVersion, ReasonPhrase, StatusCode,
+    BodyDecode, BodyEncode, DecodeOptions, EncodeVectored, Header, HeaderField, HeaderMut,
+    HttpDate, HttpVersion, ReasonPhrase, StatusCode,
 };
 
 /// HTTP response message.
-#[derive(Debug)]
+///
+/// Two messages are equal if they have the same start line, header fields and body.
+/// Header field names are compared case-insensitively.
+#[derive(Debug, Clone)]
 pub struct Response<T> {
-    buf: Vec<u8>,
+    buf: HeadBuf,
     status_line: StatusLine,
     header: Vec<HeaderFieldPosition>,
     body: T,
 }
 impl<T> Response<T> {
     /// Makes a new `Response` instance with the given status-line components and body.
-    pub fn new(version: HttpVersion, status: StatusCode, reason: ReasonPhrase, body: T) -> Self {
+    ///
+    /// `reason` can also be given as `&OwnedReasonPhrase`.
+    pub fn new<'a, R>(version: HttpVersion, status: StatusCode, reason: R, body: T) -> Self
+    where
+        R: Into<ReasonPhrase<'a>>,
+    {
+        let reason = reason.into();
         let mut buf = Vec::new();
         buf.extend_from_slice(version.as_str().as_bytes());
         buf.push(b' ');
@@ -38,7 +57,7 @@ impl<T> Response<T> {
         };
 
         Response {
-            buf,
+            buf: HeadBuf::from(buf),
             status_line,
             header: Vec::new(),
             body,
@@ -62,6 +81,31 @@ impl<T> Response<T> {
         unsafe { ReasonPhrase::new_unchecked(str::from_utf8_unchecked(&self.buf[start..end])) }
     }
 
+    /// Sets the HTTP version of the response.
+    pub fn set_http_version(&mut self, version: HttpVersion) {
+        self.buf
+            .replace(0..8, version.as_str().as_bytes(), &mut self.header);
+        self.status_line.http_version = version;
+    }
+
+    /// Sets the status code of the response.
+    pub fn set_status_code(&mut self, status: StatusCode) {
+        let start = 8 /* version */ + 1;
+        self.buf
+            .replace(start..start + 3, &status.as_bytes()[..], &mut self.header);
+        self.status_line.status_code = status;
+    }
+
+    /// Sets the reason phrase of the response.
+    pub fn set_reason_phrase<'a, R: Into<ReasonPhrase<'a>>>(&mut self, reason: R) {
+        let reason = reason.into();
+        let start = 8 /* version */ + 1 + 3 /* status */ + 1;
+        let range = start..start + self.status_line.reason_phrase_size;
+        self.buf
+            .replace(range, reason.as_str().as_bytes(), &mut self.header);
+        self.status_line.reason_phrase_size = reason.as_str().len();
+    }
+
     /// Returns the header of the response.
     pub fn header(&self) -> Header {
         Header::new(&self.buf, &self.header)
@@ -69,7 +113,25 @@ impl<T> Response<T> {
 
     /// Returns the mutable header of the response.
     pub fn header_mut(&mut self) -> HeaderMut {
-        HeaderMut::new(&mut self.buf, &mut self.header)
+        HeaderMut::new(self.buf.appendable_mut(), &mut self.header)
+    }
+
+    /// Returns the head part (i.e., the status-line and the header) of the response as `Bytes`.
+    ///
+    /// If the response has been decoded by `ResponseDecoder::decode_bytes`,
+    /// this returns the received bytes verbatim without copying them.
+    #[cfg(feature = "bytes")]
+    pub fn head_bytes(&self) -> Bytes {
+        self.buf.to_bytes()
+    }
+
+    /// Returns the value of the first field that has the name `name` in the header as `Bytes`.
+    ///
+    /// If the response has been decoded by `ResponseDecoder::decode_bytes`,
+    /// the returned value shares the storage of the response.
+    #[cfg(feature = "bytes")]
+    pub fn header_field_bytes(&self, name: &str) -> Option<Bytes> {
+        self.buf.field_bytes(&self.header, name)
     }
 
     /// Returns a reference to the body of the response.
@@ -112,6 +174,31 @@ impl<T> Response<T> {
         (res, self.body)
     }
 }
+impl Response<()> {
+    /// Returns a builder for making a response.
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder::new()
+    }
+}
+impl<T: PartialEq> PartialEq for Response<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.http_version() == other.http_version()
+            && self.status_code() == other.status_code()
+            && self.reason_phrase() == other.reason_phrase()
+            && self.header() == other.header()
+            && self.body == other.body
+    }
+}
+impl<T: Eq> Eq for Response<T> {}
+impl<T: Hash> Hash for Response<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.http_version().hash(state);
+        self.status_code().hash(state);
+        self.reason_phrase().hash(state);
+        self.header().hash(state);
+        self.body.hash(state);
+    }
+}
 impl<T: fmt::Display> fmt::Display for Response<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(
@@ -127,7 +214,124 @@ impl<T: fmt::Display> fmt::Display for Response<T> {
     }
 }
 
+/// A builder of `Response`.
+///
+/// Each component is validated when it is given, and
+/// the first validation error (if any) is reported by `build` method.
+///
+/// The default status-line is `HTTP/1.1 200 OK`.
+/// Note that the reason phrase becomes empty if a status code other than `200` is given
+/// without specifying a reason phrase.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate httpcodec;
+/// use httpcodec::Response;
+///
+/// # fn main() {
+/// let response = Response::builder()
+///     .status_code(404)
+///     .reason_phrase("Not Found")
+///     .header("Content-Length", "0")
+///     .build(())
+///     .unwrap();
+/// assert_eq!(response.status_code().as_u16(), 404);
+/// assert_eq!(response.header().get_field("Content-Length"), Some("0"));
+///
+/// let result = Response::builder().status_code(1000).build(());
+/// assert!(result.is_err());
+/// # }
+/// ```
 #[derive(Debug)]
+pub struct ResponseBuilder {
+    version: HttpVersion,
+    status: StatusCode,
+    reason: Option<String>,
+    fields: Vec<(String, String)>,
+    error: Option<Error>,
+}
+impl ResponseBuilder {
+    /// Makes a new `ResponseBuilder` instance.
+    pub fn new() -> Self {
+        ResponseBuilder {
+            version: HttpVersion::V1_1,
+            status: unsafe { StatusCode::new_unchecked(200) },
+            reason: None,
+            fields: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Sets the HTTP version of the response.
+    pub fn http_version(mut self, version: HttpVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the status code of the response.
+    pub fn status_code(mut self, code: u16) -> Self {
+        match track!(StatusCode::new(code), "status_code={}", code) {
+            Ok(status) => self.status = status,
+            Err(e) => self.check(Err(e)),
+        }
+        self
+    }
+
+    /// Sets the reason phrase of the response.
+    pub fn reason_phrase(mut self, phrase: &str) -> Self {
+        let result = track!(ReasonPhrase::new(phrase), "reason_phrase={:?}", phrase);
+        self.check(result.map(|_| ()));
+        self.reason = Some(phrase.to_owned());
+        self
+    }
+
+    /// Adds a header field to the response.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let result = track!(HeaderField::new(name, value), "header={:?}", name).map(|_| ());
+        self.check(result);
+        self.fields.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Builds a response that has the given body.
+    ///
+    /// # Errors
+    ///
+    /// If any of the given components is invalid,
+    /// the error of the first one will be returned.
+    pub fn build<T>(self, body: T) -> Result<Response<T>> {
+        if let Some(e) = self.error {
+            return Err(track!(e));
+        }
+        let reason = match self.reason {
+            Some(ref reason) => reason.as_str(),
+            None if self.status.as_u16() == 200 => "OK",
+            None => "",
+        };
+        let reason = track!(ReasonPhrase::new(reason))?;
+        let mut response = Response::new(self.version, self.status, reason, body);
+        for (name, value) in &self.fields {
+            response
+                .header_mut()
+                .add_field(track!(HeaderField::new(name, value))?);
+        }
+        Ok(response)
+    }
+
+    fn check(&mut self, result: Result<()>) {
+        if let (None, Err(e)) = (&self.error, result) {
+            self.error = Some(e);
+        }
+    }
+}
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
 struct StatusLine {
     http_version: HttpVersion,
     status_code: StatusCode,
@@ -168,9 +372,26 @@ impl Decode for StatusLineDecoder {
     }
 }
 
+/// The maximum number of interim responses kept by `ResponseDecoder`.
+const MAX_INTERIM_RESPONSES: usize = 16;
+
 /// HTTP response decoder.
+///
+/// Interim responses (i.e., responses that have a 1xx status code except for `101`) are
+/// never returned as decoded items.
+/// They are skipped while decoding and, if `set_keep_interim_responses(true)` has been called,
+/// can be retrieved via `pop_interim_response` method.
+///
+/// Responses that have `204 No Content` or `304 Not Modified` status code are regarded as having no body.
 #[derive(Debug)]
-pub struct ResponseDecoder<D>(MessageDecoder<StatusLineDecoder, D>);
+pub struct ResponseDecoder<D> {
+    inner: MessageDecoder<StatusLineDecoder, D>,
+    interim_responses: VecDeque<Response<()>>,
+    keep_interim_responses: bool,
+    connect_request: bool,
+    head_request: bool,
+    protocol_switch: Option<ProtocolSwitch>,
+}
 impl<D: BodyDecode> ResponseDecoder<D> {
     /// Make a new `ResponseDecoder` instance.
     pub fn new(body_decoder: D) -> Self {
@@ -180,18 +401,159 @@ impl<D: BodyDecode> ResponseDecoder<D> {
     /// Make a new `ResponseDecoder` instance with the given options.
     pub fn with_options(body_decoder: D, options: DecodeOptions) -> Self {
         let inner = MessageDecoder::new(StatusLineDecoder::default(), body_decoder, options);
-        ResponseDecoder(inner)
+        ResponseDecoder {
+            inner,
+            interim_responses: VecDeque::new(),
+            keep_interim_responses: false,
+            connect_request: false,
+            head_request: false,
+            protocol_switch: None,
+        }
+    }
+
+    /// Specifies whether the decoder keeps interim responses decoded before a final response.
+    ///
+    /// At most 16 interim responses are kept.
+    /// If more are decoded before they are popped, the oldest ones are discarded.
+    ///
+    /// The default value is `false` (i.e., interim responses are silently discarded).
+    pub fn set_keep_interim_responses(&mut self, enabled: bool) {
+        self.keep_interim_responses = enabled;
+    }
+
+    /// Pops the oldest interim response kept by the decoder.
+    pub fn pop_interim_response(&mut self) -> Option<Response<()>> {
+        self.interim_responses.pop_front()
+    }
+
+    /// Specifies whether the response to be decoded is a reply to a `CONNECT` request.
+    ///
+    /// If it is `true`, a `2xx` response is regarded as the establishment of a tunnel.
+    ///
+    /// This applies only to the next response.
+    /// The value is reset to `false` when the response is taken by `finish_decoding`.
+    ///
+    /// The default value is `false`.
+    pub fn set_connect_request(&mut self, is_connect: bool) {
+        self.connect_request = is_connect;
+    }
+
+    /// Specifies whether the response to be decoded is a reply to a `HEAD` request.
+    ///
+    /// If it is `true`, the response is regarded as having no body
+    /// regardless of its `Content-Length` and `Transfer-Encoding` header fields.
+    ///
+    /// This applies only to the next response.
+    /// The value is reset to `false` when the response is taken by `finish_decoding`.
+    ///
+    /// The default value is `false`.
+    pub fn set_head_request(&mut self, is_head: bool) {
+        self.head_request = is_head;
+    }
+
+    /// Returns the protocol switch caused by the last decoded response.
+    ///
+    /// A `101 Switching Protocols` response results in `ProtocolSwitch::Upgrade`, and
+    /// a `2xx` reply to a `CONNECT` request results in `ProtocolSwitch::Tunnel`.
+    /// Such responses are regarded as having no body.
+    ///
+    /// Once a switch is detected, the decoder consumes no more bytes after the response,
+    /// so the bytes following it remain in the caller's buffer and
+    /// can be handed to the next protocol layer.
+    /// Otherwise, `clear_protocol_switch` can be used to continue decoding HTTP responses.
+    pub fn protocol_switch(&self) -> Option<ProtocolSwitch> {
+        self.protocol_switch
+    }
+
+    /// Clears the detected protocol switch, and resumes decoding subsequent responses.
+    pub fn clear_protocol_switch(&mut self) {
+        self.protocol_switch = None;
+    }
+
+    /// Returns `true` if the head part of a final (i.e., non-interim) response has been decoded,
+    /// otherwise `false`.
+    ///
+    /// This becomes `false` again when the response is taken by `finish_decoding`.
+    pub fn is_head_decoded(&self) -> bool {
+        self.inner.is_head_decoded()
+    }
+
+    /// Returns a reference to the body decoder.
+    pub fn body_decoder_ref(&self) -> &D {
+        self.inner.body_ref()
+    }
+
+    /// Returns a mutable reference to the body decoder.
+    pub fn body_decoder_mut(&mut self) -> &mut D {
+        self.inner.body_mut()
+    }
+
+    /// Decodes bytes in `src` without copying the head part of the response.
+    ///
+    /// The consumed bytes are removed from `src`.
+    /// The head part of a decoded response shares the storage of `src`
+    /// (see `Response::head_bytes` and `Response::header_field_bytes`).
+    ///
+    /// Note that this and `decode` method should not be used together for decoding a response.
+    #[cfg(feature = "bytes")]
+    pub fn decode_bytes(&mut self, src: &mut BytesMut, eos: Eos) -> Result<()> {
+        track!(message::decode_bytes(self, src, eos))
+    }
+
+    fn handle_head(&mut self) -> Result<()> {
+        let status = match self.inner.head() {
+            None => return Ok(()),
+            Some((status_line, _)) => status_line.status_code.as_u16(),
+        };
+        if status == 101 {
+            self.protocol_switch = Some(ProtocolSwitch::Upgrade);
+            track!(self.inner.skip_body())?;
+        } else if self.connect_request && status / 100 == 2 {
+            self.protocol_switch = Some(ProtocolSwitch::Tunnel);
+            track!(self.inner.skip_body())?;
+        } else if is_interim(status) {
+            let m = track!(self.inner.finish_head())?;
+            if self.keep_interim_responses {
+                if self.interim_responses.len() == MAX_INTERIM_RESPONSES {
+                    self.interim_responses.pop_front();
+                }
+                self.interim_responses.push_back(Response {
+                    buf: m.buf,
+                    status_line: m.start_line,
+                    header: m.header,
+                    body: (),
+                });
+            }
+        } else if self.head_request || status == 204 || status == 304 {
+            track!(self.inner.skip_body())?;
+        }
+        Ok(())
     }
 }
 impl<D: BodyDecode> Decode for ResponseDecoder<D> {
     type Item = Response<D::Item>;
 
     fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
-        track!(self.0.decode(buf, eos))
+        if self.protocol_switch.is_some() && !self.inner.is_head_decoded() {
+            return Ok(0);
+        }
+
+        let mut offset = 0;
+        while !self.inner.is_head_decoded() {
+            offset += track!(self.inner.decode_head(&buf[offset..], eos))?;
+            if !self.inner.is_head_decoded() {
+                return Ok(offset);
+            }
+            track!(self.handle_head())?;
+        }
+        offset += track!(self.inner.decode(&buf[offset..], eos))?;
+        Ok(offset)
     }
 
     fn finish_decoding(&mut self) -> Result<Self::Item> {
-        let m = track!(self.0.finish_decoding())?;
+        let m = track!(self.inner.finish_decoding())?;
+        self.connect_request = false;
+        self.head_request = false;
         Ok(Response {
             buf: m.buf,
             status_line: m.start_line,
@@ -201,11 +563,43 @@ impl<D: BodyDecode> Decode for ResponseDecoder<D> {
     }
 
     fn requiring_bytes(&self) -> ByteCount {
-        self.0.requiring_bytes()
+        self.inner.requiring_bytes()
     }
 
     fn is_idle(&self) -> bool {
-        self.0.is_idle()
+        self.inner.is_idle()
+    }
+}
+impl<D: BodyDecode> MessageDecode for ResponseDecoder<D> {
+    fn is_suspended(&self) -> bool {
+        false
+    }
+
+    fn switched_protocol(&self) -> Option<ProtocolSwitch> {
+        if self.inner.is_head_decoded() {
+            None
+        } else {
+            self.protocol_switch
+        }
+    }
+}
+#[cfg(feature = "bytes")]
+impl<D: BodyDecode> RetainHead for ResponseDecoder<D> {
+    fn set_retain_head(&mut self, retain: bool) {
+        self.inner.set_retain_head(retain);
+    }
+
+    fn retained_size(&self) -> usize {
+        self.inner.unattached_head_size()
+    }
+
+    fn pending_head_size(&self) -> Option<usize> {
+        self.inner.pending_head_size()
+    }
+
+    fn attach_head(&mut self, head: Bytes) -> Result<()> {
+        track!(self.inner.attach_head(head))?;
+        track!(self.handle_head())
     }
 }
 impl<D: Default + BodyDecode> Default for ResponseDecoder<D> {
@@ -214,43 +608,104 @@ impl<D: Default + BodyDecode> Default for ResponseDecoder<D> {
     }
 }
 
+fn is_interim(status: u16) -> bool {
+    status / 100 == 1 && status != 101
+}
+
 /// HTTP response encoder.
 #[derive(Debug, Default)]
-pub struct ResponseEncoder<E>(MessageEncoder<E>);
+pub struct ResponseEncoder<E> {
+    inner: MessageEncoder<E>,
+    clock: Option<Clock>,
+}
 impl<E: BodyEncode> ResponseEncoder<E> {
     /// Makes a new `ResponseEncoder` instance.
     pub fn new(body_encoder: E) -> Self {
-        ResponseEncoder(MessageEncoder::new(body_encoder))
+        ResponseEncoder {
+            inner: MessageEncoder::new(body_encoder),
+            clock: None,
+        }
+    }
+
+    /// Sets the clock used to add the `Date` header field to responses.
+    ///
+    /// Once this is set, the encoder adds `Date` with the current time given by `clock`
+    /// to each response that does not have the field.
+    ///
+    /// See [RFC 7231 section 7.1.1.2](https://tools.ietf.org/html/rfc7231#section-7.1.1.2).
+    pub fn set_date_clock<F>(&mut self, clock: F)
+    where
+        F: Fn() -> SystemTime + Send + Sync + 'static,
+    {
+        self.clock = Some(Clock(Box::new(clock)));
+    }
+
+    /// Returns a reference to the body encoder.
+    pub fn body_encoder_ref(&self) -> &E {
+        self.inner.body_ref()
+    }
+
+    /// Returns a mutable reference to the body encoder.
+    pub fn body_encoder_mut(&mut self) -> &mut E {
+        self.inner.body_mut()
     }
 }
 impl<E: BodyEncode> Encode for ResponseEncoder<E> {
     type Item = Response<E::Item>;
 
     fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
-        track!(self.0.encode(buf, eos))
+        track!(self.inner.encode(buf, eos))
     }
 
-    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+    fn start_encoding(&mut self, mut item: Self::Item) -> Result<()> {
+        if let Some(ref clock) = self.clock {
+            if item.header().get_field("Date").is_none() {
+                let date = track!(HttpDate::from_system_time((clock.0)()))?.to_string();
+                item.header_mut()
+                    .add_field(track!(HeaderField::new("Date", &date))?);
+            }
+        }
         let item = Message {
             buf: item.buf,
             start_line: (),
             header: item.header,
             body: item.body,
         };
-        track!(self.0.start_encoding(item))
+        track!(self.inner.start_encoding(item))
     }
 
     fn is_idle(&self) -> bool {
-        self.0.is_idle()
+        self.inner.is_idle()
     }
 
     fn requiring_bytes(&self) -> ByteCount {
-        self.0.requiring_bytes()
+        self.inner.requiring_bytes()
+    }
+}
+impl<E: BodyEncode> MessageEncode for ResponseEncoder<E> {
+    fn is_suspended(&self) -> bool {
+        false
     }
 }
 impl<E: SizedEncode + BodyEncode> SizedEncode for ResponseEncoder<E> {
     fn exact_requiring_bytes(&self) -> u64 {
-        self.0.exact_requiring_bytes()
+        self.inner.exact_requiring_bytes()
+    }
+}
+impl<E: BodyEncode + EncodeVectored> EncodeVectored for ResponseEncoder<E> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        track!(self.inner.pending_slices(slices))
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        track!(self.inner.consume(size))
+    }
+}
+
+struct Clock(Box<dyn Fn() -> SystemTime + Send + Sync>);
+impl fmt::Debug for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Clock(_)")
     }
 }
 
@@ -258,10 +713,10 @@ impl<E: SizedEncode + BodyEncode> SizedEncode for ResponseEncoder<E> {
 mod test {
     use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder, Utf8Decoder};
     use bytecodec::io::{IoDecodeExt, IoEncodeExt};
-    use bytecodec::EncodeExt;
+    use bytecodec::{EncodeExt, ErrorKind};
 
     use super::*;
-    use {BodyDecoder, BodyEncoder, HttpVersion, ReasonPhrase, StatusCode};
+    use {BodyDecoder, BodyEncoder, HttpVersion, ProtocolSwitch, ReasonPhrase, StatusCode};
 
     #[test]
     fn response_encoder_works() {
@@ -282,6 +737,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn response_encoder_date_works() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let mut encoder = ResponseEncoder::new(BodyEncoder::new(BytesEncoder::new()));
+        encoder.set_date_clock(|| UNIX_EPOCH + Duration::from_secs(784_111_777));
+
+        let response = track_try_unwrap!(Response::builder().build(b"foo"));
+        track_try_unwrap!(encoder.start_encoding(response));
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert_eq!(
+            buf,
+            b"HTTP/1.1 200 OK\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\nContent-Length: 3\r\n\r\nfoo"
+                .as_ref()
+        );
+
+        let response = track_try_unwrap!(Response::builder()
+            .header("Date", "Mon, 07 Nov 1994 08:49:37 GMT")
+            .build(b"foo"));
+        track_try_unwrap!(encoder.start_encoding(response));
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert_eq!(
+            buf,
+            b"HTTP/1.1 200 OK\r\nDate: Mon, 07 Nov 1994 08:49:37 GMT\r\nContent-Length: 3\r\n\r\nfoo"
+                .as_ref()
+        );
+    }
+
     #[test]
     fn response_decoder_works() {
         let mut decoder =
@@ -305,4 +790,235 @@ mod test {
         );
         assert_eq!(item.body(), "barbaz");
     }
+
+    #[test]
+    fn response_decoder_skips_interim_responses() {
+        let input = concat!(
+            "HTTP/1.1 100 Continue\r\n\r\n",
+            "HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nbarbaz"
+        );
+
+        let mut decoder =
+            ResponseDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let item = track_try_unwrap!(decoder.decode_exact(input.as_bytes()));
+        assert_eq!(item.status_code().as_u16(), 200);
+        assert_eq!(item.body(), "barbaz");
+        assert!(decoder.pop_interim_response().is_none());
+
+        let mut decoder =
+            ResponseDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        decoder.set_keep_interim_responses(true);
+        let item = track_try_unwrap!(decoder.decode_exact(input.as_bytes()));
+        assert_eq!(item.status_code().as_u16(), 200);
+        assert_eq!(item.body(), "barbaz");
+
+        let interim = decoder.pop_interim_response().unwrap();
+        assert_eq!(interim.status_code().as_u16(), 100);
+        assert_eq!(interim.header().fields().count(), 0);
+
+        let interim = decoder.pop_interim_response().unwrap();
+        assert_eq!(interim.status_code().as_u16(), 103);
+        assert_eq!(
+            interim.header().get_field("Link"),
+            Some("</style.css>; rel=preload")
+        );
+        assert!(decoder.pop_interim_response().is_none());
+    }
+
+    #[test]
+    fn response_decoder_limits_interim_responses() {
+        let mut input = String::new();
+        for i in 0..20 {
+            input += &format!("HTTP/1.1 103 Early Hints\r\nLink: </{}.css>\r\n\r\n", i);
+        }
+        input += "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+
+        let mut decoder =
+            ResponseDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        decoder.set_keep_interim_responses(true);
+        let item = track_try_unwrap!(decoder.decode_exact(input.as_bytes()));
+        assert_eq!(item.status_code().as_u16(), 200);
+
+        // Only the latest 16 interim responses are kept
+        for i in 4..20 {
+            let interim = decoder.pop_interim_response().unwrap();
+            assert_eq!(
+                interim.header().get_field("Link"),
+                Some(format!("</{}.css>", i).as_str())
+            );
+        }
+        assert!(decoder.pop_interim_response().is_none());
+    }
+
+    #[test]
+    fn response_decoder_detects_protocol_switch() {
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let head = "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+        let mut input = head.as_bytes().to_vec();
+        input.extend_from_slice(b"\x81\x05hello");
+        let size = track_try_unwrap!(decoder.decode(&input, Eos::new(false)));
+        assert_eq!(size, head.len());
+        assert!(decoder.is_idle());
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.status_code().as_u16(), 101);
+        assert!(item.body().is_empty());
+        assert_eq!(decoder.protocol_switch(), Some(ProtocolSwitch::Upgrade));
+        assert_eq!(
+            track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false))),
+            0
+        );
+
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        decoder.set_connect_request(true);
+        let head = "HTTP/1.1 200 Connection Established\r\n\r\n";
+        let mut input = head.as_bytes().to_vec();
+        input.extend_from_slice(b"\x16\x03\x01");
+        let size = track_try_unwrap!(decoder.decode(&input, Eos::new(false)));
+        assert_eq!(size, head.len());
+        track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(decoder.protocol_switch(), Some(ProtocolSwitch::Tunnel));
+
+        // `set_connect_request` applies only to a single response
+        decoder.clear_protocol_switch();
+        let item = track_try_unwrap!(
+            decoder.decode_exact(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo".as_ref())
+        );
+        assert_eq!(item.body(), b"foo");
+        assert_eq!(decoder.protocol_switch(), None);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn response_decoder_decode_bytes_works() {
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        decoder.set_keep_interim_responses(true);
+        let interim = "HTTP/1.1 100 Continue\r\n\r\n";
+        let head = "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n";
+
+        let mut src = BytesMut::from(interim);
+        src.extend_from_slice(head.as_bytes());
+        src.extend_from_slice(b"foo");
+        track_try_unwrap!(decoder.decode_bytes(&mut src, Eos::new(false)));
+        assert!(decoder.is_idle());
+        assert!(src.is_empty());
+
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.status_code().as_u16(), 200);
+        assert_eq!(item.body(), b"foo");
+        assert_eq!(item.head_bytes(), head.as_bytes());
+        assert_eq!(
+            item.header_field_bytes("Content-Length"),
+            Some(Bytes::from_static(b"3"))
+        );
+
+        let interim = decoder.pop_interim_response().unwrap();
+        assert_eq!(interim.status_code().as_u16(), 100);
+    }
+
+    #[test]
+    fn response_decoder_bodiless_responses_work() {
+        let mut decoder = ResponseDecoder::<BodyDecoder<RemainingBytesDecoder>>::default();
+        let input = b"HTTP/1.1 204 No Content\r\n\r\nHTTP/1.1 304 Not Modified\r\n\r\n";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, 27);
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert!(item.body().is_empty());
+
+        track_try_unwrap!(decoder.decode(&input[size..], Eos::new(false)));
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.status_code().as_u16(), 304);
+
+        decoder.set_head_request(true);
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n";
+        let size = track_try_unwrap!(decoder.decode(input, Eos::new(false)));
+        assert_eq!(size, input.len());
+        let item = track_try_unwrap!(decoder.finish_decoding());
+        assert_eq!(item.header().get_field("Content-Length"), Some("3"));
+        assert!(item.body().is_empty());
+
+        // `set_head_request` applies only to a single response
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo";
+        let item = track_try_unwrap!(decoder.decode_exact(input.as_ref()));
+        assert_eq!(item.body(), b"foo");
+    }
+
+    #[test]
+    fn response_builder_works() {
+        let response = track_try_unwrap!(Response::builder()
+            .http_version(HttpVersion::V1_0)
+            .status_code(201)
+            .reason_phrase("Created")
+            .header("Location", "/foo")
+            .build("bar"));
+        assert_eq!(
+            response.to_string(),
+            "HTTP/1.0 201 Created\r\nLocation: /foo\r\n\r\nbar"
+        );
+
+        let response = track_try_unwrap!(Response::builder().build(()));
+        assert_eq!(response.status_code().as_u16(), 200);
+        assert_eq!(response.reason_phrase().as_str(), "OK");
+
+        let e = Response::builder()
+            .reason_phrase("Bad\r\n")
+            .header("Bad Name", "foo")
+            .build(())
+            .err()
+            .unwrap();
+        assert_eq!(*e.kind(), ErrorKind::InvalidInput);
+        assert!(e.to_string().contains("reason_phrase="));
+        assert!(!e.to_string().contains("header="));
+    }
+
+    #[test]
+    fn response_equality_works() {
+        let mut decoder =
+            ResponseDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let decoded = track_try_unwrap!(decoder
+            .decode_exact(b"HTTP/1.1 404 Not Found\r\ncontent-length: 3\r\n\r\nfoo".as_ref()));
+        let built = track_try_unwrap!(Response::builder()
+            .status_code(404)
+            .reason_phrase("Not Found")
+            .header("Content-Length", "3")
+            .build("foo".to_owned()));
+        assert_eq!(decoded, built);
+        assert_eq!(decoded.clone(), decoded);
+
+        let other = track_try_unwrap!(Response::builder()
+            .status_code(404)
+            .reason_phrase("Not found")
+            .header("Content-Length", "3")
+            .build("foo".to_owned()));
+        assert_ne!(decoded, other);
+    }
+
+    #[test]
+    fn response_start_line_setters_work() {
+        let mut decoder =
+            ResponseDecoder::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::default();
+        let mut item = track_try_unwrap!(
+            decoder.decode_exact(b"HTTP/1.0 200 OK\r\nContent-Length: 3\r\n\r\nfoo".as_ref())
+        );
+
+        item.set_http_version(HttpVersion::V1_1);
+        item.set_status_code(StatusCode::new(503).unwrap());
+        item.set_reason_phrase(ReasonPhrase::new("Service Unavailable").unwrap());
+        assert_eq!(item.http_version(), HttpVersion::V1_1);
+        assert_eq!(item.status_code().as_u16(), 503);
+        assert_eq!(item.reason_phrase().as_str(), "Service Unavailable");
+        assert_eq!(item.header().get_field("Content-Length"), Some("3"));
+        assert_eq!(
+            item.to_string(),
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 3\r\n\r\nfoo"
+        );
+
+        item.set_reason_phrase(ReasonPhrase::new("").unwrap());
+        assert_eq!(item.header().get_field("Content-Length"), Some("3"));
+        assert_eq!(
+            item.to_string(),
+            "HTTP/1.1 503 \r\nContent-Length: 3\r\n\r\nfoo"
+        );
+    }
 }
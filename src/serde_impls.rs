@@ -0,0 +1,239 @@
+use serde::de::Error as DeError;
+use serde::ser::{SerializeSeq, SerializeStruct, SerializeTuple};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use {
+    Header, HeaderField, HttpVersion, Method, OwnedHeaderField, OwnedMethod, OwnedReasonPhrase,
+    OwnedRequestTarget, ReasonPhrase, Request, RequestTarget, Response, StatusCode,
+};
+
+impl Serialize for HttpVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+impl<'de> Deserialize<'de> for HttpVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        match s.as_str() {
+            "HTTP/1.0" => Ok(HttpVersion::V1_0),
+            "HTTP/1.1" => Ok(HttpVersion::V1_1),
+            _ => Err(D::Error::custom(format!("Unknown HTTP version: {:?}", s))),
+        }
+    }
+}
+
+impl Serialize for StatusCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.as_u16())
+    }
+}
+impl<'de> Deserialize<'de> for StatusCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = u16::deserialize(deserializer)?;
+        StatusCode::new(code).map_err(D::Error::custom)
+    }
+}
+
+macro_rules! impl_str_serde {
+    ($borrowed:ident, $owned:ident) => {
+        impl<'a> Serialize for $borrowed<'a> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+        impl<'de: 'a, 'a> Deserialize<'de> for $borrowed<'a> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <&'de str>::deserialize(deserializer)?;
+                $borrowed::new(s).map_err(D::Error::custom)
+            }
+        }
+        impl Serialize for $owned {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+        impl<'de> Deserialize<'de> for $owned {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                $owned::new(s).map_err(D::Error::custom)
+            }
+        }
+    };
+}
+impl_str_serde!(Method, OwnedMethod);
+impl_str_serde!(RequestTarget, OwnedRequestTarget);
+impl_str_serde!(ReasonPhrase, OwnedReasonPhrase);
+
+impl<'n, 'v> Serialize for HeaderField<'n, 'v> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(self.name())?;
+        tuple.serialize_element(self.value())?;
+        tuple.end()
+    }
+}
+impl<'de: 'a, 'a> Deserialize<'de> for HeaderField<'a, 'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, value) = <(&'de str, &'de str)>::deserialize(deserializer)?;
+        HeaderField::new(name, value).map_err(D::Error::custom)
+    }
+}
+impl Serialize for OwnedHeaderField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_field().serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for OwnedHeaderField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, value) = <(String, String)>::deserialize(deserializer)?;
+        OwnedHeaderField::new(name, value).map_err(D::Error::custom)
+    }
+}
+
+impl<'a> Serialize for Header<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.fields().len()))?;
+        for field in self.fields() {
+            seq.serialize_element(&field)?;
+        }
+        seq.end()
+    }
+}
+
+impl<T: Serialize> Serialize for Request<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Request", 5)?;
+        s.serialize_field("method", &self.method())?;
+        s.serialize_field("target", &self.request_target())?;
+        s.serialize_field("version", &self.http_version())?;
+        s.serialize_field("header", &self.header())?;
+        s.serialize_field("body", self.body())?;
+        s.end()
+    }
+}
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Request<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Request")]
+        struct Repr<T> {
+            method: OwnedMethod,
+            target: OwnedRequestTarget,
+            version: HttpVersion,
+            header: Vec<OwnedHeaderField>,
+            body: T,
+        }
+
+        let r = Repr::deserialize(deserializer)?;
+        let mut request = Request::new(&r.method, &r.target, r.version, r.body);
+        for field in &r.header {
+            request.header_mut().add_field(field);
+        }
+        Ok(request)
+    }
+}
+
+impl<T: Serialize> Serialize for Response<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Response", 5)?;
+        s.serialize_field("version", &self.http_version())?;
+        s.serialize_field("status", &self.status_code())?;
+        s.serialize_field("reason", &self.reason_phrase())?;
+        s.serialize_field("header", &self.header())?;
+        s.serialize_field("body", self.body())?;
+        s.end()
+    }
+}
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Response<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Response")]
+        struct Repr<T> {
+            version: HttpVersion,
+            status: StatusCode,
+            reason: OwnedReasonPhrase,
+            header: Vec<OwnedHeaderField>,
+            body: T,
+        }
+
+        let r = Repr::deserialize(deserializer)?;
+        let mut response = Response::new(r.version, r.status, &r.reason, r.body);
+        for field in &r.header {
+            response.header_mut().add_field(field);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json;
+
+    use {HttpVersion, OwnedHeaderField, Request, Response, StatusCode};
+
+    #[test]
+    fn request_serde_works() {
+        let request = track_try_unwrap!(Request::builder()
+            .method("POST")
+            .request_target("/foo?bar")
+            .header("Content-Type", "text/plain")
+            .header("X-Foo", "a \"quoted\" value")
+            .build("baz".to_owned()));
+
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            json,
+            r#"{"method":"POST","target":"/foo?bar","version":"HTTP/1.1","header":[["Content-Type","text/plain"],["X-Foo","a \"quoted\" value"]],"body":"baz"}"#
+        );
+
+        let decoded: Request<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, request);
+
+        let invalid = json.replace("POST", "PO ST");
+        assert!(serde_json::from_str::<Request<String>>(&invalid).is_err());
+
+        let invalid = json.replace("X-Foo", "X Foo");
+        assert!(serde_json::from_str::<Request<String>>(&invalid).is_err());
+    }
+
+    #[test]
+    fn response_serde_works() {
+        let response = track_try_unwrap!(Response::builder()
+            .status_code(404)
+            .reason_phrase("Not Found")
+            .header("Content-Length", "0")
+            .build(()));
+
+        let json = serde_json::to_string(&response).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":"HTTP/1.1","status":404,"reason":"Not Found","header":[["Content-Length","0"]],"body":null}"#
+        );
+
+        let decoded: Response<()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, response);
+
+        let invalid = json.replace("404", "1000");
+        assert!(serde_json::from_str::<Response<()>>(&invalid).is_err());
+
+        let invalid = json.replace("HTTP/1.1", "HTTP/2");
+        assert!(serde_json::from_str::<Response<()>>(&invalid).is_err());
+    }
+
+    #[test]
+    fn component_serde_works() {
+        assert_eq!(
+            serde_json::to_string(&HttpVersion::V1_0).unwrap(),
+            r#""HTTP/1.0""#
+        );
+        assert_eq!(
+            serde_json::from_str::<StatusCode>("204").unwrap().as_u16(),
+            204
+        );
+
+        let field: OwnedHeaderField = serde_json::from_str(r#"["Host","example.com"]"#).unwrap();
+        assert_eq!(field.name(), "Host");
+        assert_eq!(field.value(), "example.com");
+        assert!(serde_json::from_str::<OwnedHeaderField>(r#"["Host","a\r\nb"]"#).is_err());
+    }
+}
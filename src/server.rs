@@ -0,0 +1,683 @@
+//! A minimal blocking HTTP/1.1 server.
+//!
+//! `Connection` serves requests arriving on an arbitrary `Read + Write` stream (e.g., `TcpStream`)
+//! by using `RequestDecoder`, and writes the responses made by a handler by using `ResponseEncoder`.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # extern crate bytecodec;
+//! # extern crate httpcodec;
+//! use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+//! use httpcodec::server::Connection;
+//! use httpcodec::{BodyDecoder, BodyEncoder, Response};
+//! use std::net::TcpListener;
+//!
+//! # fn main() {
+//! let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+//! for stream in listener.incoming() {
+//!     let mut connection = Connection::new(
+//!         stream.unwrap(),
+//!         BodyDecoder::for_request(RemainingBytesDecoder::new()),
+//!         BodyEncoder::new(BytesEncoder::new()),
+//!     );
+//!     let result = connection.serve(|request| {
+//!         let body = request.into_body();
+//!         Response::builder().build(body).unwrap()
+//!     });
+//!     if let Err(e) = result {
+//!         eprintln!("{}", e);
+//!     }
+//! }
+//! # }
+//! ```
+use bytecodec::{ByteCount, Decode, Encode, Eos, Error, ErrorKind, Result};
+use std::io::{Read, Write};
+
+use message::HeadPart;
+use util;
+use {
+    BodyDecode, BodyEncode, DecodeOptions, Header, HeaderField, HeaderMut, HttpVersion,
+    ProtocolSwitch, Request, RequestDecoder, Response, ResponseEncoder,
+};
+
+const CONTINUE_RESPONSE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+/// Blocking HTTP/1.1 server that works on a single connection.
+///
+/// Requests are served one by one, so the responses to pipelined requests
+/// are written in the order the requests were received.
+/// The connection is kept alive as long as both the request and the response allow it
+/// (i.e., it is closed after `Connection: close` or an HTTP/1.0 request without `keep-alive`).
+///
+/// If a request has the `Expect: 100-continue` field,
+/// `100 Continue` is sent before reading its body.
+///
+/// A `1xx`, `204 No Content` or `304 Not Modified` response, and a `2xx` response to
+/// a `CONNECT` request are sent without the body and
+/// the `Content-Length` and `Transfer-Encoding` fields.
+///
+/// If a request is malformed or exceeds a size limit, an error response is written
+/// before `serve` returns the error:
+///
+/// - `413 Payload Too Large`: the body exceeded the limit set by `set_max_body_size`
+/// - `414 URI Too Long`: the request-line exceeded `DecodeOptions::max_start_line_size`
+/// - `431 Request Header Fields Too Large`: the header exceeded `DecodeOptions::max_header_size`
+/// - `400 Bad Request`: other decoding errors
+#[derive(Debug)]
+pub struct Connection<S, D, E>
+where
+    D: BodyDecode,
+    E: BodyEncode,
+{
+    stream: S,
+    decoder: RequestDecoder<LimitedBodyDecoder<D>>,
+    encoder: ResponseEncoder<ServerBodyEncoder<E>>,
+    buf: Vec<u8>,
+    eos: bool,
+    in_message: bool,
+}
+impl<S, D, E> Connection<S, D, E>
+where
+    S: Read + Write,
+    D: BodyDecode,
+    E: BodyEncode,
+{
+    /// Makes a new `Connection` instance that serves requests arriving on `stream`.
+    pub fn new(stream: S, body_decoder: D, body_encoder: E) -> Self {
+        Self::with_options(stream, body_decoder, body_encoder, DecodeOptions::default())
+    }
+
+    /// Makes a new `Connection` instance with the given options.
+    pub fn with_options(
+        stream: S,
+        body_decoder: D,
+        body_encoder: E,
+        options: DecodeOptions,
+    ) -> Self {
+        let mut decoder =
+            RequestDecoder::with_options(LimitedBodyDecoder::new(body_decoder), options);
+        decoder.set_expect_continue(true);
+        Connection {
+            stream,
+            decoder,
+            encoder: ResponseEncoder::new(ServerBodyEncoder::new(body_encoder)),
+            buf: Vec::new(),
+            eos: false,
+            in_message: false,
+        }
+    }
+
+    /// Sets the maximum number of bytes allowed for the body part of a request.
+    ///
+    /// The size is measured on the wire (i.e., it includes the chunk framing of a chunked body).
+    ///
+    /// The default value is `None` (unlimited).
+    pub fn set_max_body_size(&mut self, max: Option<u64>) {
+        self.decoder.body_decoder_mut().max = max;
+    }
+
+    /// Serves requests until the connection is closed.
+    ///
+    /// `handler` is called for each request, and the returned response is written to the stream.
+    ///
+    /// This returns `Ok(())` if the peer closed the connection between requests,
+    /// the connection should not be kept alive, or the protocol has been switched.
+    /// The last one happens when the HTTP/2 connection preface is received, when a `101` response
+    /// is returned for an `Upgrade` request, or when a `2xx` response is returned for
+    /// a `CONNECT` request. In that case, the bytes following the request can be retrieved
+    /// by `into_parts` method.
+    pub fn serve<F>(&mut self, mut handler: F) -> Result<()>
+    where
+        F: FnMut(Request<D::Item>) -> Response<E::Item>,
+    {
+        loop {
+            let request = match track!(self.read_request())? {
+                None => return Ok(()),
+                Some(request) => request,
+            };
+            let keep_alive = util::is_keep_alive(request.http_version(), &request.header());
+            let is_head = request.method().as_str() == "HEAD";
+            let protocol_switch = self.decoder.protocol_switch();
+
+            let mut response = handler(request);
+            let status = response.status_code().as_u16();
+            let keep_alive =
+                keep_alive && util::is_keep_alive(response.http_version(), &response.header());
+            if !keep_alive
+                && response.http_version() == HttpVersion::V1_1
+                && !response.header().has_token("Connection", "close")
+            {
+                let field = track!(HeaderField::new("Connection", "close"))?;
+                response.header_mut().add_field(field);
+            }
+            let no_body = status / 100 == 1
+                || status == 204
+                || status == 304
+                || (protocol_switch == Some(ProtocolSwitch::Tunnel) && status / 100 == 2);
+            track!(self.write_response(response, is_head, no_body))?;
+
+            match protocol_switch {
+                Some(ProtocolSwitch::Upgrade) if status == 101 => return Ok(()),
+                Some(ProtocolSwitch::Tunnel) if status / 100 == 2 => return Ok(()),
+                _ => self.decoder.clear_protocol_switch(),
+            }
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn stream_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn stream_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Takes ownership of `Connection` and returns the underlying stream
+    /// and the received bytes that have not been consumed yet.
+    pub fn into_parts(self) -> (S, Vec<u8>) {
+        (self.stream, self.buf)
+    }
+
+    fn read_request(&mut self) -> Result<Option<Request<D::Item>>> {
+        loop {
+            if self.eos && self.buf.is_empty() && !self.in_message {
+                return Ok(None);
+            }
+            track!(self.decode_buffered())?;
+            if self.decoder.protocol_switch() == Some(ProtocolSwitch::Http2Preface) {
+                return Ok(None);
+            }
+            if self.decoder.is_suspended() {
+                let result = match self.decoder.peek_head() {
+                    Some(head) => self.decoder.body_decoder_mut().check_length(&head.header()),
+                    None => Ok(()),
+                };
+                track!(self.handle_decode_result(result))?;
+                track!(self.write_all(CONTINUE_RESPONSE))?;
+                self.decoder.resume();
+                continue;
+            }
+            if self.decoder.is_idle() {
+                let result = self.decoder.finish_decoding();
+                let request = track!(self.handle_decode_result(result))?;
+                self.in_message = false;
+                return Ok(Some(request));
+            }
+            track_assert!(!self.eos, ErrorKind::UnexpectedEos);
+            track!(self.fill_buf())?;
+        }
+    }
+
+    fn decode_buffered(&mut self) -> Result<()> {
+        let result = self.decoder.decode(&self.buf, Eos::new(self.eos));
+        let size = track!(self.handle_decode_result(result))?;
+        if size > 0 {
+            self.in_message = true;
+            self.buf.drain(..size);
+        }
+        Ok(())
+    }
+
+    fn handle_decode_result<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(ref e) = result {
+            if let Some((status, reason)) = self.error_status(e) {
+                let response = format!(
+                    "HTTP/1.1 {} {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status, reason
+                );
+                let _ = self.write_all(response.as_bytes());
+            }
+        }
+        result
+    }
+
+    fn error_status(&self, e: &Error) -> Option<(u16, &'static str)> {
+        if self.decoder.body_decoder_ref().exceeded {
+            return Some((413, "Payload Too Large"));
+        }
+        match self.decoder.exceeded_limit() {
+            Some(HeadPart::StartLine) => Some((414, "URI Too Long")),
+            Some(HeadPart::Header) => Some((431, "Request Header Fields Too Large")),
+            None if *e.kind() == ErrorKind::InvalidInput => Some((400, "Bad Request")),
+            None => None,
+        }
+    }
+
+    fn write_response(
+        &mut self,
+        response: Response<E::Item>,
+        is_head: bool,
+        no_body: bool,
+    ) -> Result<()> {
+        let response = if no_body {
+            without_framing_fields(response)
+        } else {
+            response
+        };
+        self.encoder.body_encoder_mut().discard = is_head;
+        self.encoder.body_encoder_mut().no_body = no_body;
+        track!(self.encoder.start_encoding(response))?;
+        let mut buf = [0; 4096];
+        while !self.encoder.is_idle() {
+            let size = track!(self.encoder.encode(&mut buf, Eos::new(false)))?;
+            track!(self.stream.write_all(&buf[..size]).map_err(Error::from))?;
+        }
+        track!(self.stream.flush().map_err(Error::from))?;
+        Ok(())
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        track!(self.stream.write_all(bytes).map_err(Error::from))?;
+        track!(self.stream.flush().map_err(Error::from))?;
+        Ok(())
+    }
+
+    fn fill_buf(&mut self) -> Result<()> {
+        let mut buf = [0; 4096];
+        let size = track!(self.stream.read(&mut buf).map_err(Error::from))?;
+        if size == 0 {
+            self.eos = true;
+        } else {
+            self.buf.extend_from_slice(&buf[..size]);
+        }
+        Ok(())
+    }
+}
+
+fn without_framing_fields<T>(response: Response<T>) -> Response<T> {
+    let (head, body) = response.take_body();
+    let mut response = Response::new(
+        head.http_version(),
+        head.status_code(),
+        head.reason_phrase(),
+        body,
+    );
+    for field in head.header().fields() {
+        if !field.name().eq_ignore_ascii_case("Content-Length")
+            && !field.name().eq_ignore_ascii_case("Transfer-Encoding")
+        {
+            response.header_mut().add_field(field);
+        }
+    }
+    response
+}
+
+/// A body decoder that rejects bodies larger than the configured limit.
+#[derive(Debug)]
+struct LimitedBodyDecoder<D> {
+    inner: D,
+    max: Option<u64>,
+    consumed: u64,
+    exceeded: bool,
+}
+impl<D: BodyDecode> LimitedBodyDecoder<D> {
+    fn new(inner: D) -> Self {
+        LimitedBodyDecoder {
+            inner,
+            max: None,
+            consumed: 0,
+            exceeded: false,
+        }
+    }
+
+    fn check_length(&mut self, header: &Header) -> Result<()> {
+        let max = match self.max {
+            None => return Ok(()),
+            Some(max) => max,
+        };
+        let length = header
+            .get_field("Content-Length")
+            .and_then(|v| v.parse::<u64>().ok());
+        if let Some(length) = length {
+            if length > max {
+                self.exceeded = true;
+                track_panic!(ErrorKind::InvalidInput, "Too large body"; length, max);
+            }
+        }
+        Ok(())
+    }
+}
+impl<D: BodyDecode> Decode for LimitedBodyDecoder<D> {
+    type Item = D::Item;
+
+    fn decode(&mut self, buf: &[u8], eos: Eos) -> Result<usize> {
+        let size = track!(self.inner.decode(buf, eos))?;
+        self.consumed += size as u64;
+        if let Some(max) = self.max {
+            if self.consumed > max {
+                self.exceeded = true;
+                track_panic!(ErrorKind::InvalidInput, "Too large body"; max);
+            }
+        }
+        Ok(size)
+    }
+
+    fn finish_decoding(&mut self) -> Result<Self::Item> {
+        self.consumed = 0;
+        track!(self.inner.finish_decoding())
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        self.inner.requiring_bytes()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.inner.is_idle()
+    }
+}
+impl<D: BodyDecode> BodyDecode for LimitedBodyDecoder<D> {
+    fn initialize(&mut self, header: &Header) -> Result<()> {
+        self.consumed = 0;
+        track!(self.check_length(header))?;
+        track!(self.inner.initialize(header))
+    }
+    fn skip(&mut self) -> Result<()> {
+        self.consumed = 0;
+        track!(self.inner.skip())
+    }
+}
+
+/// A body encoder that discards the encoded body if `discard` is `true` (i.e., for HEAD requests).
+///
+/// If `no_body` is `true`, the body is dropped without being encoded
+/// and the header is left untouched (i.e., for responses that never have a body).
+#[derive(Debug)]
+struct ServerBodyEncoder<E> {
+    inner: E,
+    discard: bool,
+    no_body: bool,
+}
+impl<E: BodyEncode> ServerBodyEncoder<E> {
+    fn new(inner: E) -> Self {
+        ServerBodyEncoder {
+            inner,
+            discard: false,
+            no_body: false,
+        }
+    }
+}
+impl<E: BodyEncode> Encode for ServerBodyEncoder<E> {
+    type Item = E::Item;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        if self.no_body {
+            return Ok(0);
+        }
+        if !self.discard {
+            return track!(self.inner.encode(buf, eos));
+        }
+        let mut temp_buf = [0; 1024];
+        while !self.inner.is_idle() {
+            track!(self.inner.encode(&mut temp_buf, Eos::new(false)))?;
+        }
+        Ok(0)
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        if self.no_body {
+            return Ok(());
+        }
+        track!(self.inner.start_encoding(item))
+    }
+
+    fn is_idle(&self) -> bool {
+        self.inner.is_idle()
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        if self.discard || self.no_body {
+            ByteCount::Finite(0)
+        } else {
+            self.inner.requiring_bytes()
+        }
+    }
+}
+impl<E: BodyEncode> BodyEncode for ServerBodyEncoder<E> {
+    fn update_header(&self, header: &mut HeaderMut) -> Result<()> {
+        if self.no_body {
+            return Ok(());
+        }
+        self.inner.update_header(header)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+    use std::io::{self, Cursor, Read, Write};
+
+    use super::*;
+    use BodyDecoder;
+    use BodyEncoder;
+
+    struct ScriptedStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+    impl Read for ScriptedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+    impl Write for ScriptedStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    type TestConnection = Connection<
+        ScriptedStream,
+        BodyDecoder<RemainingBytesDecoder>,
+        BodyEncoder<BytesEncoder<Vec<u8>>>,
+    >;
+
+    fn new_connection(input: &[u8], options: DecodeOptions) -> TestConnection {
+        let stream = ScriptedStream {
+            input: Cursor::new(input.to_vec()),
+            output: Vec::new(),
+        };
+        Connection::with_options(
+            stream,
+            BodyDecoder::for_request(RemainingBytesDecoder::new()),
+            BodyEncoder::new(BytesEncoder::new()),
+            options,
+        )
+    }
+
+    fn echo(request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let target = request.request_target().as_str().to_owned();
+        let mut body = target.into_bytes();
+        body.extend_from_slice(request.body());
+        track_try_unwrap!(Response::builder().build(body))
+    }
+
+    fn output(connection: &TestConnection) -> &str {
+        ::std::str::from_utf8(&connection.stream_ref().output).unwrap()
+    }
+
+    #[test]
+    fn connection_works() {
+        let input = concat!(
+            "GET /foo HTTP/1.1\r\n\r\n",
+            "POST /bar HTTP/1.1\r\nContent-Length: 3\r\n\r\nbaz",
+            "HEAD /qux HTTP/1.1\r\n\r\n",
+            "POST /quux HTTP/1.1\r\nContent-Length: 1\r\nExpect: 100-continue\r\n\r\n!"
+        );
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(echo));
+        assert_eq!(
+            output(&connection),
+            concat!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n/foo",
+                "HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\n/barbaz",
+                "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n",
+                "HTTP/1.1 100 Continue\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\n/quux!"
+            )
+        );
+    }
+
+    #[test]
+    fn connection_close_works() {
+        let input = "GET /foo HTTP/1.1\r\nConnection: close\r\n\r\nGET /bar HTTP/1.1\r\n\r\n";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(echo));
+        assert_eq!(
+            output(&connection),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 4\r\n\r\n/foo"
+        );
+
+        let input = "GET /foo HTTP/1.0\r\n\r\nGET /bar HTTP/1.0\r\n\r\n";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(echo));
+        assert_eq!(
+            output(&connection),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 4\r\n\r\n/foo"
+        );
+
+        let input = "GET /foo HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET /bar HTTP/1.0\r\n\r\n";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(echo));
+        assert_eq!(
+            output(&connection),
+            concat!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n/foo",
+                "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 4\r\n\r\n/bar"
+            )
+        );
+
+        // `100 Continue` is never sent to HTTP/1.0 clients
+        let input = "POST /foo HTTP/1.0\r\nContent-Length: 1\r\nExpect: 100-continue\r\n\r\n!";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(echo));
+        assert_eq!(
+            output(&connection),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 5\r\n\r\n/foo!"
+        );
+    }
+
+    #[test]
+    fn connection_error_responses_work() {
+        fn error_response(input: &str, options: DecodeOptions, max_body: Option<u64>) -> String {
+            let mut connection = new_connection(input.as_bytes(), options);
+            connection.set_max_body_size(max_body);
+            assert!(connection.serve(echo).is_err());
+            output(&connection).to_owned()
+        }
+
+        let response = error_response(
+            "GET /foo HTTP/1.1\r\nfoo\r\n\r\n",
+            DecodeOptions::default(),
+            None,
+        );
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let options = DecodeOptions {
+            max_start_line_size: 16,
+            ..DecodeOptions::default()
+        };
+        let response = error_response("GET /foooooooooooo HTTP/1.1\r\n\r\n", options, None);
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+
+        let options = DecodeOptions {
+            max_header_size: 16,
+            ..DecodeOptions::default()
+        };
+        let response = error_response(
+            "GET / HTTP/1.1\r\nFoo: barbazquxquux\r\n\r\n",
+            options,
+            None,
+        );
+        assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        let input = "POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nfoo!";
+        let response = error_response(input, DecodeOptions::default(), Some(3));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let input = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nfoo!\r\n0\r\n\r\n";
+        let response = error_response(input, DecodeOptions::default(), Some(3));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        // `100 Continue` is not sent if the body is known to be too large
+        let input = "POST / HTTP/1.1\r\nContent-Length: 4\r\nExpect: 100-continue\r\n\r\n";
+        let response = error_response(input, DecodeOptions::default(), Some(3));
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        // The responses to the preceding requests are kept
+        let input = "GET /foo HTTP/1.1\r\n\r\nGET /bar HTTP/1.1\r\nfoo\r\n\r\n";
+        let response = error_response(input, DecodeOptions::default(), None);
+        assert_eq!(
+            response,
+            concat!(
+                "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n/foo",
+                "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            )
+        );
+    }
+
+    #[test]
+    fn connection_protocol_switch_works() {
+        let input = "GET / HTTP/1.1\r\nConnection: upgrade\r\nUpgrade: foo\r\n\r\nfoo-data";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(|_| {
+            track_try_unwrap!(Response::builder()
+                .status_code(101)
+                .reason_phrase("Switching Protocols")
+                .header("Connection", "upgrade")
+                .header("Upgrade", "foo")
+                .build(Vec::new()))
+        }));
+        assert_eq!(
+            output(&connection),
+            "HTTP/1.1 101 Switching Protocols\r\nConnection: upgrade\r\nUpgrade: foo\r\n\r\n"
+        );
+        let (mut stream, buf) = connection.into_parts();
+        let mut rest = buf;
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"foo-data");
+
+        // `100 Continue` is not sent for a tunnel
+        let input = "CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\
+                     Expect: 100-continue\r\n\r\nbar-data";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(|_| track_try_unwrap!(Response::builder()
+            .header("Content-Length", "0")
+            .build(Vec::new()))));
+        assert_eq!(output(&connection), "HTTP/1.1 200 OK\r\n\r\n");
+        let (mut stream, buf) = connection.into_parts();
+        let mut rest = buf;
+        stream.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"bar-data");
+    }
+
+    #[test]
+    fn connection_omits_body_for_204_and_304() {
+        let input = "DELETE /foo HTTP/1.1\r\n\r\nGET /foo HTTP/1.1\r\n\r\n";
+        let mut connection = new_connection(input.as_bytes(), DecodeOptions::default());
+        track_try_unwrap!(connection.serve(|request| {
+            let (status, reason) = if request.method().as_str() == "DELETE" {
+                (204, "No Content")
+            } else {
+                (304, "Not Modified")
+            };
+            track_try_unwrap!(Response::builder()
+                .status_code(status)
+                .reason_phrase(reason)
+                .build(b"ignored".to_vec()))
+        }));
+        assert_eq!(
+            output(&connection),
+            "HTTP/1.1 204 No Content\r\n\r\nHTTP/1.1 304 Not Modified\r\n\r\n"
+        );
+    }
+}
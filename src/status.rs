@@ -2,7 +2,7 @@ use bytecodec::bytes::CopyableBytesDecoder;
 use bytecodec::{ByteCount, Decode, Eos, Error, ErrorKind, Result};
 use std;
 use std::fmt;
-use std::str;
+use std::str::{self, FromStr};
 use trackable::error::ErrorKindExt;
 
 use util;
@@ -127,6 +127,64 @@ impl<'a> fmt::Display for ReasonPhrase<'a> {
     }
 }
 
+/// An owned variant of `ReasonPhrase`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OwnedReasonPhrase(String);
+impl OwnedReasonPhrase {
+    /// Makes a new `OwnedReasonPhrase` instance.
+    ///
+    /// # Errors
+    ///
+    /// `phrase` must satisfy the same conditions as `ReasonPhrase::new`.
+    pub fn new<T: Into<String>>(phrase: T) -> Result<Self> {
+        let phrase = phrase.into();
+        track!(ReasonPhrase::new(&phrase))?;
+        Ok(OwnedReasonPhrase(phrase))
+    }
+
+    /// Returns the borrowed variant of the phrase.
+    pub fn as_reason_phrase(&self) -> ReasonPhrase<'_> {
+        ReasonPhrase(&self.0)
+    }
+
+    /// Returns a reference to the phrase string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Takes ownership of the instance, and returns the inner string.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+impl AsRef<str> for OwnedReasonPhrase {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl fmt::Display for OwnedReasonPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+impl FromStr for OwnedReasonPhrase {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        track!(Self::new(s))
+    }
+}
+impl<'a> From<ReasonPhrase<'a>> for OwnedReasonPhrase {
+    fn from(f: ReasonPhrase<'a>) -> Self {
+        OwnedReasonPhrase(f.0.to_owned())
+    }
+}
+impl<'a> From<&'a OwnedReasonPhrase> for ReasonPhrase<'a> {
+    fn from(f: &'a OwnedReasonPhrase) -> Self {
+        f.as_reason_phrase()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ReasonPhraseDecoder {
     size: usize,
@@ -193,6 +251,7 @@ mod test {
     use bytecodec::ErrorKind;
 
     use super::*;
+    use {HttpVersion, Response};
 
     #[test]
     fn status_code_decoder_works() {
@@ -259,6 +318,15 @@ mod test {
             Some(ErrorKind::InvalidInput)
         )
     }
+
+    #[test]
+    fn owned_reason_phrase_works() {
+        let phrase = track_try_unwrap!(OwnedReasonPhrase::new("Not Found"));
+        let response = Response::new(HttpVersion::V1_1, StatusCode(404), &phrase, ());
+        assert_eq!(response.reason_phrase().as_str(), "Not Found");
+        assert_eq!(OwnedReasonPhrase::from(response.reason_phrase()), phrase);
+        assert!(OwnedReasonPhrase::new("foo\r\n").is_err());
+    }
 }
 
 fn into_invalid_input<E: std::error::Error + Send + Sync + 'static>(e: E) -> Error {
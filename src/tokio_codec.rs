@@ -0,0 +1,367 @@
+//! Adapters for `tokio_util::codec`.
+//!
+//! This module is available only if the `tokio-codec` feature is enabled.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate bytecodec;
+//! # extern crate bytes;
+//! # extern crate httpcodec;
+//! # extern crate tokio_util;
+//! use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder};
+//! use bytes::BytesMut;
+//! use httpcodec::tokio_codec::ServerCodec;
+//! use httpcodec::{BodyDecoder, BodyEncoder, HttpVersion, ReasonPhrase, Response, StatusCode};
+//! use tokio_util::codec::{Decoder, Encoder};
+//!
+//! # fn main() {
+//! let mut codec = ServerCodec::<BodyDecoder<RemainingBytesDecoder>, _>::new(
+//!     BodyDecoder::default(),
+//!     BodyEncoder::new(BytesEncoder::new()),
+//! );
+//!
+//! let mut buf = BytesMut::from(&b"GET / HTTP/1.1\r\nContent-Length: 3\r\n\r\nfoo"[..]);
+//! let request = codec.decode(&mut buf).unwrap().unwrap();
+//! assert_eq!(request.body(), b"foo");
+//!
+//! let response = Response::new(
+//!     HttpVersion::V1_1,
+//!     StatusCode::new(200).unwrap(),
+//!     ReasonPhrase::new("OK").unwrap(),
+//!     b"bar",
+//! );
+//! codec.encode(response, &mut buf).unwrap();
+//! assert_eq!(&buf[..], b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nbar");
+//! # }
+//! ```
+use bytecodec::{ByteCount, Encode, Eos, Error, ErrorKind, Result};
+use bytes::BytesMut;
+use std::cmp;
+use tokio_util::codec::{Decoder, Encoder};
+
+use message::{self, MessageEncode, RetainHead};
+use {
+    BodyDecode, BodyEncode, Request, RequestDecoder, RequestEncoder, Response, ResponseDecoder,
+    ResponseEncoder,
+};
+
+/// A server side codec that decodes requests and encodes responses.
+///
+/// The body of a response must be available as a whole when it is encoded.
+/// See `ClientCodec` for details.
+#[derive(Debug)]
+pub struct ServerCodec<D, E> {
+    decoder: CodecDecoder<RequestDecoder<D>>,
+    encoder: ResponseEncoder<E>,
+}
+impl<D: BodyDecode, E: BodyEncode> ServerCodec<D, E> {
+    /// Makes a new `ServerCodec` instance.
+    pub fn new(body_decoder: D, body_encoder: E) -> Self {
+        Self::with_codecs(
+            RequestDecoder::new(body_decoder),
+            ResponseEncoder::new(body_encoder),
+        )
+    }
+
+    /// Makes a new `ServerCodec` instance from the given request decoder and response encoder.
+    pub fn with_codecs(decoder: RequestDecoder<D>, encoder: ResponseEncoder<E>) -> Self {
+        ServerCodec {
+            decoder: CodecDecoder::new(decoder),
+            encoder,
+        }
+    }
+
+    /// Returns a reference to the request decoder.
+    pub fn decoder_ref(&self) -> &RequestDecoder<D> {
+        &self.decoder.inner
+    }
+
+    /// Returns a mutable reference to the request decoder.
+    pub fn decoder_mut(&mut self) -> &mut RequestDecoder<D> {
+        &mut self.decoder.inner
+    }
+
+    /// Returns a reference to the response encoder.
+    pub fn encoder_ref(&self) -> &ResponseEncoder<E> {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the response encoder.
+    pub fn encoder_mut(&mut self) -> &mut ResponseEncoder<E> {
+        &mut self.encoder
+    }
+}
+impl<D: BodyDecode, E: BodyEncode> Decoder for ServerCodec<D, E> {
+    type Item = Request<D::Item>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        track!(self.decoder.decode(src, Eos::new(false)))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        track!(self.decoder.decode(src, Eos::new(true)))
+    }
+}
+impl<D: BodyDecode, E: BodyEncode> Encoder<Response<E::Item>> for ServerCodec<D, E> {
+    type Error = Error;
+
+    fn encode(&mut self, item: Response<E::Item>, dst: &mut BytesMut) -> Result<()> {
+        track!(encode_item(&mut self.encoder, item, dst))
+    }
+}
+
+/// A client side codec that encodes requests and decodes responses.
+///
+/// The body of a request must be available as a whole when it is encoded,
+/// because a codec cannot wait for it.
+/// If the body encoder has no bytes ready (e.g., `StreamingBodyEncoder` waiting for the next chunk)
+/// or holds back the body (see `RequestEncoder::set_expect_continue`),
+/// `encode` fails with an `ErrorKind::Other` error and leaves `dst` unchanged.
+/// The codec cannot encode any more messages after such an error.
+#[derive(Debug)]
+pub struct ClientCodec<E, D> {
+    encoder: RequestEncoder<E>,
+    decoder: CodecDecoder<ResponseDecoder<D>>,
+}
+impl<E: BodyEncode, D: BodyDecode> ClientCodec<E, D> {
+    /// Makes a new `ClientCodec` instance.
+    pub fn new(body_encoder: E, body_decoder: D) -> Self {
+        Self::with_codecs(
+            RequestEncoder::new(body_encoder),
+            ResponseDecoder::new(body_decoder),
+        )
+    }
+
+    /// Makes a new `ClientCodec` instance from the given request encoder and response decoder.
+    pub fn with_codecs(encoder: RequestEncoder<E>, decoder: ResponseDecoder<D>) -> Self {
+        ClientCodec {
+            encoder,
+            decoder: CodecDecoder::new(decoder),
+        }
+    }
+
+    /// Returns a reference to the request encoder.
+    pub fn encoder_ref(&self) -> &RequestEncoder<E> {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the request encoder.
+    pub fn encoder_mut(&mut self) -> &mut RequestEncoder<E> {
+        &mut self.encoder
+    }
+
+    /// Returns a reference to the response decoder.
+    pub fn decoder_ref(&self) -> &ResponseDecoder<D> {
+        &self.decoder.inner
+    }
+
+    /// Returns a mutable reference to the response decoder.
+    pub fn decoder_mut(&mut self) -> &mut ResponseDecoder<D> {
+        &mut self.decoder.inner
+    }
+}
+impl<E: BodyEncode, D: BodyDecode> Encoder<Request<E::Item>> for ClientCodec<E, D> {
+    type Error = Error;
+
+    fn encode(&mut self, item: Request<E::Item>, dst: &mut BytesMut) -> Result<()> {
+        track!(encode_item(&mut self.encoder, item, dst))
+    }
+}
+impl<E: BodyEncode, D: BodyDecode> Decoder for ClientCodec<E, D> {
+    type Item = Response<D::Item>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        track!(self.decoder.decode(src, Eos::new(false)))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>> {
+        track!(self.decoder.decode(src, Eos::new(true)))
+    }
+}
+
+#[derive(Debug)]
+struct CodecDecoder<D> {
+    inner: D,
+    in_progress: bool,
+}
+impl<D: RetainHead> CodecDecoder<D> {
+    fn new(inner: D) -> Self {
+        CodecDecoder {
+            inner,
+            in_progress: false,
+        }
+    }
+
+    fn decode(&mut self, src: &mut BytesMut, eos: Eos) -> Result<Option<D::Item>> {
+        if eos.is_reached() && src.is_empty() && !self.in_progress {
+            // The stream has reached EOS at the boundary of messages
+            return Ok(None);
+        }
+
+        let remaining = src.len();
+        track!(message::decode_bytes(&mut self.inner, src, eos))?;
+        self.in_progress |= src.len() < remaining;
+        if self.inner.is_idle() {
+            let item = track!(self.inner.finish_decoding())?;
+            self.in_progress = false;
+            Ok(Some(item))
+        } else {
+            track_assert!(!eos.is_reached(), ErrorKind::UnexpectedEos);
+            Ok(None)
+        }
+    }
+}
+
+fn encode_item<E: MessageEncode>(encoder: &mut E, item: E::Item, dst: &mut BytesMut) -> Result<()> {
+    let start = dst.len();
+    track!(encoder.start_encoding(item))?;
+    while !encoder.is_idle() {
+        let n = match encoder.requiring_bytes() {
+            ByteCount::Finite(n) => cmp::min(n, 0x10000) as usize,
+            _ => 4096,
+        };
+        let offset = dst.len();
+        dst.resize(offset + n, 0);
+        let size = track!(encoder.encode(&mut dst[offset..], Eos::new(false)))?;
+        dst.truncate(offset + size);
+        if size == 0 {
+            dst.truncate(start);
+            track_assert!(
+                !encoder.is_suspended(),
+                ErrorKind::Other,
+                "The encoder has been suspended"
+            );
+            track_panic!(ErrorKind::Other, "The body is not ready to be encoded");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::bytes::{BytesEncoder, RemainingBytesDecoder, Utf8Decoder};
+    use bytecodec::ErrorKind;
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::*;
+    use {
+        BodyDecoder, BodyEncoder, BodyStream, HttpVersion, Method, ReasonPhrase, RequestTarget,
+        StatusCode, StreamingBodyEncoder,
+    };
+
+    #[test]
+    fn server_codec_works() {
+        let mut codec = ServerCodec::<BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>, _>::new(
+            BodyDecoder::default(),
+            BodyEncoder::new(BytesEncoder::new()),
+        );
+
+        let mut buf = BytesMut::from(&b"GET /foo HTTP/1.1\r\nContent-Length: 3\r\n\r\nbarGET"[..]);
+        let request = track_try_unwrap!(codec.decode(&mut buf)).unwrap();
+        assert_eq!(request.request_target().as_str(), "/foo");
+        assert_eq!(request.body(), "bar");
+        assert_eq!(&buf[..], b"GET");
+
+        // The bytes of an incomplete head part are left in the buffer
+        assert!(track_try_unwrap!(codec.decode(&mut buf)).is_none());
+        assert_eq!(&buf[..], b"GET");
+
+        buf.extend_from_slice(b" /baz HTTP/1.1\r\nContent-Length: 0\r\n\r\n");
+        let request = track_try_unwrap!(codec.decode(&mut buf)).unwrap();
+        assert_eq!(request.request_target().as_str(), "/baz");
+        assert_eq!(
+            request.head_bytes(),
+            &b"GET /baz HTTP/1.1\r\nContent-Length: 0\r\n\r\n"[..]
+        );
+        assert!(buf.is_empty());
+        assert!(track_try_unwrap!(codec.decode_eof(&mut buf)).is_none());
+
+        buf.extend_from_slice(b"GET /qux HTTP/1.1\r\n");
+        assert_eq!(
+            codec.decode_eof(&mut buf).err().map(|e| *e.kind()),
+            Some(ErrorKind::UnexpectedEos)
+        );
+
+        let response = Response::new(
+            HttpVersion::V1_1,
+            StatusCode::new(200).unwrap(),
+            ReasonPhrase::new("OK").unwrap(),
+            vec![b'a'; 10000],
+        );
+        let mut buf = BytesMut::new();
+        track_try_unwrap!(codec.encode(response, &mut buf));
+        assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\nContent-Length: 10000\r\n\r\naaa"));
+        assert_eq!(buf.len(), 42 + 10000);
+
+        // Encodes responses back to back
+        let mut buf = BytesMut::new();
+        for body in &["foo", "barbaz"] {
+            let response = Response::new(
+                HttpVersion::V1_1,
+                StatusCode::new(200).unwrap(),
+                ReasonPhrase::new("OK").unwrap(),
+                body.as_bytes().to_vec(),
+            );
+            track_try_unwrap!(codec.encode(response, &mut buf));
+        }
+        assert_eq!(
+            &buf[..],
+            &b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfoo\
+               HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nbarbaz"[..]
+        );
+    }
+
+    #[test]
+    fn client_codec_works() {
+        let mut codec = ClientCodec::<_, BodyDecoder<Utf8Decoder<RemainingBytesDecoder>>>::new(
+            BodyEncoder::new(BytesEncoder::new()),
+            BodyDecoder::default(),
+        );
+
+        let request = Request::new(
+            Method::new("GET").unwrap(),
+            RequestTarget::new("/").unwrap(),
+            HttpVersion::V1_0,
+            b"",
+        );
+        let mut buf = BytesMut::new();
+        track_try_unwrap!(codec.encode(request, &mut buf));
+        assert_eq!(&buf[..], b"GET / HTTP/1.0\r\nContent-Length: 0\r\n\r\n");
+
+        // A response whose body is delimited by the end of the stream
+        let mut buf = BytesMut::from(&b"HTTP/1.0 200 OK\r\n\r\nfoo"[..]);
+        assert!(track_try_unwrap!(codec.decode(&mut buf)).is_none());
+        buf.extend_from_slice(b"bar");
+        assert!(track_try_unwrap!(codec.decode(&mut buf)).is_none());
+
+        let response = track_try_unwrap!(codec.decode_eof(&mut buf)).unwrap();
+        assert_eq!(response.body(), "foobar");
+        assert!(track_try_unwrap!(codec.decode_eof(&mut buf)).is_none());
+    }
+
+    #[test]
+    fn client_codec_rejects_pending_body() {
+        let mut codec = ClientCodec::<_, BodyDecoder<RemainingBytesDecoder>>::new(
+            StreamingBodyEncoder::new(),
+            BodyDecoder::default(),
+        );
+
+        let (_sender, stream) = BodyStream::channel();
+        let request = Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/").unwrap(),
+            HttpVersion::V1_1,
+            stream,
+        );
+        let mut buf = BytesMut::from(&b"foo"[..]);
+        assert_eq!(
+            codec.encode(request, &mut buf).err().map(|e| *e.kind()),
+            Some(ErrorKind::Other)
+        );
+        assert_eq!(&buf[..], b"foo");
+    }
+}
@@ -0,0 +1,26 @@
+/// Protocol switch that takes place after a HTTP message.
+///
+/// After decoding such a message, the rest of the byte stream is no longer HTTP/1.x.
+/// Decoders never consume the bytes following the message, so they remain in
+/// the caller's buffer and can be passed to the next protocol layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtocolSwitch {
+    /// Switching protocols via the `Upgrade` header field (e.g., WebSocket).
+    ///
+    /// See [RFC 7230 section 6.7](https://tools.ietf.org/html/rfc7230#section-6.7).
+    Upgrade,
+
+    /// Establishing a tunnel by a `CONNECT` request.
+    ///
+    /// See [RFC 7231 section 4.3.6](https://tools.ietf.org/html/rfc7231#section-4.3.6).
+    Tunnel,
+
+    /// Starting HTTP/2 with prior knowledge.
+    ///
+    /// This is reported when the HTTP/2 connection preface (`http2::PREFACE`)
+    /// is received instead of a request.
+    /// Unlike the other variants, the preface itself has been consumed by the decoder.
+    ///
+    /// See [RFC 7540 section 3.4](https://tools.ietf.org/html/rfc7540#section-3.4).
+    Http2Preface,
+}
@@ -1,6 +1,8 @@
 use bytecodec::bytes::CopyableBytesDecoder;
 use bytecodec::{ByteCount, Decode, Eos, ErrorKind, Result};
 
+use {Header, HttpVersion};
+
 #[derive(Debug, Default)]
 pub struct SpaceDecoder(CopyableBytesDecoder<[u8; 1]>);
 impl Decode for SpaceDecoder {
@@ -76,3 +78,11 @@ pub fn is_alpha(b: u8) -> bool {
 pub fn is_vchar(b: u8) -> bool {
     0x21 <= b && b <= 0x7E
 }
+
+// https://tools.ietf.org/html/rfc7230#section-6.3
+pub fn is_keep_alive(version: HttpVersion, header: &Header) -> bool {
+    match version {
+        HttpVersion::V1_0 => header.has_token("Connection", "keep-alive"),
+        HttpVersion::V1_1 => !header.has_token("Connection", "close"),
+    }
+}
@@ -0,0 +1,316 @@
+use bytecodec::{ByteCount, Encode, Eos, Error, ErrorKind, Result, SizedEncode};
+use std::cmp;
+use std::io::{IoSlice, Write};
+
+/// This trait allows for encoding items into a sequence of slices instead of a contiguous buffer.
+///
+/// The slices can be written to a socket at once by using vectored I/O
+/// (e.g., `Write::write_vectored`), so that the payload is not copied.
+///
+/// Note that this and `Encode::encode` method should not be used together for encoding an item.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate bytecodec;
+/// # extern crate httpcodec;
+/// use bytecodec::Encode;
+/// use httpcodec::{BodyEncoder, EncodeVectored, HttpVersion, ReasonPhrase, Response,
+///                 ResponseEncoder, StatusCode, VectoredBytesEncoder};
+/// use std::io::IoSlice;
+///
+/// # fn main() {
+/// let response = Response::new(
+///     HttpVersion::V1_1,
+///     StatusCode::new(200).unwrap(),
+///     ReasonPhrase::new("OK").unwrap(),
+///     vec![b'a'; 1024],
+/// );
+/// let mut encoder = ResponseEncoder::new(BodyEncoder::new(VectoredBytesEncoder::new()));
+/// encoder.start_encoding(response).unwrap();
+///
+/// let mut slices: Vec<IoSlice> = Vec::new();
+/// encoder.pending_slices(&mut slices).unwrap();
+/// assert_eq!(slices.len(), 2);
+/// assert_eq!(&slices[0][..], &b"HTTP/1.1 200 OK\r\nContent-Length: 1024\r\n\r\n"[..]);
+/// assert_eq!(slices[1].len(), 1024);
+///
+/// encoder.consume(41 + 1024).unwrap();
+/// assert!(encoder.is_idle());
+/// # }
+/// ```
+pub trait EncodeVectored: Encode {
+    /// Appends the bytes that the encoder can produce at the moment to `slices`.
+    ///
+    /// The appended bytes must not change until they are consumed by `consume` method.
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()>;
+
+    /// Consumes the first `size` bytes of the slices appended by `pending_slices` method.
+    ///
+    /// # Errors
+    ///
+    /// If `size` exceeds the total length of the pending slices,
+    /// an `ErrorKind::InvalidInput` error will be returned.
+    fn consume(&mut self, size: usize) -> Result<()>;
+
+    /// Writes the pending slices to `writer` by using `Write::write_vectored` method,
+    /// and consumes the written bytes.
+    ///
+    /// This returns the number of the written bytes.
+    fn write_vectored_to<W: Write>(&mut self, writer: &mut W) -> Result<usize>
+    where
+        Self: Sized,
+    {
+        let mut slices = Vec::new();
+        track!(self.pending_slices(&mut slices))?;
+        if slices.is_empty() {
+            return Ok(0);
+        }
+        let size = track!(writer.write_vectored(&slices).map_err(Error::from))?;
+        track!(self.consume(size))?;
+        Ok(size)
+    }
+}
+impl<T: ?Sized + EncodeVectored> EncodeVectored for &mut T {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        (**self).pending_slices(slices)
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        (**self).consume(size)
+    }
+}
+impl<T: ?Sized + EncodeVectored> EncodeVectored for Box<T> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        (**self).pending_slices(slices)
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        (**self).consume(size)
+    }
+}
+
+/// A bytes encoder that supports vectored encoding.
+///
+/// This behaves the same as `bytecodec::bytes::BytesEncoder` except that
+/// it implements `EncodeVectored`.
+#[derive(Debug)]
+pub struct VectoredBytesEncoder<B = Vec<u8>> {
+    bytes: Option<B>,
+    offset: usize,
+}
+impl<B> VectoredBytesEncoder<B> {
+    /// Makes a new `VectoredBytesEncoder` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+impl<B: AsRef<[u8]>> VectoredBytesEncoder<B> {
+    /// Returns the bytes that have not been encoded yet.
+    pub fn remaining_bytes(&self) -> &[u8] {
+        self.bytes
+            .as_ref()
+            .map_or(&[], |b| &b.as_ref()[self.offset..])
+    }
+
+    /// Consumes up to `size` bytes, and returns the number of the consumed bytes.
+    pub(crate) fn consume_up_to(&mut self, size: usize) -> usize {
+        let size = cmp::min(size, self.remaining_bytes().len());
+        self.offset += size;
+        if self.bytes.is_some() && self.remaining_bytes().is_empty() {
+            self.bytes = None;
+        }
+        size
+    }
+}
+impl<B> Default for VectoredBytesEncoder<B> {
+    fn default() -> Self {
+        VectoredBytesEncoder {
+            bytes: None,
+            offset: 0,
+        }
+    }
+}
+impl<B: AsRef<[u8]>> Encode for VectoredBytesEncoder<B> {
+    type Item = B;
+
+    fn encode(&mut self, buf: &mut [u8], eos: Eos) -> Result<usize> {
+        let size = cmp::min(buf.len(), self.remaining_bytes().len());
+        buf[..size].copy_from_slice(&self.remaining_bytes()[..size]);
+        self.consume_up_to(size);
+        if !self.is_idle() {
+            track_assert!(!eos.is_reached(), ErrorKind::UnexpectedEos);
+        }
+        Ok(size)
+    }
+
+    fn start_encoding(&mut self, item: Self::Item) -> Result<()> {
+        track_assert!(self.is_idle(), ErrorKind::EncoderFull);
+        self.bytes = Some(item);
+        self.offset = 0;
+        Ok(())
+    }
+
+    fn requiring_bytes(&self) -> ByteCount {
+        ByteCount::Finite(self.exact_requiring_bytes())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.bytes.is_none()
+    }
+}
+impl<B: AsRef<[u8]>> SizedEncode for VectoredBytesEncoder<B> {
+    fn exact_requiring_bytes(&self) -> u64 {
+        self.remaining_bytes().len() as u64
+    }
+}
+impl<B: AsRef<[u8]>> EncodeVectored for VectoredBytesEncoder<B> {
+    fn pending_slices<'a>(&'a mut self, slices: &mut Vec<IoSlice<'a>>) -> Result<()> {
+        let bytes = self.remaining_bytes();
+        if !bytes.is_empty() {
+            slices.push(IoSlice::new(bytes));
+        }
+        Ok(())
+    }
+
+    fn consume(&mut self, size: usize) -> Result<()> {
+        let consumed = self.consume_up_to(size);
+        track_assert_eq!(consumed, size, ErrorKind::InvalidInput);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::io::IoEncodeExt;
+    use bytecodec::{Encode, EncodeExt};
+    use std::io::{self, IoSlice, Write};
+
+    use super::*;
+    use {
+        BodyEncoder, BodyStream, HeaderField, HttpVersion, Method, Request, RequestEncoder,
+        RequestTarget, StreamingBodyEncoder,
+    };
+
+    struct VectoredSink {
+        buf: Vec<u8>,
+        room: usize,
+        calls: usize,
+    }
+    impl Write for VectoredSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.write_vectored(&[IoSlice::new(buf)])
+        }
+
+        fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+            self.calls += 1;
+            let mut size = 0;
+            for b in bufs {
+                let n = ::std::cmp::min(self.room - size, b.len());
+                self.buf.extend_from_slice(&b[..n]);
+                size += n;
+            }
+            Ok(size)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn request<T>(body: T) -> Request<T> {
+        Request::new(
+            Method::new("PUT").unwrap(),
+            RequestTarget::new("/foo").unwrap(),
+            HttpVersion::V1_1,
+            body,
+        )
+    }
+
+    #[test]
+    fn vectored_bytes_encoder_works() {
+        let mut encoder = VectoredBytesEncoder::with_item(b"foobar").unwrap();
+        let mut slices = Vec::new();
+        track_try_unwrap!(encoder.pending_slices(&mut slices));
+        assert_eq!(slices.len(), 1);
+
+        track_try_unwrap!(encoder.consume(2));
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.encode_all(&mut buf));
+        assert_eq!(buf, b"obar");
+        assert!(encoder.is_idle());
+        assert!(encoder.consume(1).is_err());
+    }
+
+    #[test]
+    fn vectored_request_encoding_works() {
+        let mut encoder = RequestEncoder::new(BodyEncoder::new(VectoredBytesEncoder::new()));
+        track_try_unwrap!(encoder.start_encoding(request(vec![b'a'; 100])));
+
+        let mut sink = VectoredSink {
+            buf: Vec::new(),
+            room: 1000,
+            calls: 0,
+        };
+        while !encoder.is_idle() {
+            track_try_unwrap!(encoder.write_vectored_to(&mut sink));
+        }
+        assert_eq!(sink.calls, 1);
+        assert!(sink
+            .buf
+            .starts_with(b"PUT /foo HTTP/1.1\r\nContent-Length: 100\r\n\r\naaa"));
+        assert_eq!(sink.buf.len(), 42 + 100);
+    }
+
+    #[test]
+    fn vectored_chunked_encoding_works() {
+        let (sender, stream) = BodyStream::channel();
+        let mut encoder = RequestEncoder::new(StreamingBodyEncoder::new());
+        track_try_unwrap!(encoder.start_encoding(request(stream)));
+
+        let mut sink = VectoredSink {
+            buf: Vec::new(),
+            room: 55,
+            calls: 0,
+        };
+        track_try_unwrap!(sender.send(b"foo".to_vec()));
+        track_try_unwrap!(sender.send(vec![b'b'; 20]));
+        track_try_unwrap!(encoder.write_vectored_to(&mut sink));
+        assert_eq!(
+            sink.buf,
+            &b"PUT /foo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo"[..]
+        );
+
+        sink.room = 1000;
+        sender.finish();
+        while !encoder.is_idle() {
+            track_try_unwrap!(encoder.write_vectored_to(&mut sink));
+        }
+        assert!(sink
+            .buf
+            .ends_with(b"foo\r\n14\r\nbbbbbbbbbbbbbbbbbbbb\r\n0\r\n\r\n"));
+        assert_eq!(track_try_unwrap!(encoder.write_vectored_to(&mut sink)), 0);
+    }
+
+    #[test]
+    fn vectored_encoding_with_expect_continue_works() {
+        let mut encoder = RequestEncoder::new(BodyEncoder::new(VectoredBytesEncoder::new()));
+        encoder.set_expect_continue(true);
+        let mut item = request(b"foo");
+        item.header_mut()
+            .add_field(HeaderField::new("Expect", "100-continue").unwrap());
+        track_try_unwrap!(encoder.start_encoding(item));
+
+        let mut buf = Vec::new();
+        track_try_unwrap!(encoder.write_vectored_to(&mut buf));
+        assert!(buf.ends_with(b"\r\n\r\n"));
+        assert_eq!(track_try_unwrap!(encoder.write_vectored_to(&mut buf)), 0);
+        assert!(encoder.is_suspended());
+        assert!(!encoder.is_idle());
+
+        encoder.resume();
+        track_try_unwrap!(encoder.write_vectored_to(&mut buf));
+        assert!(buf.ends_with(b"\r\n\r\nfoo"));
+        assert!(encoder.is_idle());
+    }
+}
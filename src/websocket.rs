@@ -0,0 +1,335 @@
+//! Helpers for the WebSocket opening handshake.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate httpcodec;
+//! use httpcodec::websocket;
+//! use httpcodec::RequestTarget;
+//!
+//! # fn main() {
+//! // Client side
+//! let request = websocket::make_request(
+//!     RequestTarget::new("/chat").unwrap(),
+//!     "server.example.com",
+//!     b"the sample nonce",
+//! ).unwrap();
+//!
+//! // Server side
+//! let response = websocket::make_response(&request).unwrap();
+//! assert_eq!(response.status_code().as_u16(), 101);
+//! assert_eq!(
+//!     response.header().get_field("Sec-WebSocket-Accept"),
+//!     Some("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=")
+//! );
+//!
+//! // Client side
+//! let header = request.header();
+//! let key = header.get_field("Sec-WebSocket-Key").unwrap();
+//! websocket::check_response(&response, key).unwrap();
+//! # }
+//! ```
+//!
+//! # References
+//!
+//! - [RFC 6455] The WebSocket Protocol
+//!
+//! [RFC 6455]: https://tools.ietf.org/html/rfc6455
+use bytecodec::{ErrorKind, Result};
+
+use base64;
+use {
+    HeaderField, HttpVersion, Method, ReasonPhrase, Request, RequestTarget, Response, StatusCode,
+};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Computes the value of the `Sec-WebSocket-Accept` header field from the given key.
+pub fn accept_key(key: &str) -> String {
+    let mut input = Vec::with_capacity(key.len() + GUID.len());
+    input.extend_from_slice(key.as_bytes());
+    input.extend_from_slice(GUID.as_bytes());
+    base64::encode(&sha1(&input), base64::STANDARD, true)
+}
+
+/// Makes a WebSocket opening handshake request.
+///
+/// `nonce` should be randomly selected for each connection.
+/// It is encoded in base64 and used as the value of the `Sec-WebSocket-Key` header field.
+pub fn make_request(target: RequestTarget, host: &str, nonce: &[u8; 16]) -> Result<Request<()>> {
+    let key = base64::encode(nonce, base64::STANDARD, true);
+    let mut request = Request::new(track!(Method::new("GET"))?, target, HttpVersion::V1_1, ());
+    request
+        .header_mut()
+        .add_field(track!(HeaderField::new("Host", host))?)
+        .add_field(track!(HeaderField::new("Upgrade", "websocket"))?)
+        .add_field(track!(HeaderField::new("Connection", "Upgrade"))?)
+        .add_field(track!(HeaderField::new("Sec-WebSocket-Key", &key))?)
+        .add_field(track!(HeaderField::new("Sec-WebSocket-Version", "13"))?);
+    Ok(request)
+}
+
+/// Checks whether the given request is a valid WebSocket opening handshake request.
+///
+/// # Errors
+///
+/// If the request is not a `GET` request over HTTP/1.1 that has `Host`,
+/// `Upgrade: websocket`, `Connection: Upgrade`, `Sec-WebSocket-Version: 13` and
+/// a well-formed `Sec-WebSocket-Key` header fields,
+/// an `ErrorKind::InvalidInput` error will be returned.
+pub fn check_request<T>(request: &Request<T>) -> Result<()> {
+    track_assert_eq!(request.method().as_str(), "GET", ErrorKind::InvalidInput);
+    track_assert_eq!(
+        request.http_version(),
+        HttpVersion::V1_1,
+        ErrorKind::InvalidInput
+    );
+
+    let header = request.header();
+    track_assert!(
+        header.get_field("Host").is_some(),
+        ErrorKind::InvalidInput,
+        "No `Host` header field"
+    );
+    track_assert!(
+        header.has_token("Upgrade", "websocket"),
+        ErrorKind::InvalidInput,
+        "No `Upgrade: websocket` header field"
+    );
+    track_assert!(
+        header.has_token("Connection", "upgrade"),
+        ErrorKind::InvalidInput,
+        "No `Connection: Upgrade` header field"
+    );
+
+    let version = track_assert_some!(
+        header.get_field("Sec-WebSocket-Version"),
+        ErrorKind::InvalidInput
+    );
+    track_assert_eq!(version, "13", ErrorKind::InvalidInput);
+
+    let key = track_assert_some!(
+        header.get_field("Sec-WebSocket-Key"),
+        ErrorKind::InvalidInput
+    );
+    let nonce = track_assert_some!(base64::decode(key, base64::STANDARD, true), ErrorKind::InvalidInput; key);
+    track_assert_eq!(nonce.len(), 16, ErrorKind::InvalidInput; key);
+    Ok(())
+}
+
+/// Makes a `101 Switching Protocols` response to the given WebSocket opening handshake request.
+///
+/// # Errors
+///
+/// If `check_request(request)` fails, the error will be returned.
+pub fn make_response<T>(request: &Request<T>) -> Result<Response<()>> {
+    track!(check_request(request))?;
+    let header = request.header();
+    let key = track_assert_some!(
+        header.get_field("Sec-WebSocket-Key"),
+        ErrorKind::InvalidInput
+    );
+    let accept = accept_key(key);
+    let mut response = Response::new(
+        HttpVersion::V1_1,
+        track!(StatusCode::new(101))?,
+        track!(ReasonPhrase::new("Switching Protocols"))?,
+        (),
+    );
+    response
+        .header_mut()
+        .add_field(track!(HeaderField::new("Upgrade", "websocket"))?)
+        .add_field(track!(HeaderField::new("Connection", "Upgrade"))?)
+        .add_field(track!(HeaderField::new("Sec-WebSocket-Accept", &accept))?);
+    Ok(response)
+}
+
+/// Checks whether the given response accepts the WebSocket opening handshake request
+/// that has `key` as the value of the `Sec-WebSocket-Key` header field.
+///
+/// # Errors
+///
+/// If the response is not a `101 Switching Protocols` response that has
+/// `Upgrade: websocket`, `Connection: Upgrade` and the expected `Sec-WebSocket-Accept` header fields,
+/// an `ErrorKind::InvalidInput` error will be returned.
+pub fn check_response<T>(response: &Response<T>, key: &str) -> Result<()> {
+    track_assert_eq!(
+        response.status_code().as_u16(),
+        101,
+        ErrorKind::InvalidInput
+    );
+
+    let header = response.header();
+    track_assert!(
+        header.has_token("Upgrade", "websocket"),
+        ErrorKind::InvalidInput,
+        "No `Upgrade: websocket` header field"
+    );
+    track_assert!(
+        header.has_token("Connection", "upgrade"),
+        ErrorKind::InvalidInput,
+        "No `Connection: Upgrade` header field"
+    );
+
+    let accept = track_assert_some!(
+        header.get_field("Sec-WebSocket-Accept"),
+        ErrorKind::InvalidInput
+    );
+    track_assert_eq!(accept, accept_key(key), ErrorKind::InvalidInput);
+    Ok(())
+}
+
+// https://tools.ietf.org/html/rfc3174
+fn sha1(input: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(input.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (w, b) in w.iter_mut().zip(block.chunks(4)) {
+            *w = u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let (mut a, mut b, mut c, mut d, mut e) = (h[0], h[1], h[2], h[3], h[4]);
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, x) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*x);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (d, h) in digest.chunks_mut(4).zip(&h) {
+        d.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use bytecodec::ErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn accept_key_works() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn handshake_works() {
+        let request = track_try_unwrap!(make_request(
+            RequestTarget::new("/chat").unwrap(),
+            "server.example.com",
+            b"the sample nonce"
+        ));
+        let request = request.map_body(|()| "");
+        assert_eq!(
+            request.to_string(),
+            "GET /chat HTTP/1.1\r\n\
+             Host: server.example.com\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Version: 13\r\n\
+             \r\n"
+        );
+
+        let response = track_try_unwrap!(make_response(&request));
+        let response = response.map_body(|()| "");
+        assert_eq!(
+            response.to_string(),
+            "HTTP/1.1 101 Switching Protocols\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+             \r\n"
+        );
+        track_try_unwrap!(check_response(&response, "dGhlIHNhbXBsZSBub25jZQ=="));
+
+        assert_eq!(
+            check_response(&response, "AQIDBAUGBwgJCgsMDQ4PEA==")
+                .err()
+                .map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn check_request_works() {
+        let mut request = Request::new(
+            Method::new("GET").unwrap(),
+            RequestTarget::new("/chat").unwrap(),
+            HttpVersion::V1_1,
+            (),
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Host", "server.example.com").unwrap())
+            .add_field(HeaderField::new("Upgrade", "websocket").unwrap())
+            .add_field(HeaderField::new("Connection", "keep-alive,Upgrade").unwrap())
+            .add_field(HeaderField::new("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ").unwrap())
+            .add_field(HeaderField::new("Sec-WebSocket-Version", "13").unwrap());
+        assert_eq!(
+            check_request(&request).err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+
+        let mut request = Request::new(
+            Method::new("GET").unwrap(),
+            RequestTarget::new("/chat").unwrap(),
+            HttpVersion::V1_1,
+            (),
+        );
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Upgrade", "websocket").unwrap())
+            .add_field(HeaderField::new("Connection", "keep-alive,Upgrade").unwrap())
+            .add_field(HeaderField::new("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==").unwrap())
+            .add_field(HeaderField::new("Sec-WebSocket-Version", "13").unwrap());
+        assert_eq!(
+            check_request(&request).err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidInput)
+        );
+
+        request
+            .header_mut()
+            .add_field(HeaderField::new("Host", "server.example.com").unwrap());
+        track_try_unwrap!(check_request(&request));
+    }
+}